@@ -0,0 +1,511 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! XML attributes of the tags in lhe files
+//!
+//! Version 3 of the lhe standard allows attributes on the
+//! `<LesHouchesEvents>`, `<init>` and `<event>` tags, e.g.
+//! `<event npLO="2" npNLO="1">`.
+//! These are stored in an [`Attributes`] map in `LheFileGeneric`,
+//! `InitGeneric` and `EventGeneric`, which keeps the order in which the
+//! attributes appear in the file, so that they are written in the same
+//! order again.
+//!
+//! The entities `&amp;`, `&lt;`, `&gt;`, `&quot;` and `&apos;` in
+//! attribute values are replaced by the characters they stand for when
+//! reading and the characters `&`, `<` and `"` are escaped again when
+//! writing.
+//!
+//! [`Attributes`]: struct.Attributes.html
+
+use error::{ErrorLocator, ParseError};
+
+use nom;
+use std::io;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::Range;
+use std::slice;
+use std::str;
+
+#[cfg(test)]
+use quickcheck::Arbitrary;
+#[cfg(test)]
+use quickcheck::Gen;
+
+/// An ordered map of XML attributes
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::string::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event npLO=\"2\" npNLO='1'>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+/// let attributes = &lhe.events[0].attributes;
+/// assert_eq!(attributes.get("npLO"), Some("2"));
+/// assert_eq!(attributes.get("npNLO"), Some("1"));
+/// assert_eq!(attributes.get("ntries"), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(test, derive(Serialize, Deserialize))]
+pub struct Attributes {
+    entries: Vec<(String, String)>,
+}
+
+impl Attributes {
+    /// Create an empty attribute map
+    pub fn new() -> Attributes {
+        Attributes::default()
+    }
+
+    /// The value of the attribute `name`, if present
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.0 == name)
+            .map(|entry| entry.1.as_str())
+    }
+
+    /// Set the value of the attribute `name`
+    ///
+    /// If the attribute is already present, its value is replaced and
+    /// the old value is returned, otherwise the attribute is added at
+    /// the end.
+    pub fn insert<N: Into<String>, V: Into<String>>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> Option<String> {
+        let name = name.into();
+        let value = value.into();
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.0 == name) {
+            return Some(mem::replace(&mut entry.1, value));
+        }
+        self.entries.push((name, value));
+        None
+    }
+
+    /// Remove the attribute `name`, returning its value
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let position = self.entries.iter().position(|entry| entry.0 == name)?;
+        Some(self.entries.remove(position).1)
+    }
+
+    /// The number of attributes
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether there are no attributes
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the names and values of the attributes, in order
+    pub fn iter(&self) -> slice::Iter<'_, (String, String)> {
+        self.entries.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Attributes {
+    type Item = &'a (String, String);
+    type IntoIter = slice::Iter<'a, (String, String)>;
+
+    fn into_iter(self) -> slice::Iter<'a, (String, String)> {
+        self.iter()
+    }
+}
+
+impl FromIterator<(String, String)> for Attributes {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Attributes {
+        let mut attributes = Attributes::new();
+        attributes.extend(iter);
+        attributes
+    }
+}
+
+impl Extend<(String, String)> for Attributes {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        for (name, value) in iter {
+            self.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+impl Arbitrary for Attributes {
+    fn arbitrary<G: Gen>(gen: &mut G) -> Attributes {
+        let entries: Vec<(String, String)> = Arbitrary::arbitrary(gen);
+        entries
+            .into_iter()
+            .filter(|entry| is_name(&entry.0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(is_name_char)
+}
+
+fn is_name_char(c: u8) -> bool {
+    nom::is_alphanumeric(c) || c == b'_' || c == b'-' || c == b':' || c == b'.'
+}
+
+/// Read an opening tag with the given name and its attributes
+///
+/// This accepts e.g. `<event>` and `<event npLO="2">` for the name
+/// `event`, but not `<eventgroup>`.
+pub(crate) fn read_opening_tag<'a>(
+    input: &'a [u8],
+    name: &str,
+) -> nom::IResult<&'a [u8], Attributes> {
+    do_parse!(
+        input,
+        ws!(tag!("<")) >> tag!(name) >> attributes: read_attributes >> ws!(tag!(">"))
+            >> (attributes)
+    )
+}
+
+fn read_attributes(input: &[u8]) -> nom::IResult<&[u8], Attributes> {
+    map!(input, many0!(ws!(read_attribute)), |entries| entries
+        .into_iter()
+        .collect())
+}
+
+fn read_attribute(input: &[u8]) -> nom::IResult<&[u8], (String, String)> {
+    do_parse!(
+        input,
+        name: map_res!(take_while1!(is_name_char), str::from_utf8) >> ws!(tag!("="))
+            >> value:
+                map_res!(
+                    alt!(
+                        delimited!(tag!("\""), take_until!("\""), tag!("\""))
+                            | delimited!(tag!("'"), take_until!("'"), tag!("'"))
+                    ),
+                    str::from_utf8
+                ) >> ((name.to_string(), unescape(value)))
+    )
+}
+
+/// Walk over an opening tag with the given name and its attributes to
+/// find the first error
+pub(crate) fn diagnose_opening_tag(
+    locator: &mut ErrorLocator,
+    name: &str,
+) -> Result<Attributes, ParseError> {
+    locator.skip_whitespace();
+    let tag = format!("<{}", name);
+    if !locator.remaining().starts_with(tag.as_bytes()) {
+        return Err(locator.error(None, &format!("`<{}>`", name)));
+    }
+    locator.advance(tag.len());
+    let mut attributes = Attributes::new();
+    loop {
+        locator.skip_whitespace();
+        if locator.remaining().starts_with(b">") {
+            locator.advance(1);
+            return Ok(attributes);
+        }
+        match locator.parsed(read_attribute) {
+            Some((name, value)) => {
+                attributes.insert(name, value);
+            }
+            None => return Err(locator.error(None, "an attribute or `>`")),
+        }
+    }
+}
+
+/// An XML element in the additional information of an lhe file
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Element<'a> {
+    pub name: &'a str,
+    pub attributes: Attributes,
+    pub content: &'a str,
+}
+
+/// Split `content` into the elements with one of the given names and
+/// the remaining text
+///
+/// The remaining text consists of everything outside of these elements,
+/// with each piece trimmed and the pieces separated by newlines.
+/// Elements in comments and `CDATA` sections are not split off.
+/// Returns `None` if one of the elements is malformed, e.g. if it lacks
+/// its closing tag.
+pub(crate) fn split_elements<'a>(
+    content: &'a str,
+    names: &[&'a str],
+) -> Option<(Vec<Element<'a>>, String)> {
+    let mut elements = Vec::new();
+    let mut rest = String::new();
+    let mut position = 0;
+    for (range, element) in find_elements(content, names)? {
+        push_text(&mut rest, &content[position..range.start]);
+        elements.push(element);
+        position = range.end;
+    }
+    push_text(&mut rest, &content[position..]);
+    Some((elements, rest))
+}
+
+/// Find the elements with one of the given names in `content`, together
+/// with their positions
+///
+/// Elements in comments and `CDATA` sections are skipped.
+/// Returns `None` if one of the elements is malformed, e.g. if it lacks
+/// its closing tag.
+pub(crate) fn find_elements<'a>(
+    content: &'a str,
+    names: &[&'a str],
+) -> Option<Vec<(Range<usize>, Element<'a>)>> {
+    let mut elements = Vec::new();
+    let mut search = 0;
+    while let Some(start) = content[search..].find('<').map(|n| n + search) {
+        let skipped = [("<!--", "-->"), ("<![CDATA[", "]]>")]
+            .iter()
+            .find(|&&(open, _)| content[start..].starts_with(open));
+        if let Some(&(_, close)) = skipped {
+            match content[start..].find(close) {
+                Some(end) => search = start + end + close.len(),
+                None => break,
+            }
+            continue;
+        }
+        let after = &content[start + 1..];
+        let name = names.iter().find(|name| {
+            after.starts_with(*name)
+                && after[name.len()..]
+                    .starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        });
+        match name {
+            Some(name) => {
+                let (element, len) = read_element(&content[start..], name)?;
+                elements.push((start..start + len, element));
+                search = start + len;
+            }
+            None => search = start + 1,
+        }
+    }
+    Some(elements)
+}
+
+/// Read the element with the given name at the start of `input`,
+/// returning it and its length
+pub(crate) fn read_element<'a>(input: &'a str, name: &'a str) -> Option<(Element<'a>, usize)> {
+    let after_name = &input[1 + name.len()..];
+    let (rest, attributes) = match read_attributes(after_name.as_bytes()) {
+        nom::IResult::Done(rest, attributes) => (rest.len(), attributes),
+        _ => return None,
+    };
+    let rest = after_name[after_name.len() - rest..].trim_start();
+    let (content, after) = if let Some(after) = rest.strip_prefix("/>") {
+        ("", after)
+    } else if let Some(rest) = rest.strip_prefix('>') {
+        let closing = format!("</{}", name);
+        let end = rest.find(&closing)?;
+        let after = rest[end + closing.len()..].trim_start();
+        (&rest[..end], after.strip_prefix('>')?)
+    } else {
+        return None;
+    };
+    let element = Element {
+        name,
+        attributes,
+        content,
+    };
+    Some((element, input.len() - after.len()))
+}
+
+/// Append the trimmed `piece` to `text`, separated by a newline
+pub(crate) fn push_text(text: &mut String, piece: &str) {
+    let piece = piece.trim();
+    if piece.is_empty() {
+        return;
+    }
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(piece);
+}
+
+/// Write an element with the given name, attributes and content on a
+/// single line
+pub(crate) fn write_element<W: io::Write>(
+    writer: &mut W,
+    name: &str,
+    attributes: &Attributes,
+    content: &str,
+) -> io::Result<()> {
+    write!(writer, "<{}", name)?;
+    write_attributes(writer, attributes)?;
+    writeln!(writer, ">{}</{}>", content, name)
+}
+
+/// Write an opening tag with the given name and attributes
+pub(crate) fn write_opening_tag<W: io::Write>(
+    writer: &mut W,
+    name: &str,
+    attributes: &Attributes,
+) -> io::Result<()> {
+    write!(writer, "<{}", name)?;
+    write_attributes(writer, attributes)?;
+    writeln!(writer, ">")
+}
+
+fn write_attributes<W: io::Write>(writer: &mut W, attributes: &Attributes) -> io::Result<()> {
+    for (name, value) in attributes {
+        write!(writer, " {}=\"{}\"", name, escape(value))?;
+    }
+    Ok(())
+}
+
+fn unescape(value: &str) -> String {
+    const ENTITIES: &[(&str, char)] = &[
+        ("&amp;", '&'),
+        ("&lt;", '<'),
+        ("&gt;", '>'),
+        ("&quot;", '"'),
+        ("&apos;", '\''),
+    ];
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match ENTITIES.iter().find(|entity| rest.starts_with(entity.0)) {
+            Some(&(entity, c)) => {
+                result.push(c);
+                rest = &rest[entity.len()..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        escape, read_opening_tag, split_elements, unescape, write_opening_tag, Attributes,
+    };
+
+    use nom;
+
+    #[test]
+    fn read_tag() {
+        let (rest, attributes) =
+            read_opening_tag(b"<event npLO=\"2\" npNLO = '1' >\n1 2", "event").unwrap();
+        assert_eq!(rest, b"1 2");
+        let expected: Attributes = vec![
+            ("npLO".to_string(), "2".to_string()),
+            ("npNLO".to_string(), "1".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(attributes, expected);
+    }
+
+    #[test]
+    fn read_tag_without_attributes() {
+        let (rest, attributes) = read_opening_tag(b"<init>\n", "init").unwrap();
+        assert_eq!(rest, b"");
+        assert!(attributes.is_empty());
+    }
+
+    #[test]
+    fn reject_other_tags() {
+        match read_opening_tag(b"<eventgroup>\n", "event") {
+            nom::IResult::Error(_) => {}
+            _ => panic!("Expected an error"),
+        }
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            unescape("a &lt;b&gt; &amp;amp; &quot;&apos; &x"),
+            "a <b> &amp; \"' &x"
+        );
+        let value = "a <b> & \"c\"";
+        assert_eq!(unescape(&escape(value)), value);
+    }
+
+    #[test]
+    fn insert_keeps_order() {
+        let mut attributes = Attributes::new();
+        attributes.insert("b", "1");
+        attributes.insert("a", "2");
+        assert_eq!(attributes.insert("b", "3"), Some("1".to_string()));
+        let names: Vec<_> = attributes.iter().map(|entry| entry.0.as_str()).collect();
+        assert_eq!(names, vec!["b", "a"]);
+        assert_eq!(attributes.remove("b"), Some("3".to_string()));
+        assert_eq!(attributes.len(), 1);
+    }
+
+    #[test]
+    fn split() {
+        let content = "\
+text <weights> 1 2 </weights>
+<weight id='a'>x</weight> <weightx> <scales muf=\"2\"/> more";
+        let (elements, rest) = split_elements(content, &["weight", "weights", "scales"]).unwrap();
+        let names: Vec<_> = elements.iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["weights", "weight", "scales"]);
+        assert_eq!(elements[0].content, " 1 2 ");
+        assert_eq!(elements[1].attributes.get("id"), Some("a"));
+        assert_eq!(elements[2].content, "");
+        assert_eq!(rest, "text\n<weightx>\nmore");
+
+        assert_eq!(split_elements("<weight id='a'>x", &["weight"]), None);
+
+        let (elements, rest) =
+            split_elements("<!-- <scales/> --> <scales/>", &["scales"]).unwrap();
+        assert_eq!(elements.len(), 1);
+        assert_eq!(rest, "<!-- <scales/> -->");
+
+        let (elements, rest) =
+            split_elements("<![CDATA[ <scales/> ]]> <scales/>", &["scales"]).unwrap();
+        assert_eq!(elements.len(), 1);
+        assert_eq!(rest, "<![CDATA[ <scales/> ]]>");
+    }
+
+    quickcheck! {
+        fn roundtrip_qc(attributes: Attributes) -> bool {
+            let mut bytes = Vec::new();
+            write_opening_tag(&mut bytes, "event", &attributes).unwrap();
+            match read_opening_tag(&bytes, "event") {
+                nom::IResult::Done(rest, round) => rest.is_empty() && round == attributes,
+                _ => false,
+            }
+        }
+    }
+}
@@ -0,0 +1,539 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Transparent (de)compression of lhe files
+//!
+//! All functions in this library that read or write files, i.e.
+//! `ReadLhe::read_lhe_from_file`, `WriteLhe::write_lhe_to_file`,
+//! `LheReader::from_file` and `LheWriter::create`, go through this
+//! module.
+//!
+//! When reading, the compression format is detected from the magic
+//! bytes at the start of the file, falling back to the extension of the
+//! file name.
+//! When writing, the compression format is chosen based on the
+//! extension of the file name:
+//!
+//! | Format | Extension | Cargo feature |
+//! |--------|-----------|---------------|
+//! | gzip   | `.gz`     | `gzip`        |
+//! | xz     | `.xz`     | `xz`          |
+//! | zstd   | `.zst`    | `zstd`        |
+//!
+//! Each format is only supported if the corresponding feature of this
+//! crate is enabled.
+//! Trying to read or write a compressed file without the feature
+//! results in an `io::Error`.
+
+#[cfg(feature = "gzip")]
+use flate2;
+#[cfg(feature = "xz")]
+use xz2;
+#[cfg(feature = "zstd")]
+use zstd;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Seek};
+use std::path::Path;
+
+/// The compression formats that are recognized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// An uncompressed text file
+    Uncompressed,
+    /// gzip compression
+    Gzip,
+    /// xz compression
+    Xz,
+    /// zstd compression
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression format from the first bytes of a file
+    ///
+    /// Returns `None` if the bytes don't match any known format.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lhef::compression::Compression;
+    ///
+    /// assert_eq!(
+    ///     Compression::from_magic_bytes(&[0x1f, 0x8b, 0x08, 0x00]),
+    ///     Some(Compression::Gzip)
+    /// );
+    /// assert_eq!(Compression::from_magic_bytes(b"<LesHouchesEvents"), None);
+    /// ```
+    pub fn from_magic_bytes(bytes: &[u8]) -> Option<Compression> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Determine the compression format from the extension of a file name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lhef::compression::Compression;
+    ///
+    /// assert_eq!(Compression::from_path(&"events.lhe.gz"), Compression::Gzip);
+    /// assert_eq!(Compression::from_path(&"events.lhe"), Compression::Uncompressed);
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: &P) -> Compression {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("xz") => Compression::Xz,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::Uncompressed,
+        }
+    }
+}
+
+/// A reader that decompresses a file on the fly
+///
+/// Created by [`open`].
+///
+/// [`open`]: fn.open.html
+pub struct Reader {
+    inner: ReaderInner,
+}
+
+enum ReaderInner {
+    Uncompressed(io::BufReader<fs::File>),
+    #[cfg(feature = "gzip")]
+    Gzip(io::BufReader<flate2::bufread::MultiGzDecoder<io::BufReader<fs::File>>>),
+    #[cfg(feature = "xz")]
+    Xz(io::BufReader<xz2::bufread::XzDecoder<io::BufReader<fs::File>>>),
+    #[cfg(feature = "zstd")]
+    Zstd(io::BufReader<zstd::stream::read::Decoder<io::BufReader<fs::File>>>),
+}
+
+impl Reader {
+    /// The compression format of the file
+    pub fn compression(&self) -> Compression {
+        match self.inner {
+            ReaderInner::Uncompressed(_) => Compression::Uncompressed,
+            #[cfg(feature = "gzip")]
+            ReaderInner::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "xz")]
+            ReaderInner::Xz(_) => Compression::Xz,
+            #[cfg(feature = "zstd")]
+            ReaderInner::Zstd(_) => Compression::Zstd,
+        }
+    }
+
+    /// Skip the next `n` bytes of the (decompressed) content
+    ///
+    /// For uncompressed files this seeks in the file, while compressed
+    /// files have to be decompressed up to the new position.
+    pub fn skip(&mut self, n: u64) -> io::Result<()> {
+        match self.inner {
+            ReaderInner::Uncompressed(ref mut r) => {
+                let position = r.stream_position()?;
+                let length = r.get_ref().metadata()?.len();
+                match position.checked_add(n) {
+                    Some(target) if target <= length => {
+                        r.seek(io::SeekFrom::Start(target))?;
+                        Ok(())
+                    }
+                    _ => Err(skipped_past_end()),
+                }
+            }
+            #[cfg(any(feature = "gzip", feature = "xz", feature = "zstd"))]
+            _ => {
+                let skipped = io::copy(&mut io::Read::take(&mut *self, n), &mut io::sink())?;
+                if skipped < n {
+                    return Err(skipped_past_end());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn skipped_past_end() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "tried to skip past the end of the file",
+    )
+}
+
+impl fmt::Debug for Reader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reader")
+            .field("compression", &self.compression())
+            .finish()
+    }
+}
+
+impl io::Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner {
+            ReaderInner::Uncompressed(ref mut r) => r.read(buf),
+            #[cfg(feature = "gzip")]
+            ReaderInner::Gzip(ref mut r) => r.read(buf),
+            #[cfg(feature = "xz")]
+            ReaderInner::Xz(ref mut r) => r.read(buf),
+            #[cfg(feature = "zstd")]
+            ReaderInner::Zstd(ref mut r) => r.read(buf),
+        }
+    }
+}
+
+impl io::BufRead for Reader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.inner {
+            ReaderInner::Uncompressed(ref mut r) => r.fill_buf(),
+            #[cfg(feature = "gzip")]
+            ReaderInner::Gzip(ref mut r) => r.fill_buf(),
+            #[cfg(feature = "xz")]
+            ReaderInner::Xz(ref mut r) => r.fill_buf(),
+            #[cfg(feature = "zstd")]
+            ReaderInner::Zstd(ref mut r) => r.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self.inner {
+            ReaderInner::Uncompressed(ref mut r) => r.consume(amt),
+            #[cfg(feature = "gzip")]
+            ReaderInner::Gzip(ref mut r) => r.consume(amt),
+            #[cfg(feature = "xz")]
+            ReaderInner::Xz(ref mut r) => r.consume(amt),
+            #[cfg(feature = "zstd")]
+            ReaderInner::Zstd(ref mut r) => r.consume(amt),
+        }
+    }
+}
+
+/// Open a file for reading, decompressing it if necessary
+///
+/// The compression format is detected from the magic bytes at the
+/// start of the file.
+/// If they don't match any known format, the extension of the file
+/// name is used instead.
+pub fn open<P: AsRef<Path>>(path: &P) -> io::Result<Reader> {
+    let mut file = io::BufReader::new(fs::File::open(path)?);
+    let compression = {
+        let start = file.fill_buf()?;
+        Compression::from_magic_bytes(start).unwrap_or_else(|| Compression::from_path(path))
+    };
+    let inner = match compression {
+        Compression::Uncompressed => ReaderInner::Uncompressed(file),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => ReaderInner::Gzip(io::BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(file),
+        )),
+        #[cfg(feature = "xz")]
+        Compression::Xz => ReaderInner::Xz(io::BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(file),
+        )),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => ReaderInner::Zstd(io::BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(file)?,
+        )),
+        #[cfg(not(feature = "gzip"))]
+        Compression::Gzip => return Err(unsupported("gzip")),
+        #[cfg(not(feature = "xz"))]
+        Compression::Xz => return Err(unsupported("xz")),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => return Err(unsupported("zstd")),
+    };
+    Ok(Reader { inner })
+}
+
+/// A writer that compresses a file on the fly
+///
+/// Created by [`create`].
+/// The compressed stream is completed when the writer is dropped, but
+/// any errors that occur at that point are ignored.
+/// To handle them, call [`finish`] instead.
+///
+/// [`create`]: fn.create.html
+/// [`finish`]: #method.finish
+pub struct Writer {
+    inner: Option<WriterInner>,
+}
+
+enum WriterInner {
+    Uncompressed(io::BufWriter<fs::File>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<io::BufWriter<fs::File>>),
+    #[cfg(feature = "xz")]
+    Xz(xz2::write::XzEncoder<io::BufWriter<fs::File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<io::BufWriter<fs::File>>),
+}
+
+impl WriterInner {
+    fn compression(&self) -> Compression {
+        match *self {
+            WriterInner::Uncompressed(_) => Compression::Uncompressed,
+            #[cfg(feature = "gzip")]
+            WriterInner::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "xz")]
+            WriterInner::Xz(_) => Compression::Xz,
+            #[cfg(feature = "zstd")]
+            WriterInner::Zstd(_) => Compression::Zstd,
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            WriterInner::Uncompressed(mut w) => io::Write::flush(&mut w),
+            #[cfg(feature = "gzip")]
+            WriterInner::Gzip(w) => io::Write::flush(&mut w.finish()?),
+            #[cfg(feature = "xz")]
+            WriterInner::Xz(w) => io::Write::flush(&mut w.finish()?),
+            #[cfg(feature = "zstd")]
+            WriterInner::Zstd(w) => io::Write::flush(&mut w.finish()?),
+        }
+    }
+}
+
+impl Writer {
+    /// The compression format of the file
+    pub fn compression(&self) -> Compression {
+        self.inner().compression()
+    }
+
+    /// Complete the compressed stream and flush the file
+    pub fn finish(mut self) -> io::Result<()> {
+        self.inner
+            .take()
+            .expect("BUG: Writer used after finish")
+            .finish()
+    }
+
+    fn inner(&self) -> &WriterInner {
+        self.inner.as_ref().expect("BUG: Writer used after finish")
+    }
+
+    fn inner_mut(&mut self) -> &mut WriterInner {
+        self.inner.as_mut().expect("BUG: Writer used after finish")
+    }
+}
+
+impl fmt::Debug for Writer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Writer")
+            .field("compression", &self.compression())
+            .finish()
+    }
+}
+
+impl io::Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self.inner_mut() {
+            WriterInner::Uncompressed(ref mut w) => w.write(buf),
+            #[cfg(feature = "gzip")]
+            WriterInner::Gzip(ref mut w) => w.write(buf),
+            #[cfg(feature = "xz")]
+            WriterInner::Xz(ref mut w) => w.write(buf),
+            #[cfg(feature = "zstd")]
+            WriterInner::Zstd(ref mut w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self.inner_mut() {
+            WriterInner::Uncompressed(ref mut w) => w.flush(),
+            #[cfg(feature = "gzip")]
+            WriterInner::Gzip(ref mut w) => w.flush(),
+            #[cfg(feature = "xz")]
+            WriterInner::Xz(ref mut w) => w.flush(),
+            #[cfg(feature = "zstd")]
+            WriterInner::Zstd(ref mut w) => w.flush(),
+        }
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let _ = inner.finish();
+        }
+    }
+}
+
+/// Create a file for writing, compressing it if necessary
+///
+/// The compression format is chosen from the extension of the file
+/// name.
+/// The file is created if it doesn't exist and truncated otherwise.
+pub fn create<P: AsRef<Path>>(path: &P) -> io::Result<Writer> {
+    let compression = Compression::from_path(path);
+    // The file is only created once the format is known to be
+    // supported, so that an existing file is not truncated otherwise
+    let file = || fs::File::create(path).map(io::BufWriter::new);
+    let inner = match compression {
+        Compression::Uncompressed => WriterInner::Uncompressed(file()?),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => WriterInner::Gzip(flate2::write::GzEncoder::new(
+            file()?,
+            flate2::Compression::default(),
+        )),
+        #[cfg(feature = "xz")]
+        Compression::Xz => WriterInner::Xz(xz2::write::XzEncoder::new(file()?, 6)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => WriterInner::Zstd(zstd::stream::write::Encoder::new(file()?, 0)?),
+        #[cfg(not(feature = "gzip"))]
+        Compression::Gzip => return Err(unsupported("gzip")),
+        #[cfg(not(feature = "xz"))]
+        Compression::Xz => return Err(unsupported("xz")),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => return Err(unsupported("zstd")),
+    };
+    Ok(Writer { inner: Some(inner) })
+}
+
+#[cfg(not(all(feature = "gzip", feature = "xz", feature = "zstd")))]
+fn unsupported(feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!(
+            "{} compressed files are only supported with the `{}` feature",
+            feature, feature
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use reader::LheReader;
+    use string;
+    use writer::LheWriter;
+    use ReadLhe;
+    use WriteLhe;
+
+    use super::{create, open, Compression};
+
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("lhef-{}-{}", process::id(), name))
+    }
+
+    fn roundtrip(name: &str, compression: Compression) {
+        let path = temp_path(name);
+        let lhe =
+            string::LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        lhe.write_lhe_to_file(&path).unwrap();
+
+        let start = fs::read(&path).unwrap();
+        assert_eq!(
+            Compression::from_magic_bytes(&start).unwrap_or(Compression::Uncompressed),
+            compression
+        );
+        let round = string::LheFile::read_lhe_from_file(&path).unwrap();
+        assert_eq!(lhe, round);
+
+        let reader: LheReader<
+            _,
+            string::Comment,
+            string::Header,
+            string::InitExtra,
+            string::EventExtra,
+        > = LheReader::from_file(&path).unwrap();
+        assert_eq!(reader.count(), lhe.events.len());
+
+        let mut writer = LheWriter::create(
+            &path,
+            &lhe.version,
+            &lhe.attributes,
+            &lhe.comment,
+            &lhe.header,
+            &lhe.init,
+        )
+        .unwrap();
+        for event in &lhe.events {
+            writer.write_event(event).unwrap();
+        }
+        writer.finish().unwrap().finish().unwrap();
+        let round = string::LheFile::read_lhe_from_file(&path).unwrap();
+        assert_eq!(lhe, round);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn roundtrip_uncompressed() {
+        roundtrip("roundtrip.lhe", Compression::Uncompressed);
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn roundtrip_gzip() {
+        roundtrip("roundtrip.lhe.gz", Compression::Gzip);
+    }
+
+    #[test]
+    #[cfg(feature = "xz")]
+    fn roundtrip_xz() {
+        roundtrip("roundtrip.lhe.xz", Compression::Xz);
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn roundtrip_zstd() {
+        roundtrip("roundtrip.lhe.zst", Compression::Zstd);
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn detect_from_magic_bytes() {
+        use std::io::Write;
+
+        let path = temp_path("gzip_without_extension.lhe");
+        {
+            let mut writer = create(&temp_path("gzip_with_extension.lhe.gz")).unwrap();
+            writer.write_all(b"<LesHouchesEvents").unwrap();
+            writer.finish().unwrap();
+        }
+        fs::rename(temp_path("gzip_with_extension.lhe.gz"), &path).unwrap();
+        let reader = open(&path).unwrap();
+        assert_eq!(reader.compression(), Compression::Gzip);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(not(feature = "gzip"))]
+    fn unsupported_compression() {
+        let path = temp_path("unsupported.lhe.gz");
+        fs::write(&path, [0x1f, 0x8b, 0x08, 0x00]).unwrap();
+        assert!(create(&path).is_err());
+        assert_eq!(fs::read(&path).unwrap(), [0x1f, 0x8b, 0x08, 0x00]);
+        assert!(open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn skip_past_end() {
+        let path = temp_path("skip.lhe");
+        fs::write(&path, b"<LesHouchesEvents>").unwrap();
+        let mut reader = open(&path).unwrap();
+        reader.skip(5).unwrap();
+        assert!(reader.skip(100).is_err());
+        assert!(reader.skip(u64::MAX).is_err());
+        reader.skip(13).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}
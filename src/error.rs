@@ -0,0 +1,544 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Precise errors for malformed lhe files
+//!
+//! The nom parsers used by `ReadLhe::read_lhe` only report that
+//! parsing failed, but not where.
+//! When reading a file fails, the input is therefore walked a second
+//! time, field by field, by the `ReadLhe::diagnose_lhe` method of the
+//! types involved, to find the first field that can not be read.
+//! The result is a [`ParseError`], which contains the position of the
+//! error in the file, the section and the field in which it occurred
+//! and the offending text.
+//!
+//! Events, particles and process infos are counted from zero, in the
+//! same way as they are indexed in `LheFileGeneric`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use lhef::{ReadError, ReadLhe};
+//! use lhef::error::Section;
+//! use lhef::plain::LheFile;
+//!
+//! let bytes = b"\
+//! <LesHouchesEvents version=\"1.0\">
+//! <init>
+//! 2212 2212 6500 6500 0 0 13100 13100 3 1
+//! 2.1 3.2E-03 1.0E+00 1
+//! </init>
+//! <event>
+//! 2 1 1.0 91.2 0.0078 0.118
+//! 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+//! 21 -1 0 0 502 501 0.0 0.0 -1.0 1.x0 0.0 0. 9.
+//! </event>
+//! </LesHouchesEvents>";
+//!
+//! match LheFile::read_lhe_from_bytes(bytes) {
+//!     Err(ReadError::Parse(err)) => {
+//!         assert_eq!(err.line, 9);
+//!         assert_eq!(err.column, 32);
+//!         assert_eq!(err.section, Section::Particle(0, 1));
+//!         assert_eq!(err.field, Some("PUP(4)".to_string()));
+//!         assert_eq!(err.found, "1.x0 0.0 0. 9.");
+//!     }
+//!     _ => panic!("Expected a parse error"),
+//! }
+//! ```
+//!
+//! [`ParseError`]: struct.ParseError.html
+
+use ReadLhe;
+
+use nom;
+use std::error;
+use std::fmt;
+use std::str;
+
+/// The maximal number of characters of the input in `ParseError::found`
+const SNIPPET_LENGTH: usize = 40;
+
+pub(crate) const INTEGER: &str = "an integer";
+pub(crate) const UNSIGNED: &str = "a non-negative integer";
+pub(crate) const INTEGER_8: &str = "an integer between -128 and 127";
+pub(crate) const UNSIGNED_8: &str = "an integer between 0 and 255";
+pub(crate) const FLOAT: &str = "a floating point number";
+
+/// The part of an lhe file in which an error occurred
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Section {
+    /// The opening or closing tag of the file, or anything between the
+    /// sections
+    File,
+    /// The comment at the start of the file
+    Comment,
+    /// The header
+    Header,
+    /// The init section, except for the process infos and the
+    /// additional information
+    Init,
+    /// The process info with the given index in the init section
+    ProcInfo(usize),
+    /// The additional information in the init section
+    InitExtra,
+    /// The event with the given index, except for its particles and the
+    /// additional information
+    Event(usize),
+    /// The particle with the given index (second entry) in the event
+    /// with the given index (first entry)
+    Particle(usize, usize),
+    /// The additional information in the event with the given index
+    EventExtra(usize),
+}
+
+impl Section {
+    /// The index of the event this section belongs to, if any
+    pub fn event(&self) -> Option<usize> {
+        match *self {
+            Section::Event(n) | Section::Particle(n, _) | Section::EventExtra(n) => Some(n),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Section::File => write!(f, "the file"),
+            Section::Comment => write!(f, "the comment"),
+            Section::Header => write!(f, "the header"),
+            Section::Init => write!(f, "the init section"),
+            Section::ProcInfo(n) => write!(f, "process info {} in the init section", n),
+            Section::InitExtra => write!(f, "the additional information in the init section"),
+            Section::Event(n) => write!(f, "event {}", n),
+            Section::Particle(n, m) => write!(f, "particle {} in event {}", m, n),
+            Section::EventExtra(n) => write!(f, "the additional information in event {}", n),
+        }
+    }
+}
+
+/// A parse error, with its position in the file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte offset of the error in the file
+    pub offset: usize,
+    /// The line of the error, counting from one
+    pub line: usize,
+    /// The column of the error in bytes, counting from one
+    pub column: usize,
+    /// The section of the file in which the error occurred
+    pub section: Section,
+    /// The field that could not be read, e.g. `PUP(3)`
+    ///
+    /// The fields of the common blocks are named like in the [`lhe`]
+    /// paper, the fields of additional information are named like the
+    /// fields of the structs they are stored in.
+    ///
+    /// [`lhe`]: https://arxiv.org/abs/hep-ph/0609017
+    pub field: Option<String>,
+    /// A description of what was expected
+    pub expected: String,
+    /// The text at the position of the error, up to the end of the line
+    ///
+    /// This is empty if the error is at the end of the input.
+    pub found: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (byte {}) in {}",
+            self.line, self.column, self.offset, self.section
+        )?;
+        if let Some(ref field) = self.field {
+            write!(f, ", field {}", field)?;
+        }
+        write!(f, ": expected {}, ", self.expected)?;
+        if self.found.is_empty() {
+            write!(f, "found the end of the input")
+        } else {
+            write!(f, "found `{}`", self.found)
+        }
+    }
+}
+
+impl error::Error for ParseError {
+    fn description(&self) -> &str {
+        "Failed to parse the lhe file"
+    }
+}
+
+/// A cursor to walk over the input field by field to find an error
+///
+/// This is passed to `ReadLhe::diagnose_lhe`.
+/// All methods that read something skip leading whitespace first, like
+/// the `ws!` macro of nom.
+#[derive(Debug)]
+pub struct ErrorLocator<'a> {
+    input: &'a [u8],
+    position: usize,
+    origin_offset: usize,
+    origin_line: usize,
+    section: Section,
+}
+
+impl<'a> ErrorLocator<'a> {
+    /// Create a locator for an input that starts at the start of a file
+    pub fn new(input: &'a [u8]) -> ErrorLocator<'a> {
+        ErrorLocator::with_origin(input, 0, 1, Section::File)
+    }
+
+    /// Create a locator for an input that starts at byte `offset` and
+    /// line `line` of a file
+    pub(crate) fn with_origin(
+        input: &'a [u8],
+        offset: usize,
+        line: usize,
+        section: Section,
+    ) -> ErrorLocator<'a> {
+        ErrorLocator {
+            input,
+            position: 0,
+            origin_offset: offset,
+            origin_line: line,
+            section,
+        }
+    }
+
+    /// The section that is currently walked
+    pub fn section(&self) -> Section {
+        self.section
+    }
+
+    /// Set the section that is currently walked
+    pub fn set_section(&mut self, section: Section) {
+        self.section = section;
+    }
+
+    /// The input that has not been walked yet
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.input[self.position..]
+    }
+
+    /// Mark the first `n` bytes of the remaining input as walked
+    pub fn advance(&mut self, n: usize) {
+        self.position += n;
+    }
+
+    /// Skip whitespace, including line breaks
+    pub fn skip_whitespace(&mut self) {
+        let n = self
+            .remaining()
+            .iter()
+            .take_while(|c| is_whitespace(**c))
+            .count();
+        self.advance(n);
+    }
+
+    /// Check that the remaining input starts with `tag`, and skip it
+    pub fn tag(&mut self, tag: &str) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.remaining().starts_with(tag.as_bytes()) {
+            self.advance(tag.len());
+            Ok(())
+        } else {
+            Err(self.error(None, &format!("`{}`", tag)))
+        }
+    }
+
+    /// Read a single whitespace separated field using `parser`
+    ///
+    /// The whole field has to be consumed by the parser.
+    /// `expected` describes the contents of the field, e.g. "an integer".
+    pub fn field<T, F>(&mut self, name: &str, expected: &str, parser: F) -> Result<T, ParseError>
+    where
+        F: Fn(&[u8]) -> nom::IResult<&[u8], T>,
+    {
+        self.skip_whitespace();
+        let token_length = self
+            .remaining()
+            .iter()
+            .take_while(|c| !is_whitespace(**c) && **c != b'<')
+            .count();
+        // Terminate the token with a space, so that the nom parsers
+        // don't return `Incomplete` at the end of the input
+        let mut token = self.remaining()[..token_length].to_vec();
+        token.push(b' ');
+        match parser(&token) {
+            nom::IResult::Done(rest, value) if rest == b" " => {
+                self.advance(token_length);
+                Ok(value)
+            }
+            _ => Err(self.error(Some(name), expected)),
+        }
+    }
+
+    /// Walk over an object using its `ReadLhe::diagnose_lhe` method
+    pub fn object<T: ReadLhe>(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        T::diagnose_lhe(self)
+    }
+
+    /// Try to read an object using `parser`, and skip it if successful
+    ///
+    /// Returns `false` if `parser` fails, without changing the position.
+    pub fn skip_parsed<T, F>(&mut self, parser: F) -> bool
+    where
+        F: Fn(&[u8]) -> nom::IResult<&[u8], T>,
+    {
+        self.parsed(parser).is_some()
+    }
+
+    /// Try to read an object using `parser`, and skip it if successful
+    ///
+    /// Returns `None` if `parser` fails, without changing the position.
+    pub(crate) fn parsed<T, F>(&mut self, parser: F) -> Option<T>
+    where
+        F: Fn(&[u8]) -> nom::IResult<&[u8], T>,
+    {
+        let remaining = self.remaining();
+        match parser(remaining) {
+            nom::IResult::Done(rest, value) => {
+                self.advance(remaining.len() - rest.len());
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Create an error at the current position
+    pub fn error(&self, field: Option<&str>, expected: &str) -> ParseError {
+        let consumed = &self.input[..self.position];
+        let line_start = consumed
+            .iter()
+            .rposition(|c| *c == b'\n')
+            .map_or(0, |n| n + 1);
+        let found: Vec<u8> = self
+            .remaining()
+            .iter()
+            .take_while(|c| **c != b'\n' && **c != b'\r')
+            .cloned()
+            .collect();
+        let found = String::from_utf8_lossy(&found);
+        ParseError {
+            offset: self.origin_offset + self.position,
+            line: self.origin_line + consumed.iter().filter(|c| **c == b'\n').count(),
+            column: self.position - line_start + 1,
+            section: self.section,
+            field: field.map(|f| f.to_string()),
+            expected: expected.to_string(),
+            found: found.chars().take(SNIPPET_LENGTH).collect(),
+        }
+    }
+}
+
+/// Find the first error in `input`, which should contain a `T` and
+/// nothing else
+pub(crate) fn locate<T: ReadLhe>(locator: &mut ErrorLocator) -> ParseError {
+    let start = locator.position;
+    if let Err(err) = locator.object::<T>() {
+        return err;
+    }
+    locator.skip_whitespace();
+    if !locator.remaining().is_empty() {
+        return locator.error(None, "the end of the input");
+    }
+    // The input could be walked without errors, but the parser still
+    // failed, so the position of the error is unknown
+    locator.position = start;
+    locator.error(None, "valid input")
+}
+
+/// The signature of `ReadLhe::diagnose_lhe`
+pub(crate) type Diagnose = fn(&mut ErrorLocator) -> Result<(), ParseError>;
+
+/// Walk over objects that can appear in any order, identified by the
+/// keyword after a leading `#`
+///
+/// This is used for the additional information in `helac` files.
+pub(crate) fn diagnose_permutation(
+    locator: &mut ErrorLocator,
+    items: &[(&str, Diagnose)],
+) -> Result<(), ParseError> {
+    let mut found = vec![false; items.len()];
+    while found.iter().any(|f| !f) {
+        locator.skip_whitespace();
+        let keyword = {
+            let line = locator.remaining();
+            let after_hash = if line.starts_with(b"#") {
+                &line[1..]
+            } else {
+                &line[..0]
+            };
+            let word: Vec<u8> = after_hash
+                .iter()
+                .skip_while(|c| is_whitespace(**c))
+                .take_while(|c| !is_whitespace(**c))
+                .cloned()
+                .collect();
+            word
+        };
+        let next = items
+            .iter()
+            .enumerate()
+            .find(|&(i, item)| !found[i] && item.0.as_bytes() == &keyword[..]);
+        match next {
+            Some((i, item)) => {
+                found[i] = true;
+                (item.1)(locator)?;
+            }
+            None => {
+                let missing: Vec<_> = items
+                    .iter()
+                    .zip(&found)
+                    .filter(|&(_, f)| !f)
+                    .map(|(item, _)| format!("`# {}`", item.0))
+                    .collect();
+                return Err(locator.error(None, &format!("one of {}", missing.join(", "))));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_whitespace(c: u8) -> bool {
+    nom::is_space(c) || c == b'\r' || c == b'\n'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Section;
+
+    use helac;
+    use plain;
+    use ReadError;
+    use ReadLhe;
+
+    use std::fs;
+
+    /// Replace the whitespace separated token with index `token` in line
+    /// `line` (counting from one) of `file_name` with `replacement`
+    fn corrupt(file_name: &str, line: usize, token: usize, replacement: &str) -> Vec<u8> {
+        let contents = fs::read_to_string(file_name).unwrap();
+        let lines: Vec<String> = contents
+            .split('\n')
+            .enumerate()
+            .map(|(n, l)| {
+                if n + 1 == line {
+                    let mut tokens: Vec<_> = l.split_whitespace().collect();
+                    tokens[token] = replacement;
+                    tokens.join(" ")
+                } else {
+                    l.to_string()
+                }
+            })
+            .collect();
+        lines.join("\n").into_bytes()
+    }
+
+    fn parse_error<T: ReadLhe>(bytes: &[u8]) -> super::ParseError {
+        match T::read_lhe_from_bytes(bytes) {
+            Err(ReadError::Parse(err)) => err,
+            _ => panic!("Expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn particle_field() {
+        let bytes = corrupt("tests/real_world_files/mg5_aMC.lhe", 505, 9, "1.x0");
+        let err = parse_error::<plain::LheFile>(&bytes);
+        assert_eq!(err.line, 505);
+        assert_eq!(err.section, Section::Particle(1, 2));
+        assert_eq!(err.field, Some("PUP(4)".to_string()));
+        assert!(err.found.starts_with("1.x0 "));
+        assert_eq!(&bytes[err.offset..err.offset + 4], b"1.x0");
+    }
+
+    #[test]
+    fn event_field() {
+        let bytes = corrupt("tests/real_world_files/mg5_aMC.lhe", 516, 0, "-3");
+        let err = parse_error::<plain::LheFile>(&bytes);
+        assert_eq!(err.line, 516);
+        assert_eq!(err.column, 1);
+        assert_eq!(err.section, Section::Event(2));
+        assert_eq!(err.field, Some("NUP".to_string()));
+    }
+
+    #[test]
+    fn init_field() {
+        let bytes = corrupt("tests/real_world_files/mg5_aMC.lhe", 483, 2, "five");
+        let err = parse_error::<plain::LheFile>(&bytes);
+        assert_eq!(err.line, 483);
+        assert_eq!(err.column, 8);
+        assert_eq!(err.section, Section::Init);
+        assert_eq!(err.field, Some("EBMUP(1)".to_string()));
+        assert_eq!(err.found, "five 5.000000e+02 0 0 247000 247000 -4 1");
+
+        let bytes = corrupt("tests/real_world_files/mg5_aMC.lhe", 484, 2, "x");
+        let err = parse_error::<plain::LheFile>(&bytes);
+        assert_eq!(err.line, 484);
+        assert_eq!(err.section, Section::ProcInfo(0));
+        assert_eq!(err.field, Some("XMAXUP".to_string()));
+    }
+
+    #[test]
+    fn helac_field() {
+        let bytes = corrupt("tests/real_world_files/helac_dipoles_rs.lhe", 24, 7, "x");
+        let err = parse_error::<helac::LheFileRS>(&bytes);
+        assert_eq!(err.line, 24);
+        assert_eq!(err.section, Section::EventExtra(0));
+        assert_eq!(err.field, Some("irun".to_string()));
+        assert_eq!(err.expected, super::UNSIGNED_8);
+    }
+
+    #[test]
+    fn missing_version() {
+        let bytes = corrupt("tests/real_world_files/mg5_aMC.lhe", 1, 1, "lhef=\"3.0\">");
+        let err = parse_error::<plain::LheFile>(&bytes);
+        assert_eq!(err.line, 1);
+        assert_eq!(err.column, 1);
+        assert_eq!(err.section, Section::File);
+        assert_eq!(err.field, Some("version".to_string()));
+    }
+
+    #[test]
+    fn event_attribute() {
+        let bytes = corrupt("tests/real_world_files/mg5_aMC.lhe", 487, 0, "<event npLO=2>");
+        let err = parse_error::<plain::LheFile>(&bytes);
+        assert_eq!(err.line, 487);
+        assert_eq!(err.column, 8);
+        assert_eq!(err.section, Section::Event(0));
+        assert_eq!(err.found, "npLO=2>");
+    }
+
+    #[test]
+    fn missing_closing_tag() {
+        let bytes = fs::read("tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let truncated = &bytes[..bytes.len() - "</LesHouchesEvents>\n".len()];
+        let err = parse_error::<plain::LheFile>(truncated);
+        assert_eq!(err.section, Section::File);
+        assert_eq!(err.offset, truncated.len());
+        assert_eq!(err.found, "");
+    }
+
+    #[test]
+    fn display() {
+        let bytes = corrupt("tests/real_world_files/mg5_aMC.lhe", 483, 2, "five");
+        let err = parse_error::<plain::LheFile>(&bytes);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "line 483, column 8 (byte {}) in the init section, field EBMUP(1): \
+                 expected a floating point number, found `five 5.000000e+02 0 0 247000 247000 -4 1`",
+                err.offset
+            )
+        );
+    }
+}
@@ -14,10 +14,20 @@
 //! programs.
 
 use {Particle, PdgId, ProcInfo, ReadLhe, WriteLhe};
+use attributes::{diagnose_opening_tag, read_opening_tag, write_opening_tag, Attributes};
+use error::{ErrorLocator, ParseError, Section, FLOAT, INTEGER, UNSIGNED};
 use nom_util::{parse_f64, parse_i64, parse_u64};
+use reader::{contains, trim_start};
+use version::LhefVersion;
 
 use nom;
+use std::cmp;
+use std::error;
+use std::fmt;
 use std::io;
+use std::iter;
+use std::ops::Range;
+use std::slice;
 use std::str;
 
 #[cfg(test)]
@@ -59,7 +69,7 @@ use quickcheck::Gen;
 ///
 /// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
 ///
-/// assert_eq!(lhe.version, "1.0".to_string());
+/// assert_eq!(lhe.version.as_str(), "1.0");
 ///
 /// let Comment { ref comment } = lhe.comment;
 /// assert_eq!(comment, &Some("Process: e+ e- > mu+ mu-".to_string()));
@@ -81,7 +91,10 @@ use quickcheck::Gen;
 #[cfg_attr(test, derive(Serialize, Deserialize))]
 pub struct LheFileGeneric<Comment, Header, InitExtra, EventExtra> {
     /// The version contained in the opening tag of the lhe file.
-    pub version: String,
+    pub version: LhefVersion,
+    /// Any other attributes of the opening tag of the lhe file.
+    #[cfg_attr(test, serde(default))]
+    pub attributes: Attributes,
     /// The comment in the lhe file, in a custom format.
     pub comment: Comment,
     /// The header section in the lhe file, in a custom format.
@@ -89,7 +102,118 @@ pub struct LheFileGeneric<Comment, Header, InitExtra, EventExtra> {
     /// The init section of the lhe file, with custom addition information.
     pub init: InitGeneric<InitExtra>,
     /// The events of the lhe file, each with custom addition information.
+    ///
+    /// This includes the events in `<eventgroup>` blocks, in the order
+    /// in which they appear in the file.
     pub events: Vec<EventGeneric<EventExtra>>,
+    /// The `<eventgroup>` blocks of the lhe file, in the order in which
+    /// they appear in the file.
+    #[cfg_attr(test, serde(default))]
+    pub groups: Vec<EventGroupRange>,
+    /// All further comments in the lhe file, in a custom format.
+    ///
+    /// The first comment at the start of the file is stored in
+    /// `comment`, all other comments outside of the header, the init
+    /// section and the events are stored here together with their
+    /// position.
+    #[cfg_attr(test, serde(default = "Vec::new"))]
+    pub comments: Vec<FileComment<Comment>>,
+}
+
+impl<Comment, Header, InitExtra, EventExtra> LheFileGeneric<Comment, Header, InitExtra, EventExtra> {
+    pub(crate) fn from_start(
+        start: FileStart<Comment, Header, InitExtra>,
+        items: Vec<EventItem<EventExtra>>,
+    ) -> LheFileGeneric<Comment, Header, InitExtra, EventExtra> {
+        let (version, attributes, comment, header, init, comments) = start;
+        let mut lhe = LheFileGeneric {
+            version,
+            attributes,
+            comment,
+            header,
+            init,
+            events: Vec::new(),
+            groups: Vec::new(),
+            comments,
+        };
+        for item in items {
+            lhe.push_item(item);
+        }
+        lhe
+    }
+
+    /// Append a single event or an event group to the events of the file
+    pub fn push_item(&mut self, item: EventItem<EventExtra>) {
+        match item {
+            EventItem::Event(event) => self.events.push(event),
+            EventItem::Group(group) => {
+                let start = self.events.len();
+                self.events.extend(group.events);
+                self.groups.push(EventGroupRange {
+                    attributes: group.attributes,
+                    events: start..self.events.len(),
+                });
+            }
+        }
+    }
+
+    /// Append a comment after the last event of the file
+    pub fn push_comment(&mut self, comment: Comment) {
+        self.comments.push(FileComment {
+            position: CommentPosition::BeforeEvent(self.events.len()),
+            comment,
+        });
+    }
+
+    /// Append an entry that was read between the events of a file
+    pub(crate) fn push_entry(&mut self, entry: Entry<Comment, EventExtra>) {
+        match entry {
+            Entry::Comment(comment) => self.push_comment(comment),
+            Entry::Event(event) => self.events.push(event),
+            Entry::Group(group, comments) => {
+                let (index, start) = (self.groups.len(), self.events.len());
+                self.push_item(EventItem::Group(group));
+                self.comments
+                    .extend(comments.into_iter().map(|(n, comment)| FileComment {
+                        position: CommentPosition::InGroup(index, start + n),
+                        comment,
+                    }));
+            }
+        }
+    }
+
+    /// The event group that the `n`th event belongs to, if any
+    pub fn group_of(&self, n: usize) -> Option<&EventGroupRange> {
+        self.groups
+            .iter()
+            .find(|group| group.events.start <= n && n < group.events.end)
+    }
+
+    /// Split the events of the file into single events and event groups
+    ///
+    /// Returns an error if the ranges in `groups` are not in order,
+    /// overlap or refer to events that don't exist.
+    pub fn into_items(self) -> Result<Vec<EventItem<EventExtra>>, InvalidEventGroup> {
+        check_groups(&self.groups, self.events.len())?;
+        let mut items = Vec::new();
+        let mut events = self.events.into_iter();
+        let mut position = 0;
+        for group in self.groups {
+            items.extend(
+                events
+                    .by_ref()
+                    .take(group.events.start - position)
+                    .map(EventItem::Event),
+            );
+            items.push(EventItem::Group(EventGroup {
+                attributes: group.attributes,
+                events: events.by_ref().take(group.events.len()).collect(),
+            }));
+            position = group.events.end;
+        }
+        items.extend(events.map(EventItem::Event));
+        Ok(items)
+    }
 }
 
 impl<Comment, Header, InitExtra, EventExtra> ReadLhe
@@ -103,25 +227,36 @@ where
     fn read_lhe(
         input: &[u8],
     ) -> nom::IResult<&[u8], LheFileGeneric<Comment, Header, InitExtra, EventExtra>> {
-        let parse_comment = Comment::read_lhe;
-        let parse_header = Header::read_lhe;
-        let parse_init = InitGeneric::read_lhe;
-        do_parse!(
+        let (rest, (start, entries)) = try_parse!(
             input,
-            ws!(tag!("<LesHouchesEvents")) >> ws!(tag!("version=")) >> tag!("\"")
-                >> version:
-                    map_res!(take_until!("\""), |x| str::from_utf8(x)
-                        .map(|x| x.to_string())) >> tag!("\"") >> ws!(tag!(">"))
-                >> hi: permutation_opt!(parse_comment, parse_header, parse_init)
-                >> events: dbg_dmp!(many0!(EventGeneric::read_lhe))
-                >> dbg_dmp!(ws!(tag!("</LesHouchesEvents>"))) >> (LheFileGeneric {
-                version,
-                comment: hi.0,
-                header: hi.1,
-                init: hi.2,
-                events,
-            })
-        )
+            pair!(read_file_start, dbg_dmp!(many0!(read_entry)))
+        );
+        let (rest, _) = try_parse!(rest, dbg_dmp!(read_closing_file_tag));
+        let mut lhe = LheFileGeneric::from_start(start, Vec::new());
+        for entry in entries {
+            lhe.push_entry(entry);
+        }
+        nom::IResult::Done(rest, lhe)
+    }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        diagnose_file_start::<Comment, Header, InitExtra>(locator)?;
+        let mut n = 0;
+        loop {
+            locator.skip_whitespace();
+            if locator.remaining().starts_with(b"<event") {
+                locator.set_section(Section::Event(n));
+                n += diagnose_item::<EventExtra>(locator)?;
+            } else if locator.remaining().starts_with(b"<!--") {
+                locator.set_section(Section::Comment);
+                locator.object::<Comment>()?;
+            } else {
+                locator.set_section(Section::File);
+                return locator.tag("</LesHouchesEvents>").map_err(|_| {
+                    locator.error(None, "`<event>`, `<eventgroup>` or `</LesHouchesEvents>`")
+                });
+            }
+        }
     }
 }
 
@@ -134,13 +269,52 @@ where
     EventExtra: WriteLhe,
 {
     fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
-        write_opening_file_tag(writer, &self.version)?;
+        check_groups(&self.groups, self.events.len())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        write_opening_file_tag(writer, self.version.as_str(), &self.attributes)?;
+        let (mut in_groups, mut comments): (Vec<_>, Vec<_>) = self
+            .comments
+            .iter()
+            .partition(|comment| matches!(comment.position, CommentPosition::InGroup(..)));
+        comments.sort_by_key(|comment| comment.position);
+        in_groups.sort_by_key(|comment| comment.position);
+        let mut comments = comments.into_iter().peekable();
+        let mut in_groups = in_groups.into_iter().peekable();
         self.comment.write_lhe(writer)?;
+        write_comments(writer, &mut comments, CommentPosition::Start)?;
         self.header.write_lhe(writer)?;
         self.init.write_lhe(writer)?;
-        for event in &self.events {
+        let mut position = 0;
+        for (index, group) in self.groups.iter().enumerate() {
+            for (n, event) in self.events[..group.events.start]
+                .iter()
+                .enumerate()
+                .skip(position)
+            {
+                write_comments(writer, &mut comments, CommentPosition::BeforeEvent(n))?;
+                event.write_lhe(writer)?;
+            }
+            // Comments before events inside of the group that are not
+            // positioned inside of it are written before the group
+            let last = cmp::max(group.events.start, group.events.end.saturating_sub(1));
+            write_comments(writer, &mut comments, CommentPosition::BeforeEvent(last))?;
+            write_opening_tag(writer, "eventgroup", &group.attributes)?;
+            for n in group.events.clone() {
+                write_comments(writer, &mut in_groups, CommentPosition::InGroup(index, n))?;
+                self.events[n].write_lhe(writer)?;
+            }
+            let end = CommentPosition::InGroup(index, usize::MAX);
+            write_comments(writer, &mut in_groups, end)?;
+            writeln!(writer, "</eventgroup>")?;
+            position = group.events.end;
+        }
+        for (n, event) in self.events.iter().enumerate().skip(position) {
+            write_comments(writer, &mut comments, CommentPosition::BeforeEvent(n))?;
             event.write_lhe(writer)?;
         }
+        for comment in comments.chain(in_groups) {
+            comment.comment.write_lhe(writer)?;
+        }
         write_closing_file_tag(writer)
     }
 }
@@ -159,38 +333,439 @@ where
         while version.contains("\"") {
             version = Arbitrary::arbitrary(gen);
         }
-        LheFileGeneric {
+        let version = LhefVersion::from(version);
+        let mut attributes: Attributes = Arbitrary::arbitrary(gen);
+        attributes.remove("version");
+        let start = (
             version,
-            comment: Arbitrary::arbitrary(gen),
-            header: Arbitrary::arbitrary(gen),
-            init: Arbitrary::arbitrary(gen),
-            events: Arbitrary::arbitrary(gen),
-        }
+            attributes,
+            Arbitrary::arbitrary(gen),
+            Arbitrary::arbitrary(gen),
+            Arbitrary::arbitrary(gen),
+            Vec::new(),
+        );
+        LheFileGeneric::from_start(start, Arbitrary::arbitrary(gen))
     }
 
     fn shrink(
         &self,
     ) -> Box<Iterator<Item = LheFileGeneric<Header, Comment, InitExtra, EventExtra>>> {
-        let version = self.version.clone();
+        let version = String::from(self.version.clone());
+        let attributes = self.attributes.clone();
         let comment = self.comment.clone();
         let header = self.header.clone();
         let init = self.init.clone();
-        let tup = (version, self.events.clone());
-        let iter = tup.shrink().map(move |x| LheFileGeneric {
-            version: x.0,
-            comment: comment.clone(),
-            header: header.clone(),
-            init: init.clone(),
-            events: x.1,
+        let tup = (version, self.clone().into_items().unwrap());
+        let iter = tup.shrink().map(move |x| {
+            let start = (
+                LhefVersion::from(x.0),
+                attributes.clone(),
+                comment.clone(),
+                header.clone(),
+                init.clone(),
+                Vec::new(),
+            );
+            LheFileGeneric::from_start(start, x.1)
         });
         Box::new(iter)
     }
 }
 
-fn write_opening_file_tag<W: io::Write>(writer: &mut W, version: &str) -> io::Result<()> {
-    writeln!(writer, "<LesHouchesEvents version=\"{}\">", version)
+/// A comment in an lhe file outside of the header, the init section
+/// and the events
+///
+/// Files can contain any number of xml comments before the header,
+/// between events, inside of event groups and before the closing tag of
+/// the file.
+/// The first comment at the start of the file is stored in
+/// `LheFileGeneric::comment`, all others are stored as `FileComment`s
+/// in `LheFileGeneric::comments`.
+/// Whether the contents of the comments are kept depends on the
+/// `Comment` type, e.g. `plain::Comment` throws them away while
+/// `string::Comment` keeps them.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::generic::CommentPosition;
+/// use lhef::string::{LheFile, Comment};
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"1.0\">
+/// <!-- Generated by some program -->
+/// <!-- with some settings -->
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 0 1 1.0 91.2 0.0078 0.118
+/// </event>
+/// <!-- Second event -->
+/// <event>
+/// 0 1 2.0 91.2 0.0078 0.118
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+/// assert_eq!(lhe.comment.comment, Some("Generated by some program".to_string()));
+///
+/// assert_eq!(lhe.comments.len(), 2);
+/// assert_eq!(lhe.comments[0].position, CommentPosition::Start);
+/// assert_eq!(lhe.comments[1].position, CommentPosition::BeforeEvent(1));
+/// let Comment { ref comment } = lhe.comments[1].comment;
+/// assert_eq!(comment, &Some("Second event".to_string()));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(Serialize, Deserialize))]
+pub struct FileComment<Comment> {
+    /// The position of the comment in the file
+    pub position: CommentPosition,
+    /// The comment, in a custom format
+    pub comment: Comment,
+}
+
+/// The position of a `FileComment` in an lhe file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(test, derive(Serialize, Deserialize))]
+pub enum CommentPosition {
+    /// Before the first event, together with the header and the init
+    /// section
+    ///
+    /// These comments are written directly after
+    /// `LheFileGeneric::comment`.
+    Start,
+    /// Before the event with the given index in `LheFileGeneric::events`
+    ///
+    /// An index equal to the number of events refers to the end of the
+    /// file.
+    /// Comments before an event inside of an `<eventgroup>` are written
+    /// before the group, use `InGroup` for comments inside of it.
+    BeforeEvent(usize),
+    /// Inside of the `<eventgroup>` with the first index in
+    /// `LheFileGeneric::groups`, before the event with the second index
+    /// in `LheFileGeneric::events`
+    ///
+    /// An event index at or after the end of the group refers to the
+    /// end of the group.
+    InGroup(usize, usize),
+}
+
+/// Write all comments up to `position` from a sorted list of comments
+fn write_comments<'a, W, Comment, I>(
+    writer: &mut W,
+    comments: &mut iter::Peekable<I>,
+    position: CommentPosition,
+) -> io::Result<()>
+where
+    W: io::Write,
+    Comment: WriteLhe + 'a,
+    I: Iterator<Item = &'a FileComment<Comment>>,
+{
+    while let Some(comment) = comments.next_if(|comment| comment.position <= position) {
+        comment.comment.write_lhe(writer)?;
+    }
+    Ok(())
+}
+
+/// The version, the attributes of the opening tag, the comment, the
+/// header, the init section and further comments of an lhe file
+pub(crate) type FileStart<Comment, Header, InitExtra> = (
+    LhefVersion,
+    Attributes,
+    Comment,
+    Header,
+    InitGeneric<InitExtra>,
+    Vec<FileComment<Comment>>,
+);
+
+/// Read everything in an lhe file up to the first event
+///
+/// This parses the opening tag of the file, the comments, the header
+/// and the init section, in any order.
+/// The header and the init section may appear at most once, while
+/// there may be any number of comments.
+/// The first comment is returned separately from the others, comments
+/// after the init section are placed before the first event.
+pub(crate) fn read_file_start<Comment, Header, InitExtra>(
+    input: &[u8],
+) -> nom::IResult<&[u8], FileStart<Comment, Header, InitExtra>>
+where
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+{
+    let (mut rest, (version, attributes)) = try_parse!(
+        input,
+        map_opt!(call!(read_opening_tag, "LesHouchesEvents"), split_version)
+    );
+    let mut comments = Vec::new();
+    let mut after_init = Vec::new();
+    let mut header = None;
+    let mut init = None;
+    loop {
+        rest = trim_start(rest);
+        if rest.starts_with(b"<!--") {
+            let (after, comment) = try_parse!(rest, read_comment::<Comment>);
+            if init.is_some() {
+                after_init.push(comment);
+            } else {
+                comments.push(comment);
+            }
+            rest = after;
+        } else if header.is_none() && rest.starts_with(b"<header") {
+            let (after, parsed) = try_parse!(rest, Header::read_lhe);
+            header = Some(parsed);
+            comments.append(&mut after_init);
+            rest = after;
+        } else if init.is_none() && rest.starts_with(b"<init") {
+            let (after, parsed) = try_parse!(rest, InitGeneric::read_lhe);
+            init = Some(parsed);
+            rest = after;
+        } else if [&b"<!--"[..], b"<header", b"<init"]
+            .iter()
+            .any(|tag| tag.starts_with(rest))
+        {
+            return nom::IResult::Incomplete(nom::Needed::Unknown);
+        } else {
+            break;
+        }
+    }
+    let init = match init {
+        Some(init) => init,
+        None => return nom::IResult::Error(error_position!(nom::ErrorKind::Tag, rest)),
+    };
+    // Types that don't require a header or comment parse them from
+    // input without one
+    let header = match header {
+        Some(header) => header,
+        None => try_parse!(rest, Header::read_lhe).1,
+    };
+    let comment = if !comments.is_empty() {
+        comments.remove(0)
+    } else if !after_init.is_empty() {
+        after_init.remove(0)
+    } else {
+        try_parse!(rest, Comment::read_lhe).1
+    };
+    let comments = comments
+        .into_iter()
+        .map(|comment| (CommentPosition::Start, comment))
+        .chain(
+            after_init
+                .into_iter()
+                .map(|comment| (CommentPosition::BeforeEvent(0), comment)),
+        )
+        .map(|(position, comment)| FileComment { position, comment })
+        .collect();
+    nom::IResult::Done(
+        rest,
+        (version, attributes, comment, header, init, comments),
+    )
+}
+
+/// An event, an event group or a comment between the events of an lhe
+/// file
+pub(crate) enum Entry<Comment, EventExtra> {
+    Comment(Comment),
+    Event(EventGeneric<EventExtra>),
+    /// An event group with the comments inside of it, see
+    /// `read_event_group`
+    Group(EventGroup<EventExtra>, GroupComments<Comment>),
+}
+
+/// The comments inside of an event group, with the index of the event
+/// in the group that they precede
+pub(crate) type GroupComments<Comment> = Vec<(usize, Comment)>;
+
+/// Read an event, an event group or a comment
+pub(crate) fn read_entry<Comment, EventExtra>(
+    input: &[u8],
+) -> nom::IResult<&[u8], Entry<Comment, EventExtra>>
+where
+    Comment: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    let rest = trim_start(input);
+    if rest.starts_with(b"<!--") {
+        map!(rest, read_comment::<Comment>, Entry::Comment)
+    } else if rest.starts_with(b"<eventgroup") {
+        map!(rest, read_event_group, |(group, comments)| Entry::Group(
+            group, comments
+        ))
+    } else {
+        map!(input, EventGeneric::read_lhe, Entry::Event)
+    }
+}
+
+/// Read an event group together with the comments inside of it
+///
+/// Each comment is returned with the index of the event in the group
+/// that it precedes.
+pub(crate) fn read_event_group<Comment, EventExtra>(
+    input: &[u8],
+) -> nom::IResult<&[u8], (EventGroup<EventExtra>, GroupComments<Comment>)>
+where
+    Comment: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    let (mut rest, attributes) = try_parse!(input, call!(read_opening_tag, "eventgroup"));
+    let mut events = Vec::new();
+    let mut comments = Vec::new();
+    loop {
+        rest = trim_start(rest);
+        if rest.starts_with(b"<!--") {
+            let (after, comment) = try_parse!(rest, read_comment::<Comment>);
+            comments.push((events.len(), comment));
+            rest = after;
+        } else if rest.starts_with(b"<event") && !rest.starts_with(b"<eventgroup") {
+            let (after, event) = try_parse!(rest, EventGeneric::read_lhe);
+            events.push(event);
+            rest = after;
+        } else if [&b"<!--"[..], b"<event"]
+            .iter()
+            .any(|tag| tag.starts_with(rest))
+        {
+            return nom::IResult::Incomplete(nom::Needed::Unknown);
+        } else {
+            let (rest, _) = try_parse!(rest, ws!(tag!("</eventgroup>")));
+            return nom::IResult::Done(rest, (EventGroup { attributes, events }, comments));
+        }
+    }
+}
+
+/// Read a comment that starts at the beginning of `input`
+///
+/// The comment types also accept input without a comment, so an
+/// unterminated `<!--` would be read as an empty comment that consumes
+/// nothing.
+/// This is reported as `Incomplete` if the closing `-->` may still
+/// follow, and as an error otherwise.
+fn read_comment<Comment: ReadLhe>(input: &[u8]) -> nom::IResult<&[u8], Comment> {
+    match Comment::read_lhe(input) {
+        nom::IResult::Done(rest, _) if input.len() - rest.len() < b"<!--".len() => {
+            if contains(input, b"-->") {
+                nom::IResult::Error(error_position!(nom::ErrorKind::Tag, input))
+            } else {
+                nom::IResult::Incomplete(nom::Needed::Unknown)
+            }
+        }
+        result => result,
+    }
+}
+
+/// Skip any number of xml comments and the whitespace around them
+///
+/// This is used where the contents of comments are not kept, e.g. in
+/// `EventGroup::read_lhe`.
+pub(crate) fn skip_comments(input: &[u8]) -> nom::IResult<&[u8], ()> {
+    map!(
+        input,
+        many0!(ws!(delimited!(
+            tag!("<!--"),
+            take_until!("-->"),
+            tag!("-->")
+        ))),
+        |_| ()
+    )
+}
+
+/// Walk over everything in an lhe file up to the first event to find
+/// the first error
+pub(crate) fn diagnose_file_start<Comment, Header, InitExtra>(
+    locator: &mut ErrorLocator,
+) -> Result<(), ParseError>
+where
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+{
+    if locator.skip_parsed(read_file_start::<Comment, Header, InitExtra>) {
+        return Ok(());
+    }
+    locator.set_section(Section::File);
+    locator.skip_whitespace();
+    let missing_version = locator.error(Some("version"), "a `version` attribute");
+    if split_version(diagnose_opening_tag(locator, "LesHouchesEvents")?).is_none() {
+        return Err(missing_version);
+    }
+    let mut found_init = false;
+    let mut found_header = false;
+    loop {
+        locator.skip_whitespace();
+        let remaining = locator.remaining();
+        if !found_init && remaining.starts_with(b"<init") {
+            locator.set_section(Section::Init);
+            locator.object::<InitGeneric<InitExtra>>()?;
+            found_init = true;
+        } else if !found_header && remaining.starts_with(b"<header") {
+            locator.set_section(Section::Header);
+            locator.object::<Header>()?;
+            found_header = true;
+        } else if remaining.starts_with(b"<!--") {
+            let before = remaining.len();
+            locator.set_section(Section::Comment);
+            locator.object::<Comment>()?;
+            if before - locator.remaining().len() < b"<!--".len() {
+                return Err(locator.error(None, "`-->`"));
+            }
+        } else {
+            break;
+        }
+    }
+    locator.set_section(Section::File);
+    if found_init {
+        Ok(())
+    } else {
+        Err(locator.error(None, "`<init>`"))
+    }
+}
+
+/// Find the first error in the start of an lhe file
+///
+/// If the start of the file can be walked without errors, the error is
+/// placed after it.
+pub(crate) fn locate_file_start<Comment, Header, InitExtra>(input: &[u8]) -> ParseError
+where
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+{
+    let mut locator = ErrorLocator::new(input);
+    match diagnose_file_start::<Comment, Header, InitExtra>(&mut locator) {
+        Err(err) => err,
+        Ok(()) => {
+            locator.skip_whitespace();
+            locator.error(None, "`<event>`, `<eventgroup>` or `</LesHouchesEvents>`")
+        }
+    }
 }
-fn write_closing_file_tag<W: io::Write>(writer: &mut W) -> io::Result<()> {
+
+pub(crate) fn read_closing_file_tag(input: &[u8]) -> nom::IResult<&[u8], &[u8]> {
+    ws!(input, tag!("</LesHouchesEvents>"))
+}
+
+/// Separate the required `version` attribute from the other attributes
+/// of the opening tag of an lhe file
+fn split_version(mut attributes: Attributes) -> Option<(LhefVersion, Attributes)> {
+    let version = attributes.remove("version")?;
+    Some((LhefVersion::from(version), attributes))
+}
+
+pub(crate) fn write_opening_file_tag<W: io::Write>(
+    writer: &mut W,
+    version: &str,
+    attributes: &Attributes,
+) -> io::Result<()> {
+    let mut all = Attributes::new();
+    all.insert("version", version);
+    for (name, value) in attributes {
+        all.insert(name.as_str(), value.as_str());
+    }
+    write_opening_tag(writer, "LesHouchesEvents", &all)
+}
+pub(crate) fn write_closing_file_tag<W: io::Write>(writer: &mut W) -> io::Result<()> {
     writeln!(writer, "</LesHouchesEvents>")
 }
 
@@ -256,6 +831,9 @@ pub struct InitGeneric<InitExtra> {
     pub process_info: Vec<ProcInfo>,
     /// Any additional initialization information, in a custom format
     pub extra: InitExtra,
+    /// The attributes of the `<init>` tag
+    #[cfg_attr(test, serde(default))]
+    pub attributes: Attributes,
 }
 
 impl<InitExtra> ReadLhe for InitGeneric<InitExtra>
@@ -265,7 +843,7 @@ where
     fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], InitGeneric<InitExtra>> {
         do_parse!(
             input,
-            ws!(tag!("<init>")) >> beam_1_id: ws!(parse_i64) >> beam_2_id: ws!(parse_i64)
+            attributes: call!(read_opening_tag, "init") >> beam_1_id: ws!(parse_i64) >> beam_2_id: ws!(parse_i64)
                 >> beam_1_energy: ws!(parse_f64) >> beam_2_energy: ws!(parse_f64)
                 >> beam_1_pdf_group_id: ws!(parse_i64)
                 >> beam_2_pdf_group_id: ws!(parse_i64) >> beam_1_pdf_id: ws!(parse_i64)
@@ -285,9 +863,36 @@ where
                     weighting_strategy,
                     process_info,
                     extra,
+                    attributes,
                 })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        if locator.skip_parsed(Self::read_lhe) {
+            return Ok(());
+        }
+        locator.set_section(Section::Init);
+        diagnose_opening_tag(locator, "init")?;
+        locator.field("IDBMUP(1)", INTEGER, parse_i64)?;
+        locator.field("IDBMUP(2)", INTEGER, parse_i64)?;
+        locator.field("EBMUP(1)", FLOAT, parse_f64)?;
+        locator.field("EBMUP(2)", FLOAT, parse_f64)?;
+        locator.field("PDFGUP(1)", INTEGER, parse_i64)?;
+        locator.field("PDFGUP(2)", INTEGER, parse_i64)?;
+        locator.field("PDFSUP(1)", INTEGER, parse_i64)?;
+        locator.field("PDFSUP(2)", INTEGER, parse_i64)?;
+        locator.field("IDWTUP", INTEGER, parse_i64)?;
+        let n_processes = locator.field("NPRUP", UNSIGNED, parse_u64)?;
+        for n in 0..n_processes as usize {
+            locator.set_section(Section::ProcInfo(n));
+            locator.object::<ProcInfo>()?;
+        }
+        locator.set_section(Section::InitExtra);
+        locator.object::<InitExtra>()?;
+        locator.set_section(Section::Init);
+        locator.tag("</init>")
+    }
 }
 
 impl<InitExtra> WriteLhe for InitGeneric<InitExtra>
@@ -295,7 +900,7 @@ where
     InitExtra: WriteLhe,
 {
     fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
-        writeln!(writer, "<init>")?;
+        write_opening_tag(writer, "init", &self.attributes)?;
         writeln!(
             writer,
             "{} {} {:e} {:e} {} {} {} {} {} {}",
@@ -337,6 +942,7 @@ where
             weighting_strategy: Arbitrary::arbitrary(gen),
             process_info,
             extra: Arbitrary::arbitrary(gen),
+            attributes: Arbitrary::arbitrary(gen),
         }
     }
 }
@@ -398,6 +1004,9 @@ pub struct EventGeneric<EventExtra> {
     pub particles: Vec<Particle>,
     /// Additional event information, in a custom format
     pub extra: EventExtra,
+    /// The attributes of the `<event>` tag
+    #[cfg_attr(test, serde(default))]
+    pub attributes: Attributes,
 }
 
 impl<EventExtra> ReadLhe for EventGeneric<EventExtra>
@@ -407,7 +1016,7 @@ where
     fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], EventGeneric<EventExtra>> {
         do_parse!(
             input,
-            ws!(tag!("<event>")) >> n_particles: ws!(parse_i64) >> process_id: ws!(parse_i64)
+            attributes: call!(read_opening_tag, "event") >> n_particles: ws!(parse_i64) >> process_id: ws!(parse_i64)
                 >> weight: ws!(parse_f64) >> scale: ws!(parse_f64)
                 >> alpha_ew: ws!(parse_f64) >> alpha_qcd: ws!(parse_f64)
                 >> particles: count!(Particle::read_lhe, n_particles as usize)
@@ -420,9 +1029,33 @@ where
                     alpha_qcd,
                     particles,
                     extra,
+                    attributes,
                 })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        if locator.skip_parsed(Self::read_lhe) {
+            return Ok(());
+        }
+        let n = locator.section().event().unwrap_or(0);
+        locator.set_section(Section::Event(n));
+        diagnose_opening_tag(locator, "event")?;
+        let n_particles = locator.field("NUP", UNSIGNED, parse_u64)?;
+        locator.field("IDPRUP", INTEGER, parse_i64)?;
+        locator.field("XWGTUP", FLOAT, parse_f64)?;
+        locator.field("SCALUP", FLOAT, parse_f64)?;
+        locator.field("AQEDUP", FLOAT, parse_f64)?;
+        locator.field("AQCDUP", FLOAT, parse_f64)?;
+        for m in 0..n_particles as usize {
+            locator.set_section(Section::Particle(n, m));
+            locator.object::<Particle>()?;
+        }
+        locator.set_section(Section::EventExtra(n));
+        locator.object::<EventExtra>()?;
+        locator.set_section(Section::Event(n));
+        locator.tag("</event>")
+    }
 }
 
 impl<EventExtra> WriteLhe for EventGeneric<EventExtra>
@@ -430,7 +1063,7 @@ where
     EventExtra: WriteLhe,
 {
     fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
-        writeln!(writer, "<event>")?;
+        write_opening_tag(writer, "event", &self.attributes)?;
         writeln!(
             writer,
             "{} {} {:e} {:e} {:e} {:e}",
@@ -463,6 +1096,7 @@ where
             alpha_qcd: Arbitrary::arbitrary(gen),
             particles: Arbitrary::arbitrary(gen),
             extra: Arbitrary::arbitrary(gen),
+            attributes: Arbitrary::arbitrary(gen),
         }
     }
 
@@ -476,7 +1110,8 @@ where
             self.particles.clone(),
             self.extra.clone(),
         );
-        let iter = tup.shrink().map(|x| EventGeneric {
+        let attributes = self.attributes.clone();
+        let iter = tup.shrink().map(move |x| EventGeneric {
             process_id: x.0,
             weight: x.1,
             scale: x.2,
@@ -484,11 +1119,316 @@ where
             alpha_qcd: x.4,
             particles: x.5,
             extra: x.6,
+            attributes: attributes.clone(),
         });
         Box::new(iter)
     }
 }
 
+/// The position of an `<eventgroup>` block in the events of an lhe file
+///
+/// NLO generators following version 3 of the lhe standard write real
+/// emission events together with their correlated counter-events in
+/// `<eventgroup>` blocks, e.g. `<eventgroup nreal="1" ncounter="2">`.
+/// Since most applications treat these events like any other event,
+/// they are stored in `LheFileGeneric::events` together with the
+/// events outside of groups, and the groups only refer to them.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::plain::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 0 1 1.0 91.2 0.0078 0.118
+/// </event>
+/// <eventgroup nreal=\"1\" ncounter=\"1\">
+/// <event>
+/// 0 1 2.0 91.2 0.0078 0.118
+/// </event>
+/// <event>
+/// 0 1 -2.0 91.2 0.0078 0.118
+/// </event>
+/// </eventgroup>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+/// assert_eq!(lhe.events.len(), 3);
+///
+/// let group = &lhe.groups[0];
+/// assert_eq!(group.events, 1..3);
+/// assert_eq!(group.attributes.get("ncounter"), Some("1"));
+/// assert_eq!(lhe.events[group.events.clone()][1].weight, -2.0);
+/// assert!(lhe.group_of(0).is_none());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(test, derive(Serialize, Deserialize))]
+pub struct EventGroupRange {
+    /// The attributes of the `<eventgroup>` tag
+    pub attributes: Attributes,
+    /// The range of the events in the group in `LheFileGeneric::events`
+    pub events: Range<usize>,
+}
+
+/// Check that the groups are in order, don't overlap and only refer to
+/// existing events
+fn check_groups(groups: &[EventGroupRange], n_events: usize) -> Result<(), InvalidEventGroup> {
+    let mut position = 0;
+    for (n, group) in groups.iter().enumerate() {
+        let range = &group.events;
+        if range.start < position || range.end < range.start || range.end > n_events {
+            return Err(InvalidEventGroup {
+                group: n,
+                events: range.clone(),
+                n_events,
+            });
+        }
+        position = range.end;
+    }
+    Ok(())
+}
+
+/// An error for an event group that does not fit the events of a file
+///
+/// The ranges in `LheFileGeneric::groups` have to be in order, must not
+/// overlap and may only refer to existing events.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidEventGroup {
+    /// The index of the first invalid group in `LheFileGeneric::groups`
+    pub group: usize,
+    /// The range of the events in the invalid group
+    pub events: Range<usize>,
+    /// The number of events in the file
+    pub n_events: usize,
+}
+
+impl fmt::Display for InvalidEventGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid event group {} with the events {}..{} in a file with {} events",
+            self.group, self.events.start, self.events.end, self.n_events
+        )
+    }
+}
+
+impl error::Error for InvalidEventGroup {
+    fn description(&self) -> &str {
+        "Invalid event group"
+    }
+}
+
+/// An `<eventgroup>` block of correlated events
+///
+/// This is used when reading or writing event groups one at a time,
+/// e.g. by the [`LheReader`] and the [`LheWriter`].
+/// Comments inside of the group are not part of it: `read_lhe` skips
+/// them, while `LheFileGeneric` and the `LheReader` keep them as
+/// `FileComment`s with the position `CommentPosition::InGroup`.
+///
+/// [`LheReader`]: ../reader/struct.LheReader.html
+/// [`LheWriter`]: ../writer/struct.LheWriter.html
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(Serialize, Deserialize))]
+pub struct EventGroup<EventExtra> {
+    /// The attributes of the `<eventgroup>` tag
+    pub attributes: Attributes,
+    /// The events in the group
+    pub events: Vec<EventGeneric<EventExtra>>,
+}
+
+impl<EventExtra> EventGroup<EventExtra> {
+    /// The number of real emission events in the group (`nreal`)
+    pub fn n_real(&self) -> Option<usize> {
+        self.attributes.get("nreal")?.trim().parse().ok()
+    }
+
+    /// The number of counter-events in the group (`ncounter`)
+    pub fn n_counter(&self) -> Option<usize> {
+        self.attributes.get("ncounter")?.trim().parse().ok()
+    }
+}
+
+impl<EventExtra> ReadLhe for EventGroup<EventExtra>
+where
+    EventExtra: ReadLhe,
+{
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], EventGroup<EventExtra>> {
+        do_parse!(
+            input,
+            attributes: call!(read_opening_tag, "eventgroup")
+                >> events: many0!(preceded!(skip_comments, EventGeneric::read_lhe))
+                >> skip_comments >> ws!(tag!("</eventgroup>"))
+                >> (EventGroup { attributes, events })
+        )
+    }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        if locator.skip_parsed(Self::read_lhe) {
+            return Ok(());
+        }
+        let mut n = locator.section().event().unwrap_or(0);
+        locator.set_section(Section::File);
+        diagnose_opening_tag(locator, "eventgroup")?;
+        loop {
+            locator.skip_whitespace();
+            let remaining = locator.remaining();
+            if remaining.starts_with(b"<!--") {
+                if !locator.skip_parsed(skip_comments) {
+                    return Err(locator.error(None, "a comment ending with `-->`"));
+                }
+            } else if remaining.starts_with(b"<event") && !remaining.starts_with(b"<eventgroup") {
+                locator.set_section(Section::Event(n));
+                locator.object::<EventGeneric<EventExtra>>()?;
+                locator.set_section(Section::File);
+                n += 1;
+            } else {
+                return locator
+                    .tag("</eventgroup>")
+                    .map_err(|_| locator.error(None, "`<event>` or `</eventgroup>`"));
+            }
+        }
+    }
+}
+
+impl<EventExtra> WriteLhe for EventGroup<EventExtra>
+where
+    EventExtra: WriteLhe,
+{
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_opening_tag(writer, "eventgroup", &self.attributes)?;
+        for event in &self.events {
+            event.write_lhe(writer)?;
+        }
+        writeln!(writer, "</eventgroup>")
+    }
+}
+
+#[cfg(test)]
+impl<EventExtra> Arbitrary for EventGroup<EventExtra>
+where
+    EventExtra: Arbitrary,
+{
+    fn arbitrary<G: Gen>(gen: &mut G) -> EventGroup<EventExtra> {
+        // Groups in real files only contain a few events
+        let n_events = gen.gen_range(0, 4);
+        EventGroup {
+            attributes: Arbitrary::arbitrary(gen),
+            events: (0..n_events).map(|_| Arbitrary::arbitrary(gen)).collect(),
+        }
+    }
+
+    fn shrink(&self) -> Box<Iterator<Item = EventGroup<EventExtra>>> {
+        let attributes = self.attributes.clone();
+        let iter = self.events.shrink().map(move |events| EventGroup {
+            attributes: attributes.clone(),
+            events,
+        });
+        Box::new(iter)
+    }
+}
+
+/// A single event or a group of events in the event section of an lhe
+/// file
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(test, derive(Serialize, Deserialize))]
+pub enum EventItem<EventExtra> {
+    /// An event outside of any `<eventgroup>`
+    Event(EventGeneric<EventExtra>),
+    /// An `<eventgroup>` with its events
+    Group(EventGroup<EventExtra>),
+}
+
+impl<EventExtra> EventItem<EventExtra> {
+    /// The events of this item
+    ///
+    /// This is a single event or all events in the group.
+    pub fn events(&self) -> &[EventGeneric<EventExtra>] {
+        match *self {
+            EventItem::Event(ref event) => slice::from_ref(event),
+            EventItem::Group(ref group) => &group.events,
+        }
+    }
+
+    /// The attributes of the group, if this item is a group
+    pub fn group_attributes(&self) -> Option<&Attributes> {
+        match *self {
+            EventItem::Event(_) => None,
+            EventItem::Group(ref group) => Some(&group.attributes),
+        }
+    }
+}
+
+impl<EventExtra> ReadLhe for EventItem<EventExtra>
+where
+    EventExtra: ReadLhe,
+{
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], EventItem<EventExtra>> {
+        alt!(
+            input,
+            map!(EventGroup::read_lhe, EventItem::Group)
+                | map!(EventGeneric::read_lhe, EventItem::Event)
+        )
+    }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        diagnose_item::<EventExtra>(locator).map(|_| ())
+    }
+}
+
+/// Walk over an event or an event group, returning the number of events
+fn diagnose_item<EventExtra: ReadLhe>(locator: &mut ErrorLocator) -> Result<usize, ParseError> {
+    locator.skip_whitespace();
+    if !locator.remaining().starts_with(b"<eventgroup") {
+        return locator.object::<EventGeneric<EventExtra>>().map(|_| 1);
+    }
+    if let Some(group) = locator.parsed(EventGroup::<EventExtra>::read_lhe) {
+        return Ok(group.events.len());
+    }
+    locator.object::<EventGroup<EventExtra>>()?;
+    Err(locator.error(None, "valid input"))
+}
+
+impl<EventExtra> WriteLhe for EventItem<EventExtra>
+where
+    EventExtra: WriteLhe,
+{
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match *self {
+            EventItem::Event(ref event) => event.write_lhe(writer),
+            EventItem::Group(ref group) => group.write_lhe(writer),
+        }
+    }
+}
+
+#[cfg(test)]
+impl<EventExtra> Arbitrary for EventItem<EventExtra>
+where
+    EventExtra: Arbitrary,
+{
+    fn arbitrary<G: Gen>(gen: &mut G) -> EventItem<EventExtra> {
+        if gen.gen_weighted_bool(4) {
+            EventItem::Group(Arbitrary::arbitrary(gen))
+        } else {
+            EventItem::Event(Arbitrary::arbitrary(gen))
+        }
+    }
+
+    fn shrink(&self) -> Box<Iterator<Item = EventItem<EventExtra>>> {
+        match *self {
+            EventItem::Event(ref event) => Box::new(event.shrink().map(EventItem::Event)),
+            EventItem::Group(ref group) => Box::new(group.shrink().map(EventItem::Group)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use nom;
@@ -498,7 +1438,12 @@ mod tests {
     use {Particle, ProcInfo, ReadLhe, WriteLhe};
     use lorentz_vector::LorentzVector;
 
-    use super::{EventGeneric, InitGeneric, LheFileGeneric};
+    use attributes::Attributes;
+    use string;
+    use version::LhefVersion;
+
+    use super::{CommentPosition, EventGeneric, EventGroup, EventGroupRange, EventItem,
+                InitGeneric, InvalidEventGroup, LheFileGeneric};
 
     #[derive(Clone, Debug, PartialEq)]
     struct Nothing {}
@@ -551,6 +1496,7 @@ mod tests {
                 },
             ],
             extra: Nothing {},
+            attributes: Attributes::new(),
         };
         let result = InitGeneric::<Nothing>::read_lhe(bytes)
             .to_full_result()
@@ -609,6 +1555,7 @@ mod tests {
                 },
             ],
             extra: Nothing {},
+            attributes: Attributes::new(),
         };
         let result = EventGeneric::<Nothing>::read_lhe(bytes)
             .to_full_result()
@@ -636,7 +1583,8 @@ mod tests {
 </event>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Nothing {},
             header: Nothing {},
             init: InitGeneric {
@@ -664,6 +1612,7 @@ mod tests {
                     },
                 ],
                 extra: Nothing {},
+                attributes: Attributes::new(),
             },
             events: vec![
                 EventGeneric {
@@ -709,6 +1658,7 @@ mod tests {
                         },
                     ],
                     extra: Nothing {},
+                    attributes: Attributes::new(),
                 },
                 EventGeneric {
                     process_id: 33,
@@ -736,8 +1686,11 @@ mod tests {
                         },
                     ],
                     extra: Nothing {},
+                    attributes: Attributes::new(),
                 },
             ],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFileGeneric::<Nothing, Nothing, Nothing, Nothing>::read_lhe(bytes)
             .to_full_result()
@@ -798,13 +1751,17 @@ mod tests {
             beam_2_energy: 9.,
             process_info: Vec::new(),
             extra: Nothing {},
+            attributes: Attributes::new(),
         };
         let start = LheFileGeneric {
-            version: version.to_string(),
+            version: LhefVersion::from(version),
+            attributes: Attributes::new(),
             comment: Nothing {},
             header: Nothing {},
             init,
             events: Vec::new(),
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let mut bytes = Vec::new();
         start.write_lhe(&mut bytes).unwrap();
@@ -835,13 +1792,17 @@ mod tests {
             beam_2_energy: 9.,
             process_info: Vec::new(),
             extra: Nothing {},
+            attributes: Attributes::new(),
         };
         let start = LheFileGeneric {
-            version: version.to_string(),
+            version: LhefVersion::from(version),
+            attributes: Attributes::new(),
             comment: Nothing {},
             header: Nothing {},
             init,
             events: Vec::new(),
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let mut bytes = Vec::new();
         start.write_lhe(&mut bytes).unwrap();
@@ -857,17 +1818,55 @@ mod tests {
         assert_eq!(start, round);
     }
 
+    #[test]
+    fn read_attributes() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\" generator='test'>
+<init weights=\"2\">
+1 2 3. 4. 5 6 7 8 9 0
+</init>
+<event npLO=\"2\" npNLO=\"1\">
+0 1 1. 2. 3. 4.
+</event>
+</LesHouchesEvents>
+";
+        let lhe = LheFileGeneric::<Nothing, Nothing, Nothing, Nothing>::read_lhe(bytes)
+            .to_full_result()
+            .unwrap();
+        assert_eq!(lhe.version, LhefVersion::V3);
+        assert_eq!(lhe.attributes.get("generator"), Some("test"));
+        assert_eq!(lhe.attributes.get("version"), None);
+        assert_eq!(lhe.init.attributes.get("weights"), Some("2"));
+        let names: Vec<_> = lhe.events[0]
+            .attributes
+            .iter()
+            .map(|entry| entry.0.as_str())
+            .collect();
+        assert_eq!(names, vec!["npLO", "npNLO"]);
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        assert!(written.starts_with(b"<LesHouchesEvents version=\"3.0\" generator=\"test\">\n"));
+        let round = LheFileGeneric::<Nothing, Nothing, Nothing, Nothing>::read_lhe(&written)
+            .to_full_result()
+            .unwrap();
+        assert_eq!(lhe, round);
+    }
+
     quickcheck! {
         fn lhefile_roundtrip_noevents_qc(version: String, init: InitGeneric<Nothing>) -> quickcheck::TestResult {
             if version.contains('"') {
                 return quickcheck::TestResult::discard();
             }
             let start = LheFileGeneric {
-                version,
+                version: LhefVersion::from(version),
+                attributes: Attributes::new(),
                 comment: Nothing {},
                 header: Nothing {},
                 init,
                 events: Vec::new(),
+                groups: Vec::new(),
+                comments: Vec::new(),
             };
             let mut bytes = Vec::new();
             start.write_lhe(&mut bytes).unwrap();
@@ -896,4 +1895,159 @@ mod tests {
             quickcheck::TestResult::from_bool(start == round)
         }
     }
+
+    #[test]
+    fn read_event_groups() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+1 2 3. 4. 5 6 7 8 9 0
+</init>
+<eventgroup nreal=\"1\" ncounter=\"2\">
+<event>
+0 1 1. 2. 3. 4.
+</event>
+<event>
+0 2 1. 2. 3. 4.
+</event>
+<event>
+0 3 1. 2. 3. 4.
+</event>
+</eventgroup>
+<event>
+0 4 1. 2. 3. 4.
+</event>
+<eventgroup>
+</eventgroup>
+<eventgroup nreal=\"1\" ncounter=\"0\">
+<event>
+0 5 1. 2. 3. 4.
+</event>
+</eventgroup>
+</LesHouchesEvents>
+";
+        let lhe = LheFileGeneric::<Nothing, Nothing, Nothing, Nothing>::read_lhe(bytes)
+            .to_full_result()
+            .unwrap();
+        let ids: Vec<_> = lhe.events.iter().map(|e| e.process_id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        let ranges: Vec<_> = lhe.groups.iter().map(|g| g.events.clone()).collect();
+        assert_eq!(ranges, vec![0..3, 4..4, 4..5]);
+        assert_eq!(lhe.groups[0].attributes.get("ncounter"), Some("2"));
+        assert_eq!(lhe.group_of(2), Some(&lhe.groups[0]));
+        assert_eq!(lhe.group_of(3), None);
+        assert_eq!(lhe.group_of(4), Some(&lhe.groups[2]));
+
+        let items = lhe.clone().into_items().unwrap();
+        assert_eq!(items.len(), 4);
+        match items[0] {
+            EventItem::Group(ref group) => {
+                assert_eq!(group.n_real(), Some(1));
+                assert_eq!(group.n_counter(), Some(2));
+                assert_eq!(group.events.len(), 3);
+            }
+            EventItem::Event(_) => panic!("expected an event group"),
+        }
+        assert_eq!(items[1].events()[0].process_id, 4);
+        assert_eq!(items[1].group_attributes(), None);
+        assert_eq!(items[2].events().len(), 0);
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        let round = LheFileGeneric::<Nothing, Nothing, Nothing, Nothing>::read_lhe(&written)
+            .to_full_result()
+            .unwrap();
+        assert_eq!(lhe, round);
+    }
+
+    #[test]
+    fn comments_in_event_groups() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+1 2 3. 4. 5 6 7 8 9 0
+</init>
+<event>
+0 1 1. 2. 3. 4.
+</event>
+<!-- before the group -->
+<eventgroup nreal=\"1\" ncounter=\"1\">
+<!-- first -->
+<event>
+0 2 1. 2. 3. 4.
+</event>
+<!-- second -->
+<event>
+0 3 1. 2. 3. 4.
+</event>
+<!-- end -->
+</eventgroup>
+</LesHouchesEvents>
+";
+        let lhe = LheFileGeneric::<string::Comment, Nothing, Nothing, Nothing>::read_lhe(bytes)
+            .to_full_result()
+            .unwrap();
+        let positions: Vec<_> = lhe.comments.iter().map(|c| c.position).collect();
+        assert_eq!(
+            positions,
+            vec![
+                CommentPosition::BeforeEvent(1),
+                CommentPosition::InGroup(0, 1),
+                CommentPosition::InGroup(0, 2),
+                CommentPosition::InGroup(0, 3),
+            ]
+        );
+        assert_eq!(lhe.comments[3].comment.comment, Some("end".to_string()));
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        let written = str::from_utf8(&written).unwrap();
+        let start = written.find("<eventgroup").unwrap();
+        let end = written.find("</eventgroup>").unwrap();
+        assert!(written.find("\nbefore the group\n").unwrap() < start);
+        for comment in &["first", "second", "end"] {
+            let position = written.find(&format!("\n{}\n", comment)).unwrap();
+            assert!(start < position && position < end);
+        }
+        let round = LheFileGeneric::<string::Comment, Nothing, Nothing, Nothing>::read_lhe(
+            written.as_bytes(),
+        ).to_full_result()
+            .unwrap();
+        assert_eq!(lhe, round);
+    }
+
+    #[test]
+    fn write_invalid_groups() {
+        let mut lhe = LheFileGeneric::<Nothing, Nothing, Nothing, Nothing>::read_lhe(
+            b"<LesHouchesEvents version=\"3.0\"><init>1 2 3. 4. 5 6 7 8 9 0</init></LesHouchesEvents>",
+        ).to_full_result()
+            .unwrap();
+        lhe.groups.push(EventGroupRange {
+            attributes: Attributes::new(),
+            events: 0..1,
+        });
+        let mut written = Vec::new();
+        assert!(lhe.write_lhe(&mut written).is_err());
+        assert!(written.is_empty());
+        assert_eq!(
+            lhe.into_items(),
+            Err(InvalidEventGroup {
+                group: 0,
+                events: 0..1,
+                n_events: 0,
+            })
+        );
+    }
+
+    quickcheck! {
+        fn event_group_roundtrip_qc(start: EventGroup<Nothing>) -> bool {
+            let mut bytes = Vec::new();
+            start.write_lhe(&mut bytes).unwrap();
+            let round = match EventGroup::<Nothing>::read_lhe(&bytes).to_full_result() {
+                Ok(r) => r,
+                Err(err) => panic!("Failed to read roundtrip: {:?}", err),
+            };
+            start == round
+        }
+    }
 }
@@ -67,8 +67,11 @@
 //! ```
 
 use {PdgId, ReadLhe, WriteLhe};
+use error::{diagnose_permutation, ErrorLocator, ParseError, FLOAT, INTEGER, INTEGER_8, UNSIGNED,
+            UNSIGNED_8};
 use generic::LheFileGeneric;
 use nom_util::{parse_f64, parse_i64, parse_i8, parse_u64, parse_u8};
+use version::Downgrade;
 
 use nom;
 use std::io;
@@ -347,6 +350,8 @@ impl WriteLhe for Comment {
     }
 }
 
+impl Downgrade for Comment {}
+
 #[cfg(test)]
 impl Arbitrary for Comment {
     fn arbitrary<G: Gen>(gen: &mut G) -> Comment {
@@ -403,6 +408,8 @@ impl WriteLhe for Header {
     }
 }
 
+impl Downgrade for Header {}
+
 #[cfg(test)]
 impl Arbitrary for Header {
     fn arbitrary<G: Gen>(_gen: &mut G) -> Header {
@@ -466,6 +473,17 @@ impl ReadLhe for PdfSum {
                 >> (PdfSum { pdf_sum_pairs })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("SUMPDF")?;
+        let n = locator.field("n", UNSIGNED, parse_u64)?;
+        for i in 0..n {
+            locator.field(&format!("pdf_sum_pairs[{}].0", i), INTEGER, parse_i64)?;
+            locator.field(&format!("pdf_sum_pairs[{}].1", i), INTEGER, parse_i64)?;
+        }
+        Ok(())
+    }
 }
 
 impl WriteLhe for PdfSum {
@@ -478,6 +496,8 @@ impl WriteLhe for PdfSum {
     }
 }
 
+impl Downgrade for PdfSum {}
+
 #[cfg(test)]
 impl Arbitrary for PdfSum {
     fn arbitrary<G: Gen>(gen: &mut G) -> PdfSum {
@@ -548,6 +568,15 @@ impl ReadLhe for PdfInfo {
                 >> scale: ws!(parse_f64) >> (PdfInfo { x1, x2, scale })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("pdf")?;
+        locator.field("x1", FLOAT, parse_f64)?;
+        locator.field("x2", FLOAT, parse_f64)?;
+        locator.field("scale", FLOAT, parse_f64)?;
+        Ok(())
+    }
 }
 
 impl WriteLhe for PdfInfo {
@@ -624,6 +653,17 @@ impl ReadLhe for InitExtraRS {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        diagnose_permutation(
+            locator,
+            &[
+                ("SUMPDF", PdfSum::diagnose_lhe),
+                ("DIPMAP", DipMapInfo::diagnose_lhe),
+                ("JETALGO", JetAlgoInfo::diagnose_lhe),
+            ],
+        )
+    }
 }
 
 impl WriteLhe for InitExtraRS {
@@ -634,6 +674,8 @@ impl WriteLhe for InitExtraRS {
     }
 }
 
+impl Downgrade for InitExtraRS {}
+
 #[cfg(test)]
 impl Arbitrary for InitExtraRS {
     fn arbitrary<G: Gen>(gen: &mut G) -> InitExtraRS {
@@ -729,7 +771,7 @@ impl ReadLhe for JetAlgoInfo {
             ws!(tag!("#")) >> ws!(tag!("JETALGO")) >> algorithm_id: ws!(parse_i8)
                 >> n_bjets: ws!(parse_u8) >> eta_max: ws!(parse_f64)
                 >> dr: ws!(parse_f64)
-                >> has_pt_veto: ws!(alt!(tag!("T") => {|_| true} | tag!("F") => {|_| false}))
+                >> has_pt_veto: ws!(parse_flag)
                 >> pt_veto: ws!(parse_f64) >> (JetAlgoInfo {
                 algorithm_id,
                 n_bjets,
@@ -739,8 +781,22 @@ impl ReadLhe for JetAlgoInfo {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("JETALGO")?;
+        locator.field("algorithm_id", INTEGER_8, parse_i8)?;
+        locator.field("n_bjets", UNSIGNED_8, parse_u8)?;
+        locator.field("eta_max", FLOAT, parse_f64)?;
+        locator.field("dr", FLOAT, parse_f64)?;
+        locator.field("has_pt_veto", "`T` or `F`", parse_flag)?;
+        locator.field("pt_veto", FLOAT, parse_f64)?;
+        Ok(())
+    }
 }
 
+named!(parse_flag<bool>, alt!(tag!("T") => {|_| true} | tag!("F") => {|_| false}));
+
 impl WriteLhe for JetAlgoInfo {
     fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
         writeln!(
@@ -901,6 +957,18 @@ impl ReadLhe for DipMapInfo {
                 })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("DIPMAP")?;
+        locator.field("dipole_type", INTEGER_8, parse_i8)?;
+        let n = locator.field("ndipoles", UNSIGNED_8, parse_u8)?;
+        for i in 0..n {
+            locator.field(&format!("dipole_map[{}].0", i), INTEGER_8, parse_i8)?;
+            locator.field(&format!("dipole_map[{}].1", i), INTEGER_8, parse_i8)?;
+        }
+        Ok(())
+    }
 }
 
 impl WriteLhe for DipMapInfo {
@@ -995,6 +1063,17 @@ impl ReadLhe for EventExtraRS {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        diagnose_permutation(
+            locator,
+            &[
+                ("pdf", PdfInfo::diagnose_lhe),
+                ("me", MeInfoRS::diagnose_lhe),
+                ("jet", JetInfo::diagnose_lhe),
+            ],
+        )
+    }
 }
 
 impl WriteLhe for EventExtraRS {
@@ -1005,6 +1084,8 @@ impl WriteLhe for EventExtraRS {
     }
 }
 
+impl Downgrade for EventExtraRS {}
+
 #[cfg(test)]
 impl Arbitrary for EventExtraRS {
     fn arbitrary<G: Gen>(gen: &mut G) -> EventExtraRS {
@@ -1151,6 +1232,30 @@ impl ReadLhe for MeInfoRS {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("me")?;
+        locator.field("weight", FLOAT, parse_f64)?;
+        locator.field("max_ew", UNSIGNED_8, parse_u8)?;
+        locator.field("max_qcd", UNSIGNED_8, parse_u8)?;
+        locator.field("real_weight", FLOAT, parse_f64)?;
+        locator.field("scale", FLOAT, parse_f64)?;
+        let irun = locator.field("irun", UNSIGNED_8, parse_u8)?;
+        let n = locator.field("num_dipoles", UNSIGNED_8, parse_u8)?;
+        for i in 0..n {
+            locator.field(&format!("dipole_ids[{}]", i), INTEGER_8, parse_i8)?;
+        }
+        for i in 0..n {
+            locator.field(&format!("dipole_weights[{}]", i), FLOAT, parse_f64)?;
+        }
+        if irun > 0 {
+            for i in 0..n {
+                locator.field(&format!("dipole_mu_rs[{}]", i), FLOAT, parse_f64)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl WriteLhe for MeInfoRS {
@@ -1291,6 +1396,15 @@ impl ReadLhe for JetInfo {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("jet")?;
+        locator.field("ibvjet1", INTEGER_8, parse_i8)?;
+        locator.field("ibvjet2", INTEGER_8, parse_i8)?;
+        locator.field("ibvflreco", INTEGER_8, parse_i8)?;
+        Ok(())
+    }
 }
 
 impl WriteLhe for JetInfo {
@@ -1373,6 +1487,13 @@ impl ReadLhe for EventExtraI {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        diagnose_permutation(
+            locator,
+            &[("pdf", PdfInfo::diagnose_lhe), ("me", MeInfoI::diagnose_lhe)],
+        )
+    }
 }
 
 impl WriteLhe for EventExtraI {
@@ -1382,6 +1503,8 @@ impl WriteLhe for EventExtraI {
     }
 }
 
+impl Downgrade for EventExtraI {}
+
 #[cfg(test)]
 impl Arbitrary for EventExtraI {
     fn arbitrary<G: Gen>(gen: &mut G) -> EventExtraI {
@@ -1480,6 +1603,18 @@ impl ReadLhe for MeInfoI {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("me")?;
+        locator.field("max_ew", UNSIGNED_8, parse_u8)?;
+        locator.field("max_qcd", UNSIGNED_8, parse_u8)?;
+        for field in &["weight", "coeff_a", "coeff_b", "coeff_c"] {
+            locator.field(field, FLOAT, parse_f64)?;
+        }
+        locator.field("log_term", INTEGER_8, parse_i8)?;
+        Ok(())
+    }
 }
 
 impl WriteLhe for MeInfoI {
@@ -1584,6 +1719,24 @@ impl ReadLhe for PdfSumKP {
                 })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("SUMPDF")?;
+        locator.field("n_pdf_g_1", UNSIGNED, parse_u64)?;
+        let n_pdf_q_1 = locator.field("n_pdf_q_1", UNSIGNED, parse_u64)?;
+        locator.field("n_pdf_g_2", UNSIGNED, parse_u64)?;
+        let n_pdf_q_2 = locator.field("n_pdf_q_2", UNSIGNED, parse_u64)?;
+        locator.field("beam_1_gluon_id", INTEGER, parse_i64)?;
+        for i in 0..n_pdf_q_1 {
+            locator.field(&format!("beam_1_quark_ids[{}]", i), INTEGER, parse_i64)?;
+        }
+        locator.field("beam_2_gluon_id", INTEGER, parse_i64)?;
+        for i in 0..n_pdf_q_2 {
+            locator.field(&format!("beam_2_quark_ids[{}]", i), INTEGER, parse_i64)?;
+        }
+        Ok(())
+    }
 }
 
 impl WriteLhe for PdfSumKP {
@@ -1608,6 +1761,8 @@ impl WriteLhe for PdfSumKP {
     }
 }
 
+impl Downgrade for PdfSumKP {}
+
 #[cfg(test)]
 impl Arbitrary for PdfSumKP {
     fn arbitrary<G: Gen>(gen: &mut G) -> PdfSumKP {
@@ -1679,6 +1834,13 @@ impl ReadLhe for EventExtraKP {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        diagnose_permutation(
+            locator,
+            &[("pdf", PdfInfo::diagnose_lhe), ("me", MeInfoKP::diagnose_lhe)],
+        )
+    }
 }
 
 impl WriteLhe for EventExtraKP {
@@ -1688,6 +1850,8 @@ impl WriteLhe for EventExtraKP {
     }
 }
 
+impl Downgrade for EventExtraKP {}
+
 #[cfg(test)]
 impl Arbitrary for EventExtraKP {
     fn arbitrary<G: Gen>(gen: &mut G) -> EventExtraKP {
@@ -1816,6 +1980,37 @@ impl ReadLhe for MeInfoKP {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("me")?;
+        locator.field("max_ew", UNSIGNED_8, parse_u8)?;
+        locator.field("max_qcd", UNSIGNED_8, parse_u8)?;
+        for field in &[
+            "weight",
+            "x1_prime",
+            "x2_prime",
+            "weight_a1g_l0",
+            "weight_a1g_l1",
+            "weight_a1q_l0",
+            "weight_a1q_l1",
+            "weight_b1g_l0",
+            "weight_b1g_l1",
+            "weight_b1q_l0",
+            "weight_b1q_l1",
+            "weight_a2g_l0",
+            "weight_a2g_l1",
+            "weight_a2q_l0",
+            "weight_a2q_l1",
+            "weight_b2g_l0",
+            "weight_b2g_l1",
+            "weight_b2q_l0",
+            "weight_b2q_l1",
+        ] {
+            locator.field(field, FLOAT, parse_f64)?;
+        }
+        Ok(())
+    }
 }
 
 impl WriteLhe for MeInfoKP {
@@ -1938,6 +2133,13 @@ impl ReadLhe for InitExtra1loop {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        diagnose_permutation(
+            locator,
+            &[("SUMPDF", PdfSum::diagnose_lhe), ("NORM", Norm::diagnose_lhe)],
+        )
+    }
 }
 
 impl WriteLhe for InitExtra1loop {
@@ -1947,6 +2149,8 @@ impl WriteLhe for InitExtra1loop {
     }
 }
 
+impl Downgrade for InitExtra1loop {}
+
 #[cfg(test)]
 impl Arbitrary for InitExtra1loop {
     fn arbitrary<G: Gen>(gen: &mut G) -> InitExtra1loop {
@@ -2015,6 +2219,15 @@ impl ReadLhe for Norm {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("NORM")?;
+        locator.field("n_unweighted_events", UNSIGNED, parse_u64)?;
+        locator.field("alpha", FLOAT, parse_f64)?;
+        locator.field("alpha_err", FLOAT, parse_f64)?;
+        Ok(())
+    }
 }
 
 impl WriteLhe for Norm {
@@ -2101,6 +2314,13 @@ impl ReadLhe for EventExtra1loop {
                 })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        diagnose_permutation(
+            locator,
+            &[("pdf", PdfInfo::diagnose_lhe), ("me", MeInfo1loop::diagnose_lhe)],
+        )
+    }
 }
 
 impl WriteLhe for EventExtra1loop {
@@ -2110,6 +2330,8 @@ impl WriteLhe for EventExtra1loop {
     }
 }
 
+impl Downgrade for EventExtra1loop {}
+
 #[cfg(test)]
 impl Arbitrary for EventExtra1loop {
     fn arbitrary<G: Gen>(gen: &mut G) -> EventExtra1loop {
@@ -2219,6 +2441,20 @@ impl ReadLhe for MeInfo1loop {
                 })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#")?;
+        locator.tag("me")?;
+        locator.field("max_ew_lo", INTEGER, parse_i64)?;
+        locator.field("max_qcd_lo", INTEGER, parse_i64)?;
+        locator.field("weight_lo", FLOAT, parse_f64)?;
+        locator.field("max_ew_1loop", INTEGER, parse_i64)?;
+        locator.field("max_qcd_1loop", INTEGER, parse_i64)?;
+        for field in &["weight_1loop", "coeff_a", "coeff_b", "coeff_c"] {
+            locator.field(field, FLOAT, parse_f64)?;
+        }
+        Ok(())
+    }
 }
 
 impl WriteLhe for MeInfo1loop {
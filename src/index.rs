@@ -0,0 +1,612 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Random access to the events in lhe files
+//!
+//! An [`EventIndex`] stores the byte offsets of all `<event>` blocks in
+//! a file.
+//! It can be used to read single events or ranges of events by their
+//! number, without parsing all the events before them.
+//! The events in `<eventgroup>` blocks are indexed like any other event,
+//! so their numbers are the same as in `LheFileGeneric::events`.
+//! Since building the index requires reading the whole file once, the
+//! index can be saved to a sidecar file next to the event file and
+//! loaded again later, as long as the event file doesn't change.
+//!
+//! For compressed files the offsets refer to the decompressed content.
+//! Reading an event from a compressed file still requires decompressing
+//! everything before it, but the events before it are not parsed.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::index::EventIndex;
+//! use lhef::plain::EventExtra;
+//!
+//! let index = EventIndex::build_from_file(&"events.lhe").unwrap();
+//! index.save(&EventIndex::sidecar_path(&"events.lhe")).unwrap();
+//!
+//! let event = index.read_event::<_, EventExtra>(&"events.lhe", 1234566).unwrap();
+//! ```
+//!
+//! [`EventIndex`]: struct.EventIndex.html
+
+use compression;
+use error::{locate, ErrorLocator, Section};
+use generic::EventGeneric;
+use reader::{contains, count_lines, trim_start};
+use ReadError;
+use ReadLhe;
+
+use nom;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Read, Seek, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+const INDEX_HEADER: &str = "# lhef event index";
+
+/// The byte offsets of the events in an lhe file
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::index::EventIndex;
+/// use lhef::string::EventExtra;
+/// use std::io::Cursor;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"1.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// </event>
+/// <event>
+/// 2 1 2.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 2.0 2.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -2.0 2.0 0.0 0. 9.
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let index = EventIndex::build(&bytes[..]).unwrap();
+/// assert_eq!(index.len(), 2);
+///
+/// let event = index
+///     .read_event_from::<_, EventExtra>(&mut Cursor::new(&bytes[..]), 1)
+///     .unwrap();
+/// assert_eq!(event.weight, 2.0);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventIndex {
+    events: Vec<Range<u64>>,
+    file: Option<FileStamp>,
+}
+
+/// The length and modification time of an event file, to recognize
+/// whether a saved index belongs to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileStamp {
+    length: u64,
+    modified: Option<Duration>,
+}
+
+impl FileStamp {
+    fn of<P: AsRef<Path>>(path: &P) -> io::Result<FileStamp> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+        Ok(FileStamp {
+            length: metadata.len(),
+            modified,
+        })
+    }
+
+    /// Parse the fields of a `file` line in a saved index
+    fn parse<'a, I: Iterator<Item = &'a str>>(fields: I) -> Option<FileStamp> {
+        let fields: Vec<u64> = fields
+            .map(|field| field.parse().ok())
+            .collect::<Option<_>>()?;
+        match fields[..] {
+            [length] => Some(FileStamp {
+                length,
+                modified: None,
+            }),
+            [length, secs, nanos] if nanos < 1_000_000_000 => Some(FileStamp {
+                length,
+                modified: Some(Duration::new(secs, nanos as u32)),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl EventIndex {
+    /// Build the index by scanning the content of `reader`
+    ///
+    /// The reader has to be positioned at the start of the file.
+    /// The events are only located, not parsed.
+    pub fn build<R: BufRead>(mut reader: R) -> io::Result<EventIndex> {
+        let mut events = Vec::new();
+        let mut line = Vec::new();
+        let mut position = 0;
+        let mut start = None;
+        loop {
+            line.clear();
+            let len = reader.read_until(b'\n', &mut line)? as u64;
+            if len == 0 {
+                break;
+            }
+            if start.is_none() && is_event_start(&line) {
+                start = Some(position);
+            }
+            position += len;
+            if contains(&line, b"</event>") {
+                if let Some(start) = start.take() {
+                    events.push(start..position);
+                }
+            }
+        }
+        Ok(EventIndex { events, file: None })
+    }
+
+    /// Build the index for the file at `path`
+    ///
+    /// Compressed files are decompressed on the fly, see the
+    /// [`compression`] module.
+    /// The length and modification time of the file are stored with
+    /// the index, so that [`load`] can check that a saved index still
+    /// belongs to the file.
+    ///
+    /// [`compression`]: ../compression/index.html
+    /// [`load`]: #method.load
+    pub fn build_from_file<P: AsRef<Path>>(path: &P) -> io::Result<EventIndex> {
+        let file = FileStamp::of(path)?;
+        let mut index = EventIndex::build(compression::open(path)?)?;
+        index.file = Some(file);
+        Ok(index)
+    }
+
+    /// The path of the sidecar file for the index of the file at `path`
+    ///
+    /// This is the path of the event file with `.idx` appended.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lhef::index::EventIndex;
+    /// use std::path::PathBuf;
+    ///
+    /// assert_eq!(
+    ///     EventIndex::sidecar_path(&"run/events.lhe.gz"),
+    ///     PathBuf::from("run/events.lhe.gz.idx")
+    /// );
+    /// ```
+    pub fn sidecar_path<P: AsRef<Path>>(path: &P) -> PathBuf {
+        let mut name = OsString::from(path.as_ref());
+        name.push(".idx");
+        PathBuf::from(name)
+    }
+
+    /// Write the index to the file at `path`
+    ///
+    /// The index is stored as text, with the start and end offsets of
+    /// one event per line.
+    /// For an index built by [`build_from_file`], the length and
+    /// modification time of the event file are written before the
+    /// events.
+    ///
+    /// [`build_from_file`]: #method.build_from_file
+    pub fn save<P: AsRef<Path>>(&self, path: &P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "{}", INDEX_HEADER)?;
+        match self.file {
+            Some(FileStamp {
+                length,
+                modified: Some(modified),
+            }) => writeln!(
+                file,
+                "file {} {} {}",
+                length,
+                modified.as_secs(),
+                modified.subsec_nanos()
+            )?,
+            Some(FileStamp {
+                length,
+                modified: None,
+            }) => writeln!(file, "file {}", length)?,
+            None => {}
+        }
+        for event in &self.events {
+            writeln!(file, "{} {}", event.start, event.end)?;
+        }
+        file.flush()
+    }
+
+    /// Read an index that was written by [`save`] for the event file
+    /// at `data`
+    ///
+    /// Returns an error if the length or the modification time of the
+    /// event file differ from the ones stored in the index, since the
+    /// offsets would then point at the wrong bytes.
+    /// Indices built by [`build`] don't store them, so they are not
+    /// checked.
+    ///
+    /// [`save`]: #method.save
+    /// [`build`]: #method.build
+    pub fn load<P, Q>(path: &P, data: &Q) -> io::Result<EventIndex>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let file = io::BufReader::new(fs::File::open(path)?);
+        let mut lines = file.lines();
+        match lines.next() {
+            Some(Ok(ref header)) if header == INDEX_HEADER => {}
+            Some(Err(err)) => return Err(err),
+            _ => return Err(invalid_index()),
+        }
+        let mut events = Vec::new();
+        let mut stamp = None;
+        for (n, line) in lines.enumerate() {
+            let line = line?;
+            let fields = line.split_whitespace();
+            if n == 0 && line.starts_with("file ") {
+                stamp = Some(FileStamp::parse(fields.skip(1)).ok_or_else(invalid_index)?);
+                continue;
+            }
+            let mut offsets = fields.map(|o| o.parse::<u64>());
+            match (offsets.next(), offsets.next(), offsets.next()) {
+                (Some(Ok(start)), Some(Ok(end)), None) if start < end => events.push(start..end),
+                _ => return Err(invalid_index()),
+            }
+        }
+        if let Some(stamp) = stamp {
+            if FileStamp::of(data)? != stamp {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the event index does not belong to the event file",
+                ));
+            }
+        }
+        Ok(EventIndex {
+            events,
+            file: stamp,
+        })
+    }
+
+    /// The number of events in the file
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Check whether the file contains no events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn ranges(&self) -> &[Range<u64>] {
+        &self.events
+    }
+
+    /// The byte range of the `n`th event, counting from zero
+    pub fn event_offsets(&self, n: usize) -> Option<Range<u64>> {
+        self.events.get(n).cloned()
+    }
+
+    /// Read the `n`th event, counting from zero, from the file at `path`
+    pub fn read_event<P, EventExtra>(
+        &self,
+        path: &P,
+        n: usize,
+    ) -> Result<EventGeneric<EventExtra>, ReadError>
+    where
+        P: AsRef<Path>,
+        EventExtra: ReadLhe,
+    {
+        self.read_events(path, n..n + 1)
+            .map(|mut events| events.remove(0))
+    }
+
+    /// Read the events in `range`, counting from zero, from the file at `path`
+    pub fn read_events<P, EventExtra>(
+        &self,
+        path: &P,
+        range: Range<usize>,
+    ) -> Result<Vec<EventGeneric<EventExtra>>, ReadError>
+    where
+        P: AsRef<Path>,
+        EventExtra: ReadLhe,
+    {
+        let (start, _) = self.byte_range(&range)?;
+        let mut reader = compression::open(path)?;
+        reader.skip(start)?;
+        let bytes = self.read_bytes(reader, &range)?;
+        self.parse_events(&bytes, range, || {
+            line_number(compression::open(path)?, start)
+        })
+    }
+
+    /// Read the `n`th event, counting from zero, from `reader`
+    ///
+    /// `reader` has to contain the same content that the index was built
+    /// from.
+    pub fn read_event_from<R, EventExtra>(
+        &self,
+        reader: &mut R,
+        n: usize,
+    ) -> Result<EventGeneric<EventExtra>, ReadError>
+    where
+        R: Read + Seek,
+        EventExtra: ReadLhe,
+    {
+        self.read_events_from(reader, n..n + 1)
+            .map(|mut events| events.remove(0))
+    }
+
+    /// Read the events in `range`, counting from zero, from `reader`
+    ///
+    /// `reader` has to contain the same content that the index was built
+    /// from.
+    pub fn read_events_from<R, EventExtra>(
+        &self,
+        reader: &mut R,
+        range: Range<usize>,
+    ) -> Result<Vec<EventGeneric<EventExtra>>, ReadError>
+    where
+        R: Read + Seek,
+        EventExtra: ReadLhe,
+    {
+        let (start, _) = self.byte_range(&range)?;
+        reader.seek(io::SeekFrom::Start(start))?;
+        let bytes = self.read_bytes(&mut *reader, &range)?;
+        self.parse_events(&bytes, range, || {
+            reader.seek(io::SeekFrom::Start(0))?;
+            line_number(reader, start)
+        })
+    }
+
+    fn byte_range(&self, range: &Range<usize>) -> Result<(u64, u64), ReadError> {
+        if range.start >= range.end || range.end > self.events.len() {
+            return Err(ReadError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "events {}..{} requested, but the file contains {} events",
+                    range.start,
+                    range.end,
+                    self.events.len()
+                ),
+            )));
+        }
+        Ok((
+            self.events[range.start].start,
+            self.events[range.end - 1].end,
+        ))
+    }
+
+    /// Read the bytes of the events in `range` from a reader positioned
+    /// at the start of the first of them
+    fn read_bytes<R: Read>(&self, reader: R, range: &Range<usize>) -> Result<Vec<u8>, ReadError> {
+        let (start, end) = self.byte_range(range)?;
+        let mut bytes = Vec::new();
+        reader.take(end - start).read_to_end(&mut bytes)?;
+        if (bytes.len() as u64) < end - start {
+            return Err(ReadError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the file ends before the indexed events",
+            )));
+        }
+        Ok(bytes)
+    }
+
+    /// Parse the events in `range` from their `bytes`
+    ///
+    /// The line of the first event is only determined by `first_line`
+    /// if one of the events is invalid, since the index does not store
+    /// line numbers.
+    fn parse_events<EventExtra, F>(
+        &self,
+        bytes: &[u8],
+        range: Range<usize>,
+        first_line: F,
+    ) -> Result<Vec<EventGeneric<EventExtra>>, ReadError>
+    where
+        EventExtra: ReadLhe,
+        F: FnOnce() -> io::Result<usize>,
+    {
+        let start = self.events[range.start].start;
+        let mut events = Vec::with_capacity(range.len());
+        for (n, event) in self.events[range.clone()].iter().enumerate() {
+            let (from, to) = ((event.start - start) as usize, (event.end - start) as usize);
+            match EventGeneric::read_lhe(&bytes[from..to]).to_full_result() {
+                Ok(event) => events.push(event),
+                Err(_) => {
+                    let line = first_line()? + count_lines(&bytes[..from]);
+                    let mut locator = ErrorLocator::with_origin(
+                        &bytes[from..to],
+                        event.start as usize,
+                        line,
+                        Section::Event(range.start + n),
+                    );
+                    return Err(ReadError::Parse(locate::<EventGeneric<EventExtra>>(
+                        &mut locator,
+                    )));
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+pub(crate) fn is_event_start(line: &[u8]) -> bool {
+    let line = trim_start(line);
+    line.starts_with(b"<event")
+        && line.get(6).map_or(true, |c| {
+            *c == b'>' || nom::is_space(*c) || *c == b'\r' || *c == b'\n'
+        })
+}
+
+/// The line number of the byte at `offset` in the content of `reader`
+fn line_number<R: Read>(reader: R, offset: u64) -> io::Result<usize> {
+    let mut reader = io::BufReader::new(reader.take(offset));
+    let mut line = 1;
+    loop {
+        let length = {
+            let buffer = reader.fill_buf()?;
+            line += count_lines(buffer);
+            buffer.len()
+        };
+        if length == 0 {
+            return Ok(line);
+        }
+        reader.consume(length);
+    }
+}
+
+fn invalid_index() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid event index file")
+}
+
+#[cfg(test)]
+mod tests {
+    use generic::{EventGeneric, LheFileGeneric};
+    use helac;
+    use string;
+    use ReadError;
+    use ReadLhe;
+
+    use super::EventIndex;
+
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+    use std::process;
+
+    const SAMPLE_FILES: &[&str] = &[
+        "tests/real_world_files/mg5_aMC.lhe",
+        "tests/real_world_files/helac_dipoles_rs.lhe",
+    ];
+
+    #[test]
+    fn index_sample_files() {
+        for file in SAMPLE_FILES {
+            let lhe = string::LheFile::read_lhe_from_file(file).unwrap();
+            let index = EventIndex::build_from_file(file).unwrap();
+            assert_eq!(index.len(), lhe.events.len());
+            for (n, event) in lhe.events.iter().enumerate() {
+                let read: EventGeneric<string::EventExtra> = index.read_event(file, n).unwrap();
+                assert_eq!(&read, event);
+            }
+            let events: Vec<EventGeneric<string::EventExtra>> =
+                index.read_events(file, 1..lhe.events.len()).unwrap();
+            assert_eq!(&events[..], &lhe.events[1..]);
+        }
+    }
+
+    #[test]
+    fn index_helac() {
+        let file = "tests/real_world_files/helac_dipoles_rs.lhe";
+        let lhe: helac::LheFileRS = LheFileGeneric::read_lhe_from_file(&file).unwrap();
+        let bytes = fs::read(file).unwrap();
+        let index = EventIndex::build(&bytes[..]).unwrap();
+        let last = lhe.events.len() - 1;
+        let event: EventGeneric<helac::EventExtraRS> = index
+            .read_event_from(&mut Cursor::new(&bytes), last)
+            .unwrap();
+        assert_eq!(event, lhe.events[last]);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let path = env::temp_dir().join(format!("lhef-{}-index.lhe", process::id()));
+        let sidecar = EventIndex::sidecar_path(&path);
+        let index = EventIndex::build_from_file(&file).unwrap();
+        index.save(&sidecar).unwrap();
+        let loaded = EventIndex::load(&sidecar, &file).unwrap();
+        fs::remove_file(&sidecar).unwrap();
+        assert_eq!(index, loaded);
+    }
+
+    #[test]
+    fn load_for_other_file() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let path = env::temp_dir().join(format!("lhef-{}-stale.lhe", process::id()));
+        let sidecar = EventIndex::sidecar_path(&path);
+        fs::copy(file, &path).unwrap();
+        let index = EventIndex::build_from_file(&path).unwrap();
+        index.save(&sidecar).unwrap();
+        fs::write(&path, b"<LesHouchesEvents version=\"1.0\">").unwrap();
+        let loaded = EventIndex::load(&sidecar, &path);
+        let other = EventIndex::load(&sidecar, &"tests/real_world_files/mg5_aMC_NLO.lhe");
+        fs::remove_file(&sidecar).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+        assert!(other.is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn index_compressed() {
+        use WriteLhe;
+
+        let lhe =
+            string::LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let path = env::temp_dir().join(format!("lhef-{}-index.lhe.gz", process::id()));
+        lhe.write_lhe_to_file(&path).unwrap();
+        let index = EventIndex::build_from_file(&path).unwrap();
+        let last = lhe.events.len() - 1;
+        let event: EventGeneric<string::EventExtra> = index.read_event(&path, last).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(event, lhe.events[last]);
+    }
+
+    #[test]
+    fn invalid_event() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let bytes = fs::read(file).unwrap();
+        let index = EventIndex::build(&bytes[..]).unwrap();
+        let mut bytes = bytes;
+        let position = index.event_offsets(2).unwrap().start as usize + 12;
+        bytes[position] = b'x';
+        let expected = match string::LheFile::read_lhe_from_bytes(&bytes) {
+            Err(ReadError::Parse(err)) => err,
+            _ => panic!("Expected a parse error"),
+        };
+        assert_eq!(expected.section.event(), Some(2));
+        for range in [2..3, 1..4] {
+            let result =
+                index.read_events_from::<_, string::EventExtra>(&mut Cursor::new(&bytes), range);
+            match result {
+                Err(ReadError::Parse(err)) => assert_eq!(err, expected),
+                _ => panic!("Expected a parse error"),
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let index = EventIndex::build_from_file(&file).unwrap();
+        let n = index.len();
+        assert!(index.read_event::<_, string::EventExtra>(&file, n).is_err());
+        assert!(index
+            .read_events::<_, string::EventExtra>(&file, 0..n + 1)
+            .is_err());
+    }
+}
@@ -0,0 +1,565 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Read lhe files while skipping malformed events
+//!
+//! `ReadLhe::read_lhe` fails for the whole file if a single event can
+//! not be parsed, e.g. because it contains a number that is not finite
+//! or fewer particles than announced.
+//! The functions in this module instead skip such events and return the
+//! events that could be read, together with a [`SkippedEvent`] for each
+//! event that was skipped.
+//!
+//! Only the events are read leniently: if the start of the file can not
+//! be read, if there is anything but events and `<eventgroup>` tags
+//! between the init section and the closing tag of the file, or if the
+//! closing tag is missing, an error is returned.
+//! The tags of event groups have to be on lines of their own.
+//! Skipped events are left out of the group they belong to.
+//! The [`recover`] module can be used to read truncated files.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::lenient;
+//! use lhef::plain::LheFile;
+//!
+//! let (lhe, skipped): (LheFile, _) = lenient::read_lhe_from_file(&"events.lhe").unwrap();
+//! for event in &skipped {
+//!     println!("{}", event);
+//! }
+//! println!("read {} events, skipped {}", lhe.events.len(), skipped.len());
+//! ```
+//!
+//! [`SkippedEvent`]: struct.SkippedEvent.html
+//! [`recover`]: ../recover/index.html
+
+use attributes::{diagnose_opening_tag, read_opening_tag, Attributes};
+use compression;
+use error::{self, ErrorLocator, ParseError, Section};
+use generic::{
+    locate_file_start, read_file_start, CommentPosition, EventGeneric, EventGroupRange,
+    FileComment, LheFileGeneric,
+};
+use index::is_event_start;
+use reader::{contains, trim_start};
+use ReadError;
+use ReadLhe;
+
+use nom;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+/// An event that was skipped because it could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedEvent {
+    /// The index of the event among all events in the file, including
+    /// the skipped ones
+    pub index: usize,
+    /// The byte offset of the start of the event in the file
+    pub offset: usize,
+    /// The reason why the event could not be parsed
+    pub error: ParseError,
+}
+
+impl fmt::Display for SkippedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "skipped event {} at byte {}: {}",
+            self.index, self.offset, self.error
+        )
+    }
+}
+
+/// A file without the events that could not be parsed, and the events
+/// that were skipped
+pub type Lenient<Comment, Header, InitExtra, EventExtra> = (
+    LheFileGeneric<Comment, Header, InitExtra, EventExtra>,
+    Vec<SkippedEvent>,
+);
+
+/// Read an lhe file, skipping all events that can not be parsed
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::lenient;
+/// use lhef::error::Section;
+/// use lhef::plain::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"1.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// </event>
+/// <event>
+/// 2 1 2.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 2.0 2.0 0.0 0. 9.
+/// </event>
+/// <event>
+/// 2 1 3.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 3.0 3.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -3.0 3.0 0.0 0. 9.
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let (lhe, skipped): (LheFile, _) = lenient::read_lhe(bytes).unwrap();
+/// assert_eq!(lhe.events.len(), 2);
+/// assert_eq!(lhe.events[1].weight, 3.0);
+/// assert_eq!(skipped.len(), 1);
+/// assert_eq!(skipped[0].index, 1);
+/// assert_eq!(skipped[0].error.section, Section::Particle(1, 1));
+/// ```
+pub fn read_lhe<Comment, Header, InitExtra, EventExtra>(
+    input: &[u8],
+) -> Result<Lenient<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    let (rest, start) = match read_file_start(input) {
+        nom::IResult::Done(rest, start) => (rest, start),
+        _ => {
+            return Err(ReadError::Parse(locate_file_start::<
+                Comment,
+                Header,
+                InitExtra,
+            >(input)))
+        }
+    };
+    let mut offset = input.len() - rest.len();
+    let mut line = 1 + input[..offset].iter().filter(|c| **c == b'\n').count();
+    let mut events = Vec::new();
+    let mut groups = Vec::new();
+    let mut skipped = Vec::new();
+    let (version, attributes, comment, header, init, mut comments) = start;
+    // The offset and line of the start of the current event, if any
+    let mut event_start = None;
+    // The offset and line of the start of the current comment, if any
+    let mut comment_start = None;
+    // The attributes of the current event group and the index of its
+    // first event, if any
+    let mut group: Option<(Attributes, usize)> = None;
+    for current in input[offset..].split(|c| *c == b'\n') {
+        let end = (offset + current.len() + 1).min(input.len());
+        if event_start.is_none()
+            && comment_start.is_none()
+            && trim_start(current).starts_with(b"<!--")
+        {
+            comment_start = Some((offset, line));
+        }
+        if let Some((start, start_line)) = comment_start {
+            if contains(current, b"-->") {
+                let comment = read_comment(&input[start..end], start, start_line)?;
+                let position = match group {
+                    Some(_) => CommentPosition::InGroup(groups.len(), events.len()),
+                    None => CommentPosition::BeforeEvent(events.len()),
+                };
+                comments.push(FileComment { position, comment });
+                comment_start = None;
+            }
+            offset = end;
+            line += 1;
+            continue;
+        }
+        if is_event_start(current) {
+            if let Some((start, start_line)) = event_start {
+                // The previous event lacks its closing tag
+                parse_event(input, start, start_line, offset, &mut events, &mut skipped);
+            }
+            event_start = Some((offset, line));
+        }
+        if let Some((start, start_line)) = event_start {
+            if contains(current, b"</event>") {
+                parse_event(input, start, start_line, end, &mut events, &mut skipped);
+                event_start = None;
+            }
+        } else if group.is_none() && trim_start(current).starts_with(b"<eventgroup") {
+            let attributes = read_group_start(current, offset, line)?;
+            group = Some((attributes, events.len()));
+        } else if group.is_some() && trim_start(current).starts_with(b"</eventgroup>") {
+            let mut locator = ErrorLocator::with_origin(current, offset, line, Section::File);
+            locator.tag("</eventgroup>").map_err(ReadError::Parse)?;
+            expect_line_end(&mut locator)?;
+            let (attributes, start) = group.take().expect("BUG: no open event group");
+            groups.push(EventGroupRange {
+                attributes,
+                events: start..events.len(),
+            });
+        } else if group.is_none() && trim_start(current).starts_with(b"</LesHouchesEvents>") {
+            let mut locator =
+                ErrorLocator::with_origin(&input[offset..], offset, line, Section::File);
+            locator
+                .tag("</LesHouchesEvents>")
+                .map_err(ReadError::Parse)?;
+            locator.skip_whitespace();
+            if !locator.remaining().is_empty() {
+                return Err(ReadError::Parse(
+                    locator.error(None, "the end of the input"),
+                ));
+            }
+            break;
+        } else if !trim_start(current).is_empty() {
+            let mut locator = ErrorLocator::with_origin(current, offset, line, Section::File);
+            locator.skip_whitespace();
+            let expected = if group.is_some() {
+                "`<event>` or `</eventgroup>`"
+            } else {
+                "`<event>`, `<eventgroup>` or `</LesHouchesEvents>`"
+            };
+            return Err(ReadError::Parse(locator.error(None, expected)));
+        }
+        offset = end;
+        line += 1;
+    }
+    if offset >= input.len() {
+        let index = events.len() + skipped.len();
+        let err = match event_start {
+            Some((start, start_line)) => {
+                locate_event::<EventExtra>(&input[start..], start, start_line, index)
+            }
+            None => {
+                let expected = if comment_start.is_some() {
+                    "`-->`"
+                } else if group.is_some() {
+                    "`</eventgroup>`"
+                } else {
+                    "`</LesHouchesEvents>`"
+                };
+                ErrorLocator::with_origin(b"", offset, line, Section::File).error(None, expected)
+            }
+        };
+        return Err(ReadError::Parse(err));
+    }
+    Ok((
+        LheFileGeneric {
+            version,
+            attributes,
+            comment,
+            header,
+            init,
+            events,
+            groups,
+            comments,
+        },
+        skipped,
+    ))
+}
+
+/// Read an lhe file from the file at `path`, skipping all events that
+/// can not be parsed
+///
+/// Compressed files are decompressed on the fly, see the
+/// [`compression`] module.
+///
+/// [`compression`]: ../compression/index.html
+pub fn read_lhe_from_file<P, Comment, Header, InitExtra, EventExtra>(
+    path: &P,
+) -> Result<Lenient<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    P: AsRef<Path>,
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    let mut file = compression::open(path)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    read_lhe(&contents)
+}
+
+/// Read a line containing the opening tag of an event group
+fn read_group_start(current: &[u8], offset: usize, line: usize) -> Result<Attributes, ReadError> {
+    let mut locator = ErrorLocator::with_origin(current, offset, line, Section::File);
+    let attributes = match locator.parsed(|input| read_opening_tag(input, "eventgroup")) {
+        Some(attributes) => attributes,
+        None => diagnose_opening_tag(&mut locator, "eventgroup").map_err(ReadError::Parse)?,
+    };
+    expect_line_end(&mut locator)?;
+    Ok(attributes)
+}
+
+/// Check that there is nothing but whitespace left on the current line
+fn expect_line_end(locator: &mut ErrorLocator) -> Result<(), ReadError> {
+    locator.skip_whitespace();
+    if locator.remaining().is_empty() {
+        Ok(())
+    } else {
+        Err(ReadError::Parse(locator.error(None, "the end of the line")))
+    }
+}
+
+/// Read a comment between events that ends in the last line of `input`
+fn read_comment<Comment: ReadLhe>(
+    input: &[u8],
+    offset: usize,
+    line: usize,
+) -> Result<Comment, ReadError> {
+    let mut locator = ErrorLocator::with_origin(input, offset, line, Section::Comment);
+    locator.skip_whitespace();
+    match locator.parsed(Comment::read_lhe) {
+        Some(comment) => {
+            expect_line_end(&mut locator)?;
+            Ok(comment)
+        }
+        None => Err(ReadError::Parse(match locator.object::<Comment>() {
+            Err(err) => err,
+            Ok(()) => locator.error(None, "a comment"),
+        })),
+    }
+}
+
+/// Parse the event in `input[start..end]`, or record why it was skipped
+fn parse_event<EventExtra: ReadLhe>(
+    input: &[u8],
+    start: usize,
+    line: usize,
+    end: usize,
+    events: &mut Vec<EventGeneric<EventExtra>>,
+    skipped: &mut Vec<SkippedEvent>,
+) {
+    let index = events.len() + skipped.len();
+    let bytes = &input[start..end];
+    match EventGeneric::read_lhe(bytes).to_full_result() {
+        Ok(event) => events.push(event),
+        Err(_) => skipped.push(SkippedEvent {
+            index,
+            offset: start,
+            error: locate_event::<EventExtra>(bytes, start, line, index),
+        }),
+    }
+}
+
+fn locate_event<EventExtra: ReadLhe>(
+    bytes: &[u8],
+    offset: usize,
+    line: usize,
+    index: usize,
+) -> ParseError {
+    let mut locator = ErrorLocator::with_origin(bytes, offset, line, Section::Event(index));
+    error::locate::<EventGeneric<EventExtra>>(&mut locator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_lhe;
+
+    use error::Section;
+    use plain;
+    use string;
+    use ReadError;
+    use ReadLhe;
+
+    use std::fs;
+
+    const SAMPLE_FILES: &[&str] = &[
+        "tests/real_world_files/helac_1loop_tree.lhe",
+        "tests/real_world_files/helac_1loop_virt.lhe",
+        "tests/real_world_files/helac_dipoles_i.lhe",
+        "tests/real_world_files/helac_dipoles_kp.lhe",
+        "tests/real_world_files/helac_dipoles_rs.lhe",
+        "tests/real_world_files/mg5_aMC.lhe",
+        "tests/real_world_files/mg5_aMC_NLO.lhe",
+        "tests/real_world_files/mg5_aMC_NLO_rwgt.lhe",
+    ];
+
+    /// Replace line `line` (counting from one) of `file_name` with
+    /// `replacement`
+    fn replace_line(file_name: &str, line: usize, replacement: &str) -> Vec<u8> {
+        let contents = fs::read_to_string(file_name).unwrap();
+        let lines: Vec<&str> = contents
+            .split('\n')
+            .enumerate()
+            .map(|(n, l)| if n + 1 == line { replacement } else { l })
+            .collect();
+        lines.join("\n").into_bytes()
+    }
+
+    #[test]
+    fn same_as_strict() {
+        for file in SAMPLE_FILES {
+            let bytes = fs::read(file).unwrap();
+            let strict = string::LheFile::read_lhe(&bytes).to_full_result().unwrap();
+            let (lenient, skipped): (string::LheFile, _) = read_lhe(&bytes).unwrap();
+            assert_eq!(strict, lenient);
+            assert!(skipped.is_empty());
+        }
+    }
+
+    #[test]
+    fn skip_non_finite_number() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let bytes = replace_line(file, 505, "21 1 1 2 501 503 -nan 0.0 1.0 1.0 0.0 0.0 9.0");
+        let strict = plain::LheFile::read_lhe_from_file(&file).unwrap();
+        let (lhe, skipped): (plain::LheFile, _) = read_lhe(&bytes).unwrap();
+        assert_eq!(lhe.events.len(), strict.events.len() - 1);
+        assert_eq!(lhe.events[0], strict.events[0]);
+        assert_eq!(lhe.events[1], strict.events[2]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].index, 1);
+        assert_eq!(&bytes[skipped[0].offset..skipped[0].offset + 7], b"<event>");
+        assert_eq!(skipped[0].error.line, 505);
+        assert_eq!(skipped[0].error.section, Section::Particle(1, 2));
+        assert_eq!(skipped[0].error.field, Some("PUP(1)".to_string()));
+    }
+
+    #[test]
+    fn skip_wrong_particle_count() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let contents = fs::read_to_string(file).unwrap();
+        let nup_line = contents.split('\n').nth(501).unwrap();
+        let mut tokens: Vec<&str> = nup_line.split_whitespace().collect();
+        tokens[0] = "50";
+        let bytes = replace_line(file, 502, &tokens.join(" "));
+        let (lhe, skipped): (plain::LheFile, _) = read_lhe(&bytes).unwrap();
+        let strict = plain::LheFile::read_lhe_from_file(&file).unwrap();
+        assert_eq!(lhe.events.len(), strict.events.len() - 1);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].index, 1);
+        assert_eq!(skipped[0].error.section.event(), Some(1));
+    }
+
+    #[test]
+    fn skip_missing_closing_event_tag() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let contents = fs::read_to_string(file).unwrap();
+        assert_eq!(contents.split('\n').nth(513), Some("</event>"));
+        let bytes = replace_line(file, 514, "");
+        let (lhe, skipped): (plain::LheFile, _) = read_lhe(&bytes).unwrap();
+        let strict = plain::LheFile::read_lhe_from_file(&file).unwrap();
+        assert_eq!(lhe.events.len(), strict.events.len() - 1);
+        assert_eq!(lhe.events[1], strict.events[2]);
+        assert_eq!(skipped[0].index, 1);
+    }
+
+    #[test]
+    fn structural_errors() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let bytes = replace_line(file, 500, "</event>\ngarbage");
+        match read_lhe::<plain::Comment, plain::Header, plain::InitExtra, plain::EventExtra>(&bytes)
+        {
+            Err(ReadError::Parse(err)) => {
+                assert_eq!(err.line, 501);
+                assert_eq!(err.found, "garbage");
+                assert_eq!(err.section, Section::File);
+            }
+            _ => panic!("Expected a parse error"),
+        }
+
+        let bytes = fs::read(file).unwrap();
+        let truncated = &bytes[..bytes.len() - "</LesHouchesEvents>\n".len()];
+        assert!(
+            read_lhe::<plain::Comment, plain::Header, plain::InitExtra, plain::EventExtra>(
+                truncated
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn skip_event_in_group() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<eventgroup nreal=\"1\" ncounter=\"2\">
+<event>
+0 1 1.0 91.2 0.0078 0.118
+</event>
+<event>
+1 1 -1.0 91.2 0.0078 0.118
+</event>
+<event>
+0 1 -2.0 91.2 0.0078 0.118
+</event>
+</eventgroup>
+<event>
+0 1 3.0 91.2 0.0078 0.118
+</event>
+</LesHouchesEvents>
+";
+        let (lhe, skipped): (plain::LheFile, _) = read_lhe(bytes).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].index, 1);
+        assert_eq!(lhe.events.len(), 3);
+        assert_eq!(lhe.groups.len(), 1);
+        assert_eq!(lhe.groups[0].events, 0..2);
+        assert_eq!(lhe.groups[0].attributes.get("nreal"), Some("1"));
+        assert_eq!(lhe.events[1].weight, -2.0);
+
+        let unclosed = String::from_utf8(bytes.to_vec())
+            .unwrap()
+            .replace("</eventgroup>\n", "");
+        match read_lhe::<plain::Comment, plain::Header, plain::InitExtra, plain::EventExtra>(
+            unclosed.as_bytes(),
+        ) {
+            Err(ReadError::Parse(err)) => {
+                assert_eq!(err.line, 19);
+                assert_eq!(err.expected, "`<event>` or `</eventgroup>`");
+            }
+            _ => panic!("Expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn read_comments() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<!-- first -->
+<!-- second -->
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<event>
+0 1 1.0 91.2 0.0078 0.118
+</event>
+<!-- between
+     events -->
+<eventgroup nreal=\"1\" ncounter=\"1\">
+<!-- in group -->
+<event>
+0 1 2.0 91.2 0.0078 0.118
+</event>
+<event>
+0 1 -2.0 91.2 0.0078 0.118
+</event>
+</eventgroup>
+<!-- at the end -->
+</LesHouchesEvents>
+";
+        let strict = string::LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let (lenient, skipped): (string::LheFile, _) = read_lhe(bytes).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(lenient.comments.len(), 4);
+        assert_eq!(lenient, strict);
+
+        let unclosed = String::from_utf8(bytes.to_vec())
+            .unwrap()
+            .replace("-->\n</LesHouchesEvents>", "");
+        match read_lhe::<plain::Comment, plain::Header, plain::InitExtra, plain::EventExtra>(
+            unclosed.as_bytes(),
+        ) {
+            Err(ReadError::Parse(err)) => assert_eq!(err.expected, "`-->`"),
+            _ => panic!("Expected a parse error"),
+        }
+    }
+}
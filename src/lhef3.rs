@@ -0,0 +1,2602 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A module for the additional information defined in version 3 of the lhe standard
+//!
+//! Version 3 of the lhe standard, described in [`arXiv:1405.1067`],
+//! defines a number of tags for the header, the init section and the
+//! events of lhe files.
+//! This module contains types that parse these tags into typed
+//! structures and that can be used in `LheFileGeneric`.
+//! Any content that is not covered by these types is kept as a string.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::ReadLhe;
+//! use lhef::lhef3::LheFile;
+//!
+//! let lhe = LheFile::read_lhe_from_file(&"events.lhe").unwrap();
+//!
+//! // all weights declared in the header
+//! if let Some(ref initrwgt) = lhe.header.initrwgt {
+//!     for weight in initrwgt.weights() {
+//!         println!("{}: {}", weight.id, weight.text);
+//!     }
+//! }
+//! ```
+//!
+//! [`arXiv:1405.1067`]: https://arxiv.org/abs/1405.1067
+
+use {Particle, PdgId, ReadLhe, WriteLhe};
+use attributes::{find_elements, push_text, split_elements, write_element, write_opening_tag,
+                 Attributes, Element};
+use generic::{EventGeneric, LheFileGeneric};
+use string;
+use version::{comment_out, downgrade_text, write_removed, Downgrade, DowngradeMode, LhefVersion};
+
+use nom;
+use std::collections::HashMap;
+use std::f64;
+use std::fmt;
+use std::io;
+use std::slice;
+use std::str;
+
+/// A type to read and write lhe files with the additional information
+/// defined in version 3 of the lhe standard
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::lhef3::{Combine, LheFile};
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <header>
+/// <initrwgt>
+/// <weightgroup name='scale_variation' combine='envelope'>
+/// <weight id='1001'> muR=0.10000E+01 muF=0.10000E+01 </weight>
+/// <weight id='1002'> muR=0.20000E+01 muF=0.10000E+01 </weight>
+/// </weightgroup>
+/// </initrwgt>
+/// </header>
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let initrwgt = lhe.header.initrwgt.as_ref().unwrap();
+/// assert_eq!(initrwgt.groups[0].combine, Some(Combine::Envelope));
+/// let weight = initrwgt.weight("1002").unwrap();
+/// assert_eq!(weight.parameter("muR"), Some(2.));
+/// ```
+pub type LheFile = LheFileGeneric<string::Comment, Header, InitExtra, EventExtra>;
+
+/// The header of an lhe file, with the weight declarations parsed
+///
+/// The `<initrwgt>` block of the header, if present, is parsed into an
+/// [`InitRwgt`].
+/// The rest of the header is kept as a string, with leading and
+/// trailing whitespace removed.
+/// When writing, the `<initrwgt>` block is placed at the end of the
+/// header.
+///
+/// [`InitRwgt`]: struct.InitRwgt.html
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    /// The content of the header apart from the weight declarations,
+    /// `None` if the file has no header
+    pub header: Option<String>,
+    /// The weight declarations in the header
+    pub initrwgt: Option<InitRwgt>,
+}
+
+impl ReadLhe for Header {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], Header> {
+        map_opt!(
+            input,
+            string::Header::read_lhe,
+            |string::Header { header }| {
+                match header {
+                    Some(header) => split_header(&header),
+                    None => Some(Header {
+                        header: None,
+                        initrwgt: None,
+                    }),
+                }
+            }
+        )
+    }
+}
+
+fn split_header(header: &str) -> Option<Header> {
+    let (elements, rest) = split_elements(header, &["initrwgt"])?;
+    let initrwgt = match elements.len() {
+        0 => None,
+        1 => Some(InitRwgt::from_element(&elements[0])?),
+        _ => return None,
+    };
+    Some(Header {
+        header: Some(rest),
+        initrwgt,
+    })
+}
+
+impl WriteLhe for Header {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.header.is_none() && self.initrwgt.is_none() {
+            return Ok(());
+        }
+        writeln!(writer, "<header>")?;
+        match self.header {
+            Some(ref header) if !header.is_empty() => writeln!(writer, "{}", header)?,
+            _ => {}
+        }
+        if let Some(ref initrwgt) = self.initrwgt {
+            initrwgt.write_lhe(writer)?;
+        }
+        writeln!(writer, "</header>")
+    }
+}
+
+impl Downgrade for Header {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        if let Some(ref mut header) = self.header {
+            *header = downgrade_text(header, version, mode);
+        }
+        if version.allows_tag("initrwgt") {
+            return;
+        }
+        if let Some(initrwgt) = self.initrwgt.take() {
+            if mode == DowngradeMode::Comment {
+                let mut removed = Vec::new();
+                write_removed(&initrwgt, &mut removed);
+                comment_out(self.header.get_or_insert_with(String::new), &removed);
+            }
+        }
+    }
+}
+
+/// The weight declarations in the `<initrwgt>` block of the header
+///
+/// The weights are declared either in a `<weightgroup>` or on their
+/// own.
+/// Weights that are declared on their own are written after the
+/// groups.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InitRwgt {
+    /// The groups of weights
+    pub groups: Vec<WeightGroup>,
+    /// The weights that are not part of a group
+    pub weights: Vec<Weight>,
+}
+
+impl InitRwgt {
+    fn from_element(element: &Element) -> Option<InitRwgt> {
+        let (elements, rest) = split_elements(element.content, &["weightgroup", "weight"])?;
+        if !rest.is_empty() {
+            return None;
+        }
+        let mut initrwgt = InitRwgt::default();
+        for element in &elements {
+            if element.name == "weight" {
+                initrwgt.weights.push(Weight::from_element(element));
+            } else {
+                initrwgt.groups.push(WeightGroup::from_element(element)?);
+            }
+        }
+        Some(initrwgt)
+    }
+
+    /// Iterate over all declared weights, in the order in which they
+    /// are written
+    pub fn weights(&self) -> impl Iterator<Item = &Weight> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.weights.iter())
+            .chain(self.weights.iter())
+    }
+
+    /// The declaration of the weight with the id `id`
+    pub fn weight(&self, id: &str) -> Option<&Weight> {
+        self.weights().find(|weight| weight.id == id)
+    }
+
+    /// The group the weight with the id `id` belongs to
+    pub fn group_of(&self, id: &str) -> Option<&WeightGroup> {
+        self.groups
+            .iter()
+            .find(|group| group.weights.iter().any(|weight| weight.id == id))
+    }
+}
+
+impl WriteLhe for InitRwgt {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "<initrwgt>")?;
+        for group in &self.groups {
+            group.write_lhe(writer)?;
+        }
+        for weight in &self.weights {
+            weight.write_lhe(writer)?;
+        }
+        writeln!(writer, "</initrwgt>")
+    }
+}
+
+/// A group of weights declared in a `<weightgroup>` tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightGroup {
+    /// The name of the group
+    pub name: Option<String>,
+    /// How the weights in the group should be combined to estimate an
+    /// uncertainty
+    pub combine: Option<Combine>,
+    /// The weights in the group
+    pub weights: Vec<Weight>,
+    /// Any other attributes of the group
+    pub attributes: Attributes,
+}
+
+impl WeightGroup {
+    fn from_element(element: &Element) -> Option<WeightGroup> {
+        let (elements, rest) = split_elements(element.content, &["weight"])?;
+        if !rest.is_empty() {
+            return None;
+        }
+        let mut attributes = element.attributes.clone();
+        Some(WeightGroup {
+            name: attributes.remove("name"),
+            combine: attributes.remove("combine").map(|c| Combine::from_name(&c)),
+            weights: elements.iter().map(Weight::from_element).collect(),
+            attributes,
+        })
+    }
+}
+
+impl WriteLhe for WeightGroup {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        if let Some(ref name) = self.name {
+            attributes.insert("name", name.as_str());
+        }
+        if let Some(ref combine) = self.combine {
+            attributes.insert("combine", combine.name());
+        }
+        attributes.extend(self.attributes.iter().cloned());
+        write_opening_tag(writer, "weightgroup", &attributes)?;
+        for weight in &self.weights {
+            weight.write_lhe(writer)?;
+        }
+        writeln!(writer, "</weightgroup>")
+    }
+}
+
+/// The ways in which the weights in a group can be combined
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Combine {
+    /// The weights should not be combined
+    None,
+    /// The envelope of the weights gives the uncertainty
+    Envelope,
+    /// The weights belong to a hessian pdf error set
+    Hessian,
+    /// The weights are replicas whose standard deviation gives the
+    /// uncertainty
+    Gaussian,
+    /// Any other value of the `combine` attribute
+    Other(String),
+}
+
+impl Combine {
+    fn from_name(name: &str) -> Combine {
+        match name {
+            "none" => Combine::None,
+            "envelope" => Combine::Envelope,
+            "hessian" => Combine::Hessian,
+            "gaussian" => Combine::Gaussian,
+            other => Combine::Other(other.to_string()),
+        }
+    }
+
+    /// The value of the `combine` attribute for this strategy
+    pub fn name(&self) -> &str {
+        match *self {
+            Combine::None => "none",
+            Combine::Envelope => "envelope",
+            Combine::Hessian => "hessian",
+            Combine::Gaussian => "gaussian",
+            Combine::Other(ref other) => other,
+        }
+    }
+}
+
+/// The declaration of a weight in a `<weight>` tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weight {
+    /// The id of the weight, used to refer to it in the events
+    pub id: String,
+    /// The description of the weight, with leading and trailing
+    /// whitespace removed
+    pub text: String,
+    /// Any other attributes of the weight
+    pub attributes: Attributes,
+}
+
+impl Weight {
+    fn from_element(element: &Element) -> Weight {
+        let mut attributes = element.attributes.clone();
+        Weight {
+            id: attributes.remove("id").unwrap_or_default(),
+            text: element.content.trim().to_string(),
+            attributes,
+        }
+    }
+
+    /// The value of a parameter given as `name=value` in the
+    /// description of the weight
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lhef::attributes::Attributes;
+    /// use lhef::lhef3::Weight;
+    ///
+    /// let weight = Weight {
+    ///     id: "1002".to_string(),
+    ///     text: "dyn=  -1 muR=0.20000E+01 muF=0.10000E+01".to_string(),
+    ///     attributes: Attributes::new(),
+    /// };
+    /// assert_eq!(weight.parameter("dyn"), Some(-1.));
+    /// assert_eq!(weight.parameter("muR"), Some(2.));
+    /// assert_eq!(weight.parameter("PDF"), None);
+    /// ```
+    pub fn parameter(&self, name: &str) -> Option<f64> {
+        let pattern = format!("{}=", name);
+        let mut search = 0;
+        while let Some(start) = self.text[search..].find(&pattern).map(|n| n + search) {
+            let at_boundary = self.text[..start].ends_with(char::is_whitespace) || start == 0;
+            if at_boundary {
+                let value = self.text[start + pattern.len()..]
+                    .split_whitespace()
+                    .next()?;
+                return value.parse().ok();
+            }
+            search = start + pattern.len();
+        }
+        None
+    }
+}
+
+impl WriteLhe for Weight {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        attributes.insert("id", self.id.as_str());
+        attributes.extend(self.attributes.iter().cloned());
+        write_element(writer, "weight", &attributes, &format!(" {} ", self.text))
+    }
+}
+
+/// Additional initialization information, with the standard tags
+/// parsed
+///
+/// The `<generator>`, `<xsecinfo>`, `<cutsinfo>`, `<procinfo>` and
+/// `<mergeinfo>` tags are parsed into typed structs.
+/// The rest of the additional information is kept as a string, with
+/// leading and trailing whitespace removed, and is written before the
+/// tags.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::lhef3::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// <generator name='MadGraph5_aMC@NLO' version='2.5.1'>please cite 1405.0301 </generator>
+/// <xsecinfo neve=\"10000\" totxsec=\"2.1\" negweights=\"yes\"/>
+/// <procinfo iproc=\"1\" qcdorder=\"2\" eworder=\"0\" rscheme=\"MSbar\"/>
+/// </init>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let extra = &lhe.init.extra;
+/// assert_eq!(extra.generators[0].name, Some("MadGraph5_aMC@NLO".to_string()));
+/// assert_eq!(extra.generators[0].text, "please cite 1405.0301");
+/// let xsecinfo = extra.xsecinfo.as_ref().unwrap();
+/// assert_eq!(xsecinfo.neve, 10000);
+/// assert!(xsecinfo.negweights);
+/// assert_eq!(xsecinfo.maxweight, 1.);
+/// let procinfo = extra.procinfo(1).unwrap();
+/// assert_eq!(procinfo.qcdorder, Some(2));
+/// assert_eq!(procinfo.rscheme, Some("MSbar".to_string()));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InitExtra {
+    /// The programs that generated the file
+    pub generators: Vec<Generator>,
+    /// The information about the cross section in the `<xsecinfo>` tag
+    pub xsecinfo: Option<XSecInfo>,
+    /// The generation cuts in the `<cutsinfo>` tag
+    pub cutsinfo: Option<CutsInfo>,
+    /// The information about the processes in the `<procinfo>` tags
+    pub procinfo: Vec<ProcessInfo>,
+    /// The information about the merging in the `<mergeinfo>` tags
+    pub mergeinfo: Vec<MergeInfo>,
+    /// Any other additional initialization information
+    pub extra: String,
+}
+
+impl ReadLhe for InitExtra {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], InitExtra> {
+        map_opt!(input, take_until!("</init>"), |x| str::from_utf8(x)
+            .ok()
+            .and_then(split_init_extra))
+    }
+}
+
+/// Split the additional init information into its typed elements
+///
+/// A second `<xsecinfo>` or `<cutsinfo>` element is kept verbatim in
+/// `extra`, like everything else that is not parsed.
+fn split_init_extra(content: &str) -> Option<InitExtra> {
+    let names = ["generator", "xsecinfo", "cutsinfo", "procinfo", "mergeinfo"];
+    let mut init_extra = InitExtra::default();
+    let mut position = 0;
+    for (range, element) in find_elements(content, &names)? {
+        match element.name {
+            "generator" => init_extra
+                .generators
+                .push(Generator::from_element(&element)),
+            "xsecinfo" if init_extra.xsecinfo.is_none() => {
+                init_extra.xsecinfo = Some(XSecInfo::from_element(&element)?);
+            }
+            "cutsinfo" if init_extra.cutsinfo.is_none() => {
+                init_extra.cutsinfo = Some(CutsInfo::from_element(&element)?);
+            }
+            "procinfo" => init_extra
+                .procinfo
+                .push(ProcessInfo::from_element(&element)?),
+            "mergeinfo" => init_extra
+                .mergeinfo
+                .push(MergeInfo::from_element(&element)?),
+            _ => continue,
+        }
+        push_text(&mut init_extra.extra, &content[position..range.start]);
+        position = range.end;
+    }
+    push_text(&mut init_extra.extra, &content[position..]);
+    Some(init_extra)
+}
+
+impl InitExtra {
+    /// The information about the process with the id `iproc` (`LPRUP`)
+    pub fn procinfo(&self, iproc: i64) -> Option<&ProcessInfo> {
+        self.procinfo.iter().find(|info| info.iproc == iproc)
+    }
+
+    /// The merging information about the process with the id `iproc`
+    /// (`LPRUP`)
+    pub fn mergeinfo(&self, iproc: i64) -> Option<&MergeInfo> {
+        self.mergeinfo.iter().find(|info| info.iproc == iproc)
+    }
+}
+
+impl WriteLhe for InitExtra {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for generator in &self.generators {
+            generator.write_lhe(writer)?;
+        }
+        if let Some(ref xsecinfo) = self.xsecinfo {
+            xsecinfo.write_lhe(writer)?;
+        }
+        if let Some(ref cutsinfo) = self.cutsinfo {
+            cutsinfo.write_lhe(writer)?;
+        }
+        for procinfo in &self.procinfo {
+            procinfo.write_lhe(writer)?;
+        }
+        for mergeinfo in &self.mergeinfo {
+            mergeinfo.write_lhe(writer)?;
+        }
+        // The additional information is written last, so that elements
+        // that were kept in it because they are duplicates stay after
+        // the ones that were parsed
+        if !self.extra.is_empty() {
+            writeln!(writer, "{}", self.extra)?;
+        }
+        Ok(())
+    }
+}
+
+impl Downgrade for InitExtra {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        self.extra = downgrade_text(&self.extra, version, mode);
+        let mut removed = Vec::new();
+        if !version.allows_tag("generator") {
+            for generator in self.generators.drain(..) {
+                write_removed(&generator, &mut removed);
+            }
+        }
+        if !version.allows_tag("xsecinfo") {
+            if let Some(xsecinfo) = self.xsecinfo.take() {
+                write_removed(&xsecinfo, &mut removed);
+            }
+        }
+        if !version.allows_tag("cutsinfo") {
+            if let Some(cutsinfo) = self.cutsinfo.take() {
+                write_removed(&cutsinfo, &mut removed);
+            }
+        }
+        if !version.allows_tag("procinfo") {
+            for procinfo in self.procinfo.drain(..) {
+                write_removed(&procinfo, &mut removed);
+            }
+        }
+        if !version.allows_tag("mergeinfo") {
+            for mergeinfo in self.mergeinfo.drain(..) {
+                write_removed(&mergeinfo, &mut removed);
+            }
+        }
+        if mode == DowngradeMode::Comment {
+            comment_out(&mut self.extra, &removed);
+        }
+    }
+}
+
+/// A program that generated the file, given in a `<generator>` tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct Generator {
+    /// The name of the program
+    pub name: Option<String>,
+    /// The version of the program
+    pub version: Option<String>,
+    /// The content of the tag, with leading and trailing whitespace
+    /// removed
+    pub text: String,
+    /// Any other attributes of the tag
+    pub attributes: Attributes,
+}
+
+impl Generator {
+    fn from_element(element: &Element) -> Generator {
+        let mut attributes = element.attributes.clone();
+        Generator {
+            name: attributes.remove("name"),
+            version: attributes.remove("version"),
+            text: element.content.trim().to_string(),
+            attributes,
+        }
+    }
+}
+
+impl WriteLhe for Generator {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        if let Some(ref name) = self.name {
+            attributes.insert("name", name.as_str());
+        }
+        if let Some(ref version) = self.version {
+            attributes.insert("version", version.as_str());
+        }
+        attributes.extend(self.attributes.iter().cloned());
+        write_element(writer, "generator", &attributes, &self.text)
+    }
+}
+
+/// The information about the cross section of the events in the file,
+/// given in the `<xsecinfo>` tag
+///
+/// The attributes that are not given in the tag take their default
+/// values from the lhe standard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XSecInfo {
+    /// The number of events in the file (`neve`)
+    pub neve: i64,
+    /// The total cross section in pb (`totxsec`)
+    pub totxsec: f64,
+    /// The maximum weight of the events (`maxweight`), 1 by default
+    pub maxweight: f64,
+    /// The mean weight of the events (`meanweight`), 1 by default
+    pub meanweight: f64,
+    /// Whether there are events with negative weights (`negweights`),
+    /// `false` by default
+    pub negweights: bool,
+    /// Whether the events have variable weights (`varweights`), `false`
+    /// by default
+    pub varweights: bool,
+    /// Any other attributes of the tag
+    pub attributes: Attributes,
+}
+
+impl XSecInfo {
+    fn from_element(element: &Element) -> Option<XSecInfo> {
+        let mut attributes = element.attributes.clone();
+        Some(XSecInfo {
+            neve: take_attribute(&mut attributes, "neve").ok()??,
+            totxsec: take_attribute(&mut attributes, "totxsec").ok()??,
+            maxweight: take_attribute(&mut attributes, "maxweight")
+                .ok()?
+                .unwrap_or(1.),
+            meanweight: take_attribute(&mut attributes, "meanweight")
+                .ok()?
+                .unwrap_or(1.),
+            negweights: take_flag(&mut attributes, "negweights")?.unwrap_or(false),
+            varweights: take_flag(&mut attributes, "varweights")?.unwrap_or(false),
+            attributes,
+        })
+    }
+}
+
+impl WriteLhe for XSecInfo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        attributes.insert("neve", self.neve.to_string());
+        attributes.insert("totxsec", format!("{:e}", self.totxsec));
+        if self.maxweight != 1. {
+            attributes.insert("maxweight", format!("{:e}", self.maxweight));
+        }
+        if self.meanweight != 1. {
+            attributes.insert("meanweight", format!("{:e}", self.meanweight));
+        }
+        if self.negweights {
+            attributes.insert("negweights", "yes");
+        }
+        if self.varweights {
+            attributes.insert("varweights", "yes");
+        }
+        attributes.extend(self.attributes.iter().cloned());
+        write_element(writer, "xsecinfo", &attributes, "")
+    }
+}
+
+/// The generation cuts, given in the `<cutsinfo>` tag
+///
+/// The cuts refer to the particles either by their pdg id or by the
+/// name of a group of particles defined in a `<ptype>` tag.
+/// Only final state particles, i.e. those with status 1, are
+/// considered when evaluating the cuts on an event.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::lhef3::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// <cutsinfo>
+/// <ptype name=\"leptons\">11 -11 13 -13</ptype>
+/// <cut type=\"pT\" p1=\"leptons\" min=\"20\"></cut>
+/// <cut type=\"m\" p1=\"11\" p2=\"-11\"> 60 120 </cut>
+/// </cutsinfo>
+/// </init>
+/// <event>
+/// 4 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 45.6 45.6 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -45.6 45.6 0.0 0. 9.
+/// 11 1 1 2 0 0 45.6 0.0 0.0 45.6 0.0 0. 9.
+/// -11 1 1 2 0 0 -45.6 0.0 0.0 45.6 0.0 0. 9.
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let cutsinfo = lhe.init.extra.cutsinfo.as_ref().unwrap();
+/// assert_eq!(cutsinfo.cuts[1].max, Some(120.));
+/// assert!(cutsinfo.passes(&lhe.events[0]));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CutsInfo {
+    /// The groups of particles defined in `<ptype>` tags
+    pub ptypes: Vec<ParticleType>,
+    /// The cuts defined in `<cut>` tags
+    pub cuts: Vec<Cut>,
+}
+
+impl CutsInfo {
+    fn from_element(element: &Element) -> Option<CutsInfo> {
+        let (elements, rest) = split_elements(element.content, &["ptype", "cut"])?;
+        if !rest.is_empty() {
+            return None;
+        }
+        let mut cutsinfo = CutsInfo::default();
+        for element in &elements {
+            if element.name == "ptype" {
+                cutsinfo.ptypes.push(ParticleType::from_element(element)?);
+            } else {
+                cutsinfo.cuts.push(Cut::from_element(element)?);
+            }
+        }
+        Some(cutsinfo)
+    }
+
+    /// The group of particles with the name `name`
+    pub fn ptype(&self, name: &str) -> Option<&ParticleType> {
+        self.ptypes.iter().find(|ptype| ptype.name == name)
+    }
+
+    /// Check whether an event passes all cuts
+    pub fn passes<E>(&self, event: &EventGeneric<E>) -> bool {
+        self.cuts.iter().all(|cut| self.passes_cut(cut, event))
+    }
+
+    /// The cuts that an event fails
+    pub fn failed_cuts<E>(&self, event: &EventGeneric<E>) -> Vec<&Cut> {
+        self.cuts
+            .iter()
+            .filter(|cut| !self.passes_cut(cut, event))
+            .collect()
+    }
+
+    /// Check whether an event passes a single cut
+    ///
+    /// Cuts with one particle, like `pT`, have to be passed by each of
+    /// the final state particles that match `p1`.
+    /// Cuts with two particles, like `m`, have to be passed by each
+    /// pair of a final state particle that matches `p1` and a different
+    /// one that matches `p2`, or `p1` again if `p2` is not given.
+    /// `ETmiss` has to be passed by the sum of the transverse momenta of
+    /// all final state particles that match `p1`.
+    /// Cuts of an unknown type are always passed.
+    pub fn passes_cut<E>(&self, cut: &Cut, event: &EventGeneric<E>) -> bool {
+        let first = self.select(cut.p1.as_ref(), event);
+        match cut.cut_type {
+            CutType::Mass | CutType::DeltaR => {
+                let second = match cut.p2 {
+                    Some(_) => self.select(cut.p2.as_ref(), event),
+                    None => first.clone(),
+                };
+                first.iter().all(|&(i, p)| {
+                    second
+                        .iter()
+                        .filter(|&&(j, _)| i != j)
+                        .all(|&(_, q)| cut.contains(pair_value(&cut.cut_type, p, q)))
+                })
+            }
+            CutType::ETmiss => {
+                let px: f64 = first.iter().map(|&(_, p)| p.momentum.px).sum();
+                let py: f64 = first.iter().map(|&(_, p)| p.momentum.py).sum();
+                cut.contains(px.hypot(py))
+            }
+            CutType::Other(_) => true,
+            _ => first
+                .iter()
+                .all(|&(_, p)| cut.contains(single_value(&cut.cut_type, p))),
+        }
+    }
+
+    /// The final state particles of an event that match `selection`,
+    /// with their positions in the event
+    fn select<'a, E>(
+        &self,
+        selection: Option<&String>,
+        event: &'a EventGeneric<E>,
+    ) -> Vec<(usize, &'a Particle)> {
+        let ids: Option<Vec<PdgId>> = match selection {
+            None => None,
+            Some(name) => match self.ptype(name) {
+                Some(ptype) => Some(ptype.pdg_ids.clone()),
+                None => match name.trim().parse() {
+                    Ok(0) => None,
+                    Ok(id) => Some(vec![id]),
+                    Err(_) => Some(Vec::new()),
+                },
+            },
+        };
+        event
+            .particles
+            .iter()
+            .enumerate()
+            .filter(|&(_, p)| p.status == 1)
+            .filter(|&(_, p)| match ids {
+                Some(ref ids) => ids.contains(&p.pdg_id),
+                None => true,
+            })
+            .collect()
+    }
+}
+
+impl WriteLhe for CutsInfo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "<cutsinfo>")?;
+        for ptype in &self.ptypes {
+            ptype.write_lhe(writer)?;
+        }
+        for cut in &self.cuts {
+            cut.write_lhe(writer)?;
+        }
+        writeln!(writer, "</cutsinfo>")
+    }
+}
+
+fn single_value(cut_type: &CutType, p: &Particle) -> f64 {
+    let m = &p.momentum;
+    match *cut_type {
+        CutType::Pt => m.px.hypot(m.py),
+        CutType::Eta => pseudorapidity(p),
+        CutType::Rapidity => 0.5 * ((m.e + m.pz) / (m.e - m.pz)).ln(),
+        CutType::Energy => m.e,
+        _ => unreachable!("BUG: not a cut on a single particle"),
+    }
+}
+
+fn pair_value(cut_type: &CutType, p: &Particle, q: &Particle) -> f64 {
+    let (m, n) = (&p.momentum, &q.momentum);
+    match *cut_type {
+        CutType::Mass => {
+            let e = m.e + n.e;
+            let (px, py, pz) = (m.px + n.px, m.py + n.py, m.pz + n.pz);
+            (e * e - px * px - py * py - pz * pz).max(0.).sqrt()
+        }
+        CutType::DeltaR => {
+            let delta_eta = pseudorapidity(p) - pseudorapidity(q);
+            let mut delta_phi = (m.py.atan2(m.px) - n.py.atan2(n.px)).abs();
+            if delta_phi > f64::consts::PI {
+                delta_phi = 2. * f64::consts::PI - delta_phi;
+            }
+            delta_eta.hypot(delta_phi)
+        }
+        _ => unreachable!("BUG: not a cut on a pair of particles"),
+    }
+}
+
+fn pseudorapidity(p: &Particle) -> f64 {
+    let m = &p.momentum;
+    (m.pz / m.px.hypot(m.py)).asinh()
+}
+
+/// A group of particles defined in a `<ptype>` tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParticleType {
+    /// The name of the group, used to refer to it in the cuts
+    pub name: String,
+    /// The pdg ids of the particles in the group
+    pub pdg_ids: Vec<PdgId>,
+    /// Any other attributes of the tag
+    pub attributes: Attributes,
+}
+
+impl ParticleType {
+    fn from_element(element: &Element) -> Option<ParticleType> {
+        let mut attributes = element.attributes.clone();
+        let pdg_ids: Result<_, _> = element.content.split_whitespace().map(str::parse).collect();
+        Some(ParticleType {
+            name: attributes.remove("name")?,
+            pdg_ids: pdg_ids.ok()?,
+            attributes,
+        })
+    }
+}
+
+impl WriteLhe for ParticleType {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        attributes.insert("name", self.name.as_str());
+        attributes.extend(self.attributes.iter().cloned());
+        let ids: Vec<_> = self.pdg_ids.iter().map(|id| id.to_string()).collect();
+        write_element(writer, "ptype", &attributes, &ids.join(" "))
+    }
+}
+
+/// A generation cut defined in a `<cut>` tag
+///
+/// The limits are taken from the `min` and `max` attributes, or from
+/// the content of the tag, where a single value is the lower limit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cut {
+    /// The quantity the cut is applied to (`type`)
+    pub cut_type: CutType,
+    /// The particles the cut is applied to, either a pdg id or the name
+    /// of a `<ptype>`, or all particles if it is not given (`p1`)
+    pub p1: Option<String>,
+    /// The second particles for cuts on pairs of particles (`p2`)
+    pub p2: Option<String>,
+    /// The lower limit
+    pub min: Option<f64>,
+    /// The upper limit
+    pub max: Option<f64>,
+    /// Any other attributes of the tag
+    pub attributes: Attributes,
+}
+
+impl Cut {
+    fn from_element(element: &Element) -> Option<Cut> {
+        let mut attributes = element.attributes.clone();
+        let cut_type = CutType::from_name(&attributes.remove("type")?);
+        let mut min = take_attribute(&mut attributes, "min").ok()?;
+        let mut max = take_attribute(&mut attributes, "max").ok()?;
+        let limits: Result<Vec<f64>, _> =
+            element.content.split_whitespace().map(str::parse).collect();
+        match limits.ok()?.as_slice() {
+            [] => {}
+            [lower] => min = Some(*lower),
+            [lower, upper] => {
+                min = Some(*lower);
+                max = Some(*upper);
+            }
+            _ => return None,
+        }
+        Some(Cut {
+            cut_type,
+            p1: attributes.remove("p1"),
+            p2: attributes.remove("p2"),
+            min,
+            max,
+            attributes,
+        })
+    }
+
+    /// Check whether a value lies within the limits of the cut
+    pub fn contains(&self, value: f64) -> bool {
+        let above_min = match self.min {
+            Some(min) => value >= min,
+            None => true,
+        };
+        let below_max = match self.max {
+            Some(max) => value <= max,
+            None => true,
+        };
+        above_min && below_max
+    }
+}
+
+impl WriteLhe for Cut {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        attributes.insert("type", self.cut_type.name());
+        if let Some(ref p1) = self.p1 {
+            attributes.insert("p1", p1.as_str());
+        }
+        if let Some(ref p2) = self.p2 {
+            attributes.insert("p2", p2.as_str());
+        }
+        if let Some(min) = self.min {
+            attributes.insert("min", format!("{:e}", min));
+        }
+        if let Some(max) = self.max {
+            attributes.insert("max", format!("{:e}", max));
+        }
+        attributes.extend(self.attributes.iter().cloned());
+        write_element(writer, "cut", &attributes, "")
+    }
+}
+
+/// The quantities that cuts can be applied to
+///
+/// The names are matched case insensitively.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CutType {
+    /// The invariant mass of a pair of particles (`m`)
+    Mass,
+    /// The transverse momentum of a particle (`pT` or `kt`)
+    Pt,
+    /// The pseudorapidity of a particle (`eta`)
+    Eta,
+    /// The rapidity of a particle (`y`)
+    Rapidity,
+    /// The distance of a pair of particles in the eta-phi plane
+    /// (`deltaR`)
+    DeltaR,
+    /// The energy of a particle (`E`)
+    Energy,
+    /// The missing transverse energy (`ETmiss`)
+    ETmiss,
+    /// Any other type of cut
+    Other(String),
+}
+
+impl CutType {
+    fn from_name(name: &str) -> CutType {
+        match name.to_lowercase().as_str() {
+            "m" => CutType::Mass,
+            "pt" | "kt" => CutType::Pt,
+            "eta" => CutType::Eta,
+            "y" => CutType::Rapidity,
+            "deltar" => CutType::DeltaR,
+            "e" => CutType::Energy,
+            "etmiss" => CutType::ETmiss,
+            _ => CutType::Other(name.to_string()),
+        }
+    }
+
+    /// The value of the `type` attribute for this kind of cut
+    pub fn name(&self) -> &str {
+        match *self {
+            CutType::Mass => "m",
+            CutType::Pt => "pT",
+            CutType::Eta => "eta",
+            CutType::Rapidity => "y",
+            CutType::DeltaR => "deltaR",
+            CutType::Energy => "E",
+            CutType::ETmiss => "ETmiss",
+            CutType::Other(ref name) => name,
+        }
+    }
+}
+
+/// The information about a process, given in a `<procinfo>` tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessInfo {
+    /// The id of the process (`iproc`), corresponding to `LPRUP`
+    pub iproc: i64,
+    /// The loops included in the calculation (`loops`)
+    pub loops: Option<String>,
+    /// The power of alpha strong of the process (`qcdorder`)
+    pub qcdorder: Option<i64>,
+    /// The power of alpha electroweak of the process (`eworder`)
+    pub eworder: Option<i64>,
+    /// The renormalisation scheme (`rscheme`)
+    pub rscheme: Option<String>,
+    /// The factorisation scheme (`fscheme`)
+    pub fscheme: Option<String>,
+    /// The description of the process, with leading and trailing
+    /// whitespace removed
+    pub text: String,
+    /// Any other attributes of the tag
+    pub attributes: Attributes,
+}
+
+impl ProcessInfo {
+    fn from_element(element: &Element) -> Option<ProcessInfo> {
+        let mut attributes = element.attributes.clone();
+        Some(ProcessInfo {
+            iproc: take_attribute(&mut attributes, "iproc").ok()??,
+            loops: attributes.remove("loops"),
+            qcdorder: take_attribute(&mut attributes, "qcdorder").ok()?,
+            eworder: take_attribute(&mut attributes, "eworder").ok()?,
+            rscheme: attributes.remove("rscheme"),
+            fscheme: attributes.remove("fscheme"),
+            text: element.content.trim().to_string(),
+            attributes,
+        })
+    }
+}
+
+impl WriteLhe for ProcessInfo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        attributes.insert("iproc", self.iproc.to_string());
+        if let Some(ref loops) = self.loops {
+            attributes.insert("loops", loops.as_str());
+        }
+        if let Some(qcdorder) = self.qcdorder {
+            attributes.insert("qcdorder", qcdorder.to_string());
+        }
+        if let Some(eworder) = self.eworder {
+            attributes.insert("eworder", eworder.to_string());
+        }
+        if let Some(ref rscheme) = self.rscheme {
+            attributes.insert("rscheme", rscheme.as_str());
+        }
+        if let Some(ref fscheme) = self.fscheme {
+            attributes.insert("fscheme", fscheme.as_str());
+        }
+        attributes.extend(self.attributes.iter().cloned());
+        write_element(writer, "procinfo", &attributes, &self.text)
+    }
+}
+
+/// The information about the merging of a process, given in a
+/// `<mergeinfo>` tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeInfo {
+    /// The id of the process (`iproc`), corresponding to `LPRUP`
+    pub iproc: i64,
+    /// The merging scale (`mergingscale`)
+    pub mergingscale: Option<f64>,
+    /// Whether the process has the maximum multiplicity of the merged
+    /// processes (`maxmult`), `false` by default
+    pub maxmult: bool,
+    /// The content of the tag, with leading and trailing whitespace
+    /// removed
+    pub text: String,
+    /// Any other attributes of the tag
+    pub attributes: Attributes,
+}
+
+impl MergeInfo {
+    fn from_element(element: &Element) -> Option<MergeInfo> {
+        let mut attributes = element.attributes.clone();
+        Some(MergeInfo {
+            iproc: take_attribute(&mut attributes, "iproc").ok()??,
+            mergingscale: take_attribute(&mut attributes, "mergingscale").ok()?,
+            maxmult: take_flag(&mut attributes, "maxmult")?.unwrap_or(false),
+            text: element.content.trim().to_string(),
+            attributes,
+        })
+    }
+}
+
+impl WriteLhe for MergeInfo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        attributes.insert("iproc", self.iproc.to_string());
+        if let Some(mergingscale) = self.mergingscale {
+            attributes.insert("mergingscale", format!("{:e}", mergingscale));
+        }
+        if self.maxmult {
+            attributes.insert("maxmult", "yes");
+        }
+        attributes.extend(self.attributes.iter().cloned());
+        write_element(writer, "mergeinfo", &attributes, &self.text)
+    }
+}
+
+/// Remove the attribute `name` and parse its value
+///
+/// Returns an error if the value can not be parsed.
+fn take_attribute<T: str::FromStr>(
+    attributes: &mut Attributes,
+    name: &str,
+) -> Result<Option<T>, ()> {
+    match attributes.remove(name) {
+        Some(value) => value.trim().parse().map(Some).map_err(|_| ()),
+        None => Ok(None),
+    }
+}
+
+/// Remove the attribute `name` and parse its value as `yes` or `no`
+///
+/// Returns `None` if the value is neither.
+fn take_flag(attributes: &mut Attributes, name: &str) -> Option<Option<bool>> {
+    match attributes.remove(name).as_ref().map(|value| value.trim()) {
+        Some("yes") => Some(Some(true)),
+        Some("no") => Some(Some(false)),
+        Some(_) => None,
+        None => Some(None),
+    }
+}
+
+/// Additional event information, with the event weights parsed
+///
+/// The `<rwgt>` block of the event, if present, is parsed into an
+/// [`Rwgt`], and the compact `<weights>` block into a list of values.
+/// The values in the `<weights>` block belong to the weights declared
+/// in the header, in the order of the declarations.
+/// The `<scales>` tag is parsed into [`Scales`], the `<pdfinfo>` tag
+/// into [`PdfInfo`] and the `<clustering>` block into [`Clustering`].
+/// The rest of the additional information is kept as a string, with
+/// leading and trailing whitespace removed, and is written before the
+/// weights.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::lhef3::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// ## Additional event information
+/// <rwgt>
+/// <wgt id='1001'> 0.73958E+09 </wgt>
+/// <wgt id='1002'> 0.61966E+09 </wgt>
+/// </rwgt>
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let extra = &lhe.events[0].extra;
+/// assert_eq!(extra.extra, "# Additional event information");
+/// let rwgt = extra.rwgt.as_ref().unwrap();
+/// assert_eq!(rwgt.get("1002"), Some(0.61966E+09));
+/// ```
+///
+/// [`Rwgt`]: struct.Rwgt.html
+/// [`Scales`]: struct.Scales.html
+/// [`PdfInfo`]: struct.PdfInfo.html
+/// [`Clustering`]: struct.Clustering.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventExtra {
+    /// The event weights in the `<rwgt>` block
+    pub rwgt: Option<Rwgt>,
+    /// The event weights in the compact `<weights>` block
+    pub weights: Option<Vec<f64>>,
+    /// The scales in the `<scales>` tag
+    pub scales: Option<Scales>,
+    /// The information about the parton densities in the `<pdfinfo>` tag
+    pub pdfinfo: Option<PdfInfo>,
+    /// The clustering history in the `<clustering>` block
+    pub clustering: Option<Clustering>,
+    /// Any other additional event information
+    pub extra: String,
+}
+
+impl ReadLhe for EventExtra {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], EventExtra> {
+        map_opt!(input, take_until!("</event>"), |x| str::from_utf8(x)
+            .ok()
+            .and_then(split_event_extra))
+    }
+}
+
+fn split_event_extra(content: &str) -> Option<EventExtra> {
+    let names = ["rwgt", "weights", "scales", "pdfinfo", "clustering"];
+    let (elements, extra) = split_elements(content, &names)?;
+    let mut event_extra = EventExtra {
+        extra,
+        ..Default::default()
+    };
+    for element in &elements {
+        match element.name {
+            "rwgt" if event_extra.rwgt.is_none() => {
+                event_extra.rwgt = Some(Rwgt::from_element(element)?);
+            }
+            "weights" if event_extra.weights.is_none() => {
+                let weights: Result<_, _> =
+                    element.content.split_whitespace().map(str::parse).collect();
+                event_extra.weights = Some(weights.ok()?);
+            }
+            "scales" if event_extra.scales.is_none() => {
+                event_extra.scales = Some(Scales::from_element(element)?);
+            }
+            "pdfinfo" if event_extra.pdfinfo.is_none() => {
+                event_extra.pdfinfo = Some(PdfInfo::from_element(element)?);
+            }
+            "clustering" if event_extra.clustering.is_none() => {
+                event_extra.clustering = Some(Clustering::from_element(element)?);
+            }
+            _ => return None,
+        }
+    }
+    Some(event_extra)
+}
+
+impl EventExtra {
+    /// The weight with the id `id`
+    ///
+    /// The weight is taken from the `<rwgt>` block if it is present,
+    /// and otherwise from the `<weights>` block, using the position of
+    /// the declaration of the weight in `initrwgt`.
+    pub fn weight(&self, initrwgt: &InitRwgt, id: &str) -> Option<f64> {
+        if let Some(ref rwgt) = self.rwgt {
+            return rwgt.get(id);
+        }
+        let weights = self.weights.as_ref()?;
+        let position = initrwgt.weights().position(|weight| weight.id == id)?;
+        weights.get(position).cloned()
+    }
+
+    /// Convert the weights of the event to the given format
+    ///
+    /// The weights in the other format are removed.
+    /// Returns `false` and leaves the weights unchanged if they don't
+    /// match the declarations in `initrwgt`, i.e. if a declared weight
+    /// is missing in the `<rwgt>` block or if the number of values in
+    /// the `<weights>` block is not the number of declared weights.
+    pub fn convert_weights(&mut self, initrwgt: &InitRwgt, format: WeightFormat) -> bool {
+        match format {
+            WeightFormat::Rwgt => {
+                if let Some(ref weights) = self.weights {
+                    match initrwgt.rwgt_from_compact(weights) {
+                        Some(rwgt) => self.rwgt = Some(rwgt),
+                        None => return false,
+                    }
+                }
+                self.weights = None;
+            }
+            WeightFormat::Compact => {
+                if let Some(ref rwgt) = self.rwgt {
+                    match initrwgt.compact_from_rwgt(rwgt) {
+                        Some(weights) => self.weights = Some(weights),
+                        None => return false,
+                    }
+                }
+                self.rwgt = None;
+            }
+        }
+        true
+    }
+}
+
+/// The ways in which the weights of an event can be written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WeightFormat {
+    /// A `<rwgt>` block with a `<wgt>` tag for each weight
+    Rwgt,
+    /// A compact `<weights>` block with the values of all weights
+    Compact,
+}
+
+impl WriteLhe for EventExtra {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if !self.extra.is_empty() {
+            writeln!(writer, "{}", self.extra)?;
+        }
+        if let Some(ref weights) = self.weights {
+            write_weights(writer, weights)?;
+        }
+        if let Some(ref rwgt) = self.rwgt {
+            rwgt.write_lhe(writer)?;
+        }
+        if let Some(ref scales) = self.scales {
+            scales.write_lhe(writer)?;
+        }
+        if let Some(ref pdfinfo) = self.pdfinfo {
+            pdfinfo.write_lhe(writer)?;
+        }
+        if let Some(ref clustering) = self.clustering {
+            clustering.write_lhe(writer)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_weights<W: io::Write>(writer: &mut W, weights: &[f64]) -> io::Result<()> {
+    let weights: Vec<_> = weights.iter().map(|w| format!("{:e}", w)).collect();
+    write_element(
+        writer,
+        "weights",
+        &Attributes::new(),
+        &format!(" {} ", weights.join(" ")),
+    )
+}
+
+impl Downgrade for EventExtra {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        self.extra = downgrade_text(&self.extra, version, mode);
+        let mut removed = Vec::new();
+        if !version.allows_tag("weights") {
+            if let Some(weights) = self.weights.take() {
+                write_weights(&mut removed, &weights).expect("BUG: Writing to a Vec failed");
+            }
+        }
+        if !version.allows_tag("rwgt") {
+            if let Some(rwgt) = self.rwgt.take() {
+                write_removed(&rwgt, &mut removed);
+            }
+        }
+        if !version.allows_tag("scales") {
+            if let Some(scales) = self.scales.take() {
+                write_removed(&scales, &mut removed);
+            }
+        }
+        if !version.allows_tag("pdfinfo") {
+            if let Some(pdfinfo) = self.pdfinfo.take() {
+                write_removed(&pdfinfo, &mut removed);
+            }
+        }
+        if !version.allows_tag("clustering") {
+            if let Some(clustering) = self.clustering.take() {
+                write_removed(&clustering, &mut removed);
+            }
+        }
+        if mode == DowngradeMode::Comment {
+            comment_out(&mut self.extra, &removed);
+        }
+    }
+}
+
+/// The weights of an event given in a `<rwgt>` block
+///
+/// The weights are kept in the order in which they appear in the
+/// event, and can be looked up by their id.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::lhef3::Rwgt;
+///
+/// let mut rwgt = Rwgt::new();
+/// rwgt.insert("1001", 2.5);
+/// rwgt.insert("1002", 1.5);
+/// assert_eq!(rwgt.get("1002"), Some(1.5));
+/// assert_eq!(rwgt.get("1003"), None);
+///
+/// let ids: Vec<_> = rwgt.iter().map(|w| w.0.as_str()).collect();
+/// assert_eq!(ids, vec!["1001", "1002"]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rwgt {
+    weights: Vec<(String, f64)>,
+    index: HashMap<String, usize>,
+}
+
+impl Rwgt {
+    /// Create an empty set of weights
+    pub fn new() -> Rwgt {
+        Rwgt::default()
+    }
+
+    fn from_element(element: &Element) -> Option<Rwgt> {
+        let (elements, rest) = split_elements(element.content, &["wgt"])?;
+        if !rest.is_empty() {
+            return None;
+        }
+        let mut rwgt = Rwgt::new();
+        for element in &elements {
+            let id = element.attributes.get("id")?;
+            let value = element.content.trim().parse().ok()?;
+            rwgt.insert(id, value);
+        }
+        Some(rwgt)
+    }
+
+    /// The weight with the id `id`
+    pub fn get(&self, id: &str) -> Option<f64> {
+        self.index.get(id).map(|&n| self.weights[n].1)
+    }
+
+    /// Set the weight with the id `id`
+    ///
+    /// If a weight with this id is already present, its value is
+    /// replaced and the old value is returned, otherwise the weight is
+    /// added at the end.
+    pub fn insert<I: Into<String>>(&mut self, id: I, value: f64) -> Option<f64> {
+        let id = id.into();
+        if let Some(&n) = self.index.get(&id) {
+            let old = self.weights[n].1;
+            self.weights[n].1 = value;
+            return Some(old);
+        }
+        self.index.insert(id.clone(), self.weights.len());
+        self.weights.push((id, value));
+        None
+    }
+
+    /// The number of weights
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Check whether there are no weights
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    /// Iterate over the ids and values of the weights, in order
+    pub fn iter(&self) -> slice::Iter<'_, (String, f64)> {
+        self.weights.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Rwgt {
+    type Item = &'a (String, f64);
+    type IntoIter = slice::Iter<'a, (String, f64)>;
+
+    fn into_iter(self) -> slice::Iter<'a, (String, f64)> {
+        self.iter()
+    }
+}
+
+impl WriteLhe for Rwgt {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "<rwgt>")?;
+        for &(ref id, value) in &self.weights {
+            let mut attributes = Attributes::new();
+            attributes.insert("id", id.as_str());
+            write_element(writer, "wgt", &attributes, &format!(" {:e} ", value))?;
+        }
+        writeln!(writer, "</rwgt>")
+    }
+}
+
+/// The scales of an event given in a `<scales>` tag
+///
+/// Attributes whose name ends in an underscore followed by a number,
+/// like `pt_start_3`, are scales for a single particle, where the
+/// number is the position of the particle in the event, counting from
+/// one.
+/// All other attributes are named scales of the event.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::lhef3::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// <scales muf=\"45.6\" mups=\"30\" pt_start_2=\"20\"></scales>
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let event = &lhe.events[0];
+/// assert_eq!(event.factorisation_scale(), 45.6);
+/// assert_eq!(event.renormalisation_scale(), 91.2);
+/// let scales = event.extra.scales.as_ref().unwrap();
+/// assert_eq!(scales.particle_scale("pt_start", 2), Some(20.));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scales {
+    /// The factorisation scale
+    pub muf: Option<f64>,
+    /// The renormalisation scale
+    pub mur: Option<f64>,
+    /// The starting scale of the parton shower
+    pub mups: Option<f64>,
+    /// Any other scales of the event, by name
+    pub named: Vec<(String, f64)>,
+    /// The scales for single particles
+    pub particles: Vec<ParticleScale>,
+}
+
+/// A scale for a single particle in a `<scales>` tag
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParticleScale {
+    /// The name of the scale, e.g. `pt_start`
+    pub name: String,
+    /// The position of the particle in the event, counting from one
+    pub particle: usize,
+    /// The value of the scale
+    pub value: f64,
+}
+
+impl Scales {
+    fn from_element(element: &Element) -> Option<Scales> {
+        if !element.content.trim().is_empty() {
+            return None;
+        }
+        let mut scales = Scales::default();
+        for (name, value) in &element.attributes {
+            let value = value.trim().parse().ok()?;
+            match name.as_str() {
+                "muf" => scales.muf = Some(value),
+                "mur" => scales.mur = Some(value),
+                "mups" => scales.mups = Some(value),
+                _ => match split_particle_index(name) {
+                    Some((name, particle)) => scales.particles.push(ParticleScale {
+                        name: name.to_string(),
+                        particle,
+                        value,
+                    }),
+                    None => scales.named.push((name.clone(), value)),
+                },
+            }
+        }
+        Some(scales)
+    }
+
+    /// The scale with the given name
+    ///
+    /// This includes `muf`, `mur` and `mups`, but not the scales for
+    /// single particles.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        match name {
+            "muf" => self.muf,
+            "mur" => self.mur,
+            "mups" => self.mups,
+            _ => self
+                .named
+                .iter()
+                .find(|scale| scale.0 == name)
+                .map(|scale| scale.1),
+        }
+    }
+
+    /// The scale with the given name for the particle at position
+    /// `particle` in the event, counting from one
+    pub fn particle_scale(&self, name: &str, particle: usize) -> Option<f64> {
+        self.particles
+            .iter()
+            .find(|scale| scale.name == name && scale.particle == particle)
+            .map(|scale| scale.value)
+    }
+}
+
+/// Split an attribute name like `pt_start_3` into `pt_start` and 3
+fn split_particle_index(name: &str) -> Option<(&str, usize)> {
+    let underscore = name.rfind('_')?;
+    let (name, index) = (&name[..underscore], &name[underscore + 1..]);
+    if name.is_empty() || index.is_empty() || !index.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((name, index.parse().ok()?))
+}
+
+impl WriteLhe for Scales {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        let main = [("muf", self.muf), ("mur", self.mur), ("mups", self.mups)];
+        for &(name, value) in &main {
+            if let Some(value) = value {
+                attributes.insert(name, format!("{:e}", value));
+            }
+        }
+        for &(ref name, value) in &self.named {
+            attributes.insert(name.as_str(), format!("{:e}", value));
+        }
+        for scale in &self.particles {
+            let name = format!("{}_{}", scale.name, scale.particle);
+            attributes.insert(name, format!("{:e}", scale.value));
+        }
+        write_element(writer, "scales", &attributes, "")
+    }
+}
+
+impl EventGeneric<EventExtra> {
+    /// The factorisation scale of the event
+    ///
+    /// This is `muf` from the `<scales>` tag if it is given, and the
+    /// scale of the event (`SCALUP`) otherwise.
+    pub fn factorisation_scale(&self) -> f64 {
+        self.scale_or_default(|scales| scales.muf)
+    }
+
+    /// The renormalisation scale of the event
+    ///
+    /// This is `mur` from the `<scales>` tag if it is given, and the
+    /// scale of the event (`SCALUP`) otherwise.
+    pub fn renormalisation_scale(&self) -> f64 {
+        self.scale_or_default(|scales| scales.mur)
+    }
+
+    /// The starting scale of the parton shower
+    ///
+    /// This is `mups` from the `<scales>` tag if it is given, and the
+    /// scale of the event (`SCALUP`) otherwise.
+    pub fn shower_scale(&self) -> f64 {
+        self.scale_or_default(|scales| scales.mups)
+    }
+
+    fn scale_or_default<F: Fn(&Scales) -> Option<f64>>(&self, scale: F) -> f64 {
+        self.extra
+            .scales
+            .as_ref()
+            .and_then(scale)
+            .unwrap_or(self.scale)
+    }
+}
+
+/// The information about the parton densities of an event, given in a
+/// `<pdfinfo>` tag
+///
+/// The scale at which the parton densities were evaluated is taken from
+/// the content of the tag, or from a `scale` attribute.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::lhef3::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// <pdfinfo p1=\"21\" p2=\"21\" x1=\"0.012\" x2=\"0.034\">91.2</pdfinfo>
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let pdfinfo = lhe.events[0].extra.pdfinfo.as_ref().unwrap();
+/// assert_eq!(pdfinfo.p1, Some(21));
+/// assert_eq!(pdfinfo.x2, Some(0.034));
+/// assert_eq!(pdfinfo.scale, Some(91.2));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PdfInfo {
+    /// The pdg id of the first incoming parton (`p1`)
+    pub p1: Option<PdgId>,
+    /// The pdg id of the second incoming parton (`p2`)
+    pub p2: Option<PdgId>,
+    /// The momentum fraction of the first incoming parton (`x1`)
+    pub x1: Option<f64>,
+    /// The momentum fraction of the second incoming parton (`x2`)
+    pub x2: Option<f64>,
+    /// The value of the parton density of the first incoming parton,
+    /// multiplied by `x1` (`xf1`)
+    pub xf1: Option<f64>,
+    /// The value of the parton density of the second incoming parton,
+    /// multiplied by `x2` (`xf2`)
+    pub xf2: Option<f64>,
+    /// The scale at which the parton densities were evaluated
+    pub scale: Option<f64>,
+    /// Any other attributes of the tag
+    pub attributes: Attributes,
+}
+
+impl PdfInfo {
+    fn from_element(element: &Element) -> Option<PdfInfo> {
+        let mut attributes = element.attributes.clone();
+        let mut scale = take_attribute(&mut attributes, "scale").ok()?;
+        let content = element.content.trim();
+        if !content.is_empty() {
+            scale = Some(content.parse().ok()?);
+        }
+        Some(PdfInfo {
+            p1: take_attribute(&mut attributes, "p1").ok()?,
+            p2: take_attribute(&mut attributes, "p2").ok()?,
+            x1: take_attribute(&mut attributes, "x1").ok()?,
+            x2: take_attribute(&mut attributes, "x2").ok()?,
+            xf1: take_attribute(&mut attributes, "xf1").ok()?,
+            xf2: take_attribute(&mut attributes, "xf2").ok()?,
+            scale,
+            attributes,
+        })
+    }
+}
+
+impl WriteLhe for PdfInfo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        for &(name, id) in &[("p1", self.p1), ("p2", self.p2)] {
+            if let Some(id) = id {
+                attributes.insert(name, id.to_string());
+            }
+        }
+        let values = [
+            ("x1", self.x1),
+            ("x2", self.x2),
+            ("xf1", self.xf1),
+            ("xf2", self.xf2),
+        ];
+        for &(name, value) in &values {
+            if let Some(value) = value {
+                attributes.insert(name, format!("{:e}", value));
+            }
+        }
+        attributes.extend(self.attributes.iter().cloned());
+        let scale = self.scale.map(|s| format!("{:e}", s)).unwrap_or_default();
+        write_element(writer, "pdfinfo", &attributes, &scale)
+    }
+}
+
+/// The clustering history of an event, given in a `<clustering>` block
+///
+/// Each `<clus>` tag in the block describes a step in which two entries
+/// were clustered into one.
+/// The entries are numbered from one, with the numbers up to the number
+/// of particles referring to the particles of the event.
+/// The result of a step replaces the entry `p0`, so that later steps can
+/// refer to it.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::lhef3::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 4 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 45.6 45.6 0.0 0. 9.
+/// 21 -1 0 0 502 503 0.0 0.0 -45.6 45.6 0.0 0. 9.
+/// 21 1 1 2 501 504 20.0 0.0 0.0 20.0 0.0 0. 9.
+/// 21 1 1 2 504 503 -20.0 0.0 0.0 20.0 0.0 0. 9.
+/// <clustering>
+/// <clus scale=\"20\"> 3 4 </clus>
+/// <clus scale=\"45\" alphas=\"0.13\"> 1 3 </clus>
+/// </clustering>
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let event = &lhe.events[0];
+/// let clustering = event.extra.clustering.as_ref().unwrap();
+/// assert_eq!(clustering.steps[1].alphas, Some(0.13));
+/// // The second step clusters the first particle with the result of
+/// // the first step
+/// assert_eq!(event.clustered_particles(1), Some((vec![0], vec![2, 3])));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Clustering {
+    /// The clustering steps, in the order in which they were performed
+    pub steps: Vec<ClusteringStep>,
+}
+
+impl Clustering {
+    fn from_element(element: &Element) -> Option<Clustering> {
+        let (elements, rest) = split_elements(element.content, &["clus"])?;
+        if !rest.is_empty() {
+            return None;
+        }
+        let steps: Option<_> = elements.iter().map(ClusteringStep::from_element).collect();
+        Some(Clustering { steps: steps? })
+    }
+
+    /// The particles in the two entries clustered in the step with
+    /// index `step`, counting from zero
+    ///
+    /// The particles are given as indices into the particles of an
+    /// event with `n_particles` particles, counting from zero.
+    /// Returns `None` if the step doesn't exist or if one of the
+    /// entries is neither a particle nor the result of an earlier step.
+    pub fn particles(&self, step: usize, n_particles: usize) -> Option<(Vec<usize>, Vec<usize>)> {
+        let current = self.steps.get(step)?;
+        // The particles in the results of all earlier steps, resolved
+        // once in order
+        let mut results = Vec::with_capacity(step);
+        for earlier in &self.steps[..step] {
+            let result = self
+                .resolve(&results, earlier, n_particles)
+                .map(|(mut first, second)| {
+                    first.extend(second);
+                    first.sort();
+                    first.dedup();
+                    first
+                });
+            results.push(result);
+        }
+        self.resolve(&results, current, n_particles)
+    }
+
+    /// The particles in the two entries of `step`, given the particles
+    /// in the `results` of all steps before it
+    fn resolve(
+        &self,
+        results: &[Option<Vec<usize>>],
+        step: &ClusteringStep,
+        n_particles: usize,
+    ) -> Option<(Vec<usize>, Vec<usize>)> {
+        let entry = |entry: usize| {
+            let earlier = self.steps[..results.len()]
+                .iter()
+                .rposition(|earlier| earlier.p0 == entry);
+            match earlier {
+                Some(earlier) => results[earlier].clone(),
+                None if entry >= 1 && entry <= n_particles => Some(vec![entry - 1]),
+                None => None,
+            }
+        };
+        Some((entry(step.p1)?, entry(step.p2)?))
+    }
+}
+
+impl WriteLhe for Clustering {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "<clustering>")?;
+        for step in &self.steps {
+            step.write_lhe(writer)?;
+        }
+        writeln!(writer, "</clustering>")
+    }
+}
+
+/// A clustering step, given in a `<clus>` tag
+///
+/// The entries are taken from the content of the tag, `p1 p2 [p0]`, or
+/// from the attributes of the same name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusteringStep {
+    /// The first of the clustered entries, counting from one (`p1`)
+    pub p1: usize,
+    /// The second of the clustered entries, counting from one (`p2`)
+    pub p2: usize,
+    /// The entry replaced by the result of the clustering, counting
+    /// from one (`p0`)
+    ///
+    /// This is the same as `p1` if it is not given.
+    pub p0: usize,
+    /// The scale of the clustering step
+    pub scale: Option<f64>,
+    /// The value of alpha strong used for the clustering step
+    pub alphas: Option<f64>,
+    /// Any other attributes of the tag
+    pub attributes: Attributes,
+}
+
+impl ClusteringStep {
+    fn from_element(element: &Element) -> Option<ClusteringStep> {
+        let mut attributes = element.attributes.clone();
+        let entries: Result<Vec<usize>, _> =
+            element.content.split_whitespace().map(str::parse).collect();
+        let (p1, p2, p0) = match entries.ok()?.as_slice() {
+            [] => {
+                let p1 = take_attribute(&mut attributes, "p1").ok()??;
+                let p2 = take_attribute(&mut attributes, "p2").ok()??;
+                let p0 = take_attribute(&mut attributes, "p0").ok()?;
+                (p1, p2, p0.unwrap_or(p1))
+            }
+            [p1, p2] => (*p1, *p2, *p1),
+            [p1, p2, p0] => (*p1, *p2, *p0),
+            _ => return None,
+        };
+        Some(ClusteringStep {
+            p1,
+            p2,
+            p0,
+            scale: take_attribute(&mut attributes, "scale").ok()?,
+            alphas: take_attribute(&mut attributes, "alphas").ok()?,
+            attributes,
+        })
+    }
+}
+
+impl WriteLhe for ClusteringStep {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut attributes = Attributes::new();
+        if let Some(scale) = self.scale {
+            attributes.insert("scale", format!("{:e}", scale));
+        }
+        if let Some(alphas) = self.alphas {
+            attributes.insert("alphas", format!("{:e}", alphas));
+        }
+        attributes.extend(self.attributes.iter().cloned());
+        let content = if self.p0 == self.p1 {
+            format!(" {} {} ", self.p1, self.p2)
+        } else {
+            format!(" {} {} {} ", self.p1, self.p2, self.p0)
+        };
+        write_element(writer, "clus", &attributes, &content)
+    }
+}
+
+impl EventGeneric<EventExtra> {
+    /// The particles clustered in the step with index `step` of the
+    /// clustering history of the event, counting from zero
+    ///
+    /// The particles in each of the two clustered entries are given as
+    /// indices into `particles`.
+    /// See `Clustering::particles` for details.
+    pub fn clustered_particles(&self, step: usize) -> Option<(Vec<usize>, Vec<usize>)> {
+        self.extra
+            .clustering
+            .as_ref()?
+            .particles(step, self.particles.len())
+    }
+}
+
+/// A mismatch between the weights of an event and the weights declared
+/// in the header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WeightMismatch {
+    /// The event has a weight with an id that is not declared
+    Undeclared(String),
+    /// A declared weight is missing in the event
+    Missing(String),
+    /// The number of values in the `<weights>` block of the event is
+    /// not the number of declared weights
+    Count {
+        /// The number of declared weights
+        declared: usize,
+        /// The number of values in the event
+        found: usize,
+    },
+}
+
+impl fmt::Display for WeightMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WeightMismatch::Undeclared(ref id) => write!(f, "weight {} is not declared", id),
+            WeightMismatch::Missing(ref id) => write!(f, "declared weight {} is missing", id),
+            WeightMismatch::Count { declared, found } => write!(
+                f,
+                "{} weights are declared, but the event has {}",
+                declared, found
+            ),
+        }
+    }
+}
+
+impl InitRwgt {
+    /// Compare the ids of the weights of an event with the declared
+    /// weights
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lhef::attributes::Attributes;
+    /// use lhef::lhef3::{InitRwgt, Rwgt, Weight, WeightMismatch};
+    ///
+    /// let initrwgt = InitRwgt {
+    ///     groups: vec![],
+    ///     weights: vec![Weight {
+    ///         id: "1001".to_string(),
+    ///         text: String::new(),
+    ///         attributes: Attributes::new(),
+    ///     }],
+    /// };
+    /// let mut rwgt = Rwgt::new();
+    /// rwgt.insert("1002", 1.);
+    /// assert_eq!(
+    ///     initrwgt.check_ids(&rwgt),
+    ///     vec![
+    ///         WeightMismatch::Undeclared("1002".to_string()),
+    ///         WeightMismatch::Missing("1001".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn check_ids(&self, rwgt: &Rwgt) -> Vec<WeightMismatch> {
+        let undeclared = rwgt
+            .iter()
+            .filter(|weight| self.weight(&weight.0).is_none())
+            .map(|weight| WeightMismatch::Undeclared(weight.0.clone()));
+        let missing = self
+            .weights()
+            .filter(|weight| rwgt.get(&weight.id).is_none())
+            .map(|weight| WeightMismatch::Missing(weight.id.clone()));
+        undeclared.chain(missing).collect()
+    }
+
+    /// Assign the values of a compact `<weights>` block to the ids of
+    /// the declared weights
+    ///
+    /// Returns `None` if the number of values is not the number of
+    /// declared weights.
+    pub fn rwgt_from_compact(&self, weights: &[f64]) -> Option<Rwgt> {
+        if weights.len() != self.weights().count() {
+            return None;
+        }
+        let mut rwgt = Rwgt::new();
+        for (weight, &value) in self.weights().zip(weights) {
+            rwgt.insert(weight.id.as_str(), value);
+        }
+        Some(rwgt)
+    }
+
+    /// Order the weights of a `<rwgt>` block like the declared weights,
+    /// for a compact `<weights>` block
+    ///
+    /// Returns `None` if a declared weight is missing in `rwgt`.
+    /// Weights in `rwgt` that are not declared are dropped.
+    pub fn compact_from_rwgt(&self, rwgt: &Rwgt) -> Option<Vec<f64>> {
+        self.weights().map(|weight| rwgt.get(&weight.id)).collect()
+    }
+}
+
+impl<Comment, InitExtra> LheFileGeneric<Comment, Header, InitExtra, EventExtra> {
+    /// Compare the ids of the weights of all events with the weights
+    /// declared in the header
+    ///
+    /// Returns the index of the event together with each mismatch.
+    /// For events with a compact `<weights>` block, only the number of
+    /// values is checked.
+    /// Nothing is checked if the header doesn't declare any weights.
+    pub fn check_weight_ids(&self) -> Vec<(usize, WeightMismatch)> {
+        let initrwgt = match self.header.initrwgt {
+            Some(ref initrwgt) => initrwgt,
+            None => return Vec::new(),
+        };
+        let declared = initrwgt.weights().count();
+        let mut mismatches = Vec::new();
+        for (n, event) in self.events.iter().enumerate() {
+            if let Some(ref rwgt) = event.extra.rwgt {
+                let event_mismatches = initrwgt.check_ids(rwgt);
+                mismatches.extend(event_mismatches.into_iter().map(|m| (n, m)));
+            }
+            if let Some(ref weights) = event.extra.weights {
+                if weights.len() != declared {
+                    let found = weights.len();
+                    mismatches.push((n, WeightMismatch::Count { declared, found }));
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// Convert the weights of all events to the given format
+    ///
+    /// Returns the index of the first event whose weights don't match
+    /// the declarations in the header as an error, see
+    /// `EventExtra::convert_weights`.
+    /// In this case, no event is converted.
+    /// If the header doesn't declare any weights, only events without
+    /// weights in the other format can be converted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// use lhef::ReadLhe;
+    /// use lhef::WriteLhe;
+    /// use lhef::lhef3::{LheFile, WeightFormat};
+    ///
+    /// let mut lhe = LheFile::read_lhe_from_file(&"events.lhe").unwrap();
+    /// lhe.convert_weights(WeightFormat::Compact).unwrap();
+    /// lhe.write_lhe(&mut writer).unwrap();
+    /// ```
+    pub fn convert_weights(&mut self, format: WeightFormat) -> Result<(), usize> {
+        let no_declarations = InitRwgt::default();
+        let initrwgt = self.header.initrwgt.as_ref().unwrap_or(&no_declarations);
+        // All events are converted first, so that the file is left
+        // unchanged if one of them fails
+        match format {
+            WeightFormat::Rwgt => {
+                let converted = self
+                    .events
+                    .iter()
+                    .enumerate()
+                    .map(|(n, event)| match event.extra.weights {
+                        Some(ref weights) => initrwgt.rwgt_from_compact(weights).map(Some).ok_or(n),
+                        None => Ok(None),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                for (event, rwgt) in self.events.iter_mut().zip(converted) {
+                    if rwgt.is_some() {
+                        event.extra.rwgt = rwgt;
+                    }
+                    event.extra.weights = None;
+                }
+            }
+            WeightFormat::Compact => {
+                let converted = self
+                    .events
+                    .iter()
+                    .enumerate()
+                    .map(|(n, event)| match event.extra.rwgt {
+                        Some(ref rwgt) => initrwgt.compact_from_rwgt(rwgt).map(Some).ok_or(n),
+                        None => Ok(None),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                for (event, weights) in self.events.iter_mut().zip(converted) {
+                    if weights.is_some() {
+                        event.extra.weights = weights;
+                    }
+                    event.extra.rwgt = None;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {ReadLhe, WriteLhe};
+    use generic::LheFileGeneric;
+    use string;
+
+    use attributes::Attributes;
+
+    use super::{
+        Clustering, ClusteringStep, Combine, Cut, CutType, CutsInfo, LheFile, ParticleScale,
+        ParticleType, PdfInfo, Rwgt, Scales, WeightFormat, WeightMismatch,
+    };
+
+    use std::str;
+
+    #[test]
+    fn read_initrwgt() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO.lhe").unwrap();
+        let initrwgt = lhe.header.initrwgt.as_ref().unwrap();
+        assert_eq!(initrwgt.groups.len(), 1);
+        let group = &initrwgt.groups[0];
+        assert_eq!(group.name, Some("scale_variation   -1".to_string()));
+        assert_eq!(group.combine, Some(Combine::Envelope));
+        let ids: Vec<_> = initrwgt.weights().map(|w| w.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["1001", "1002", "1003", "1004", "1005", "1006", "1007", "1008", "1009"]
+        );
+        let weight = initrwgt.weight("1006").unwrap();
+        assert_eq!(weight.text, "dyn=  -1 muR=0.50000E+00 muF=0.20000E+01");
+        assert_eq!(weight.parameter("muR"), Some(0.5));
+        assert_eq!(weight.parameter("muF"), Some(2.));
+        assert_eq!(initrwgt.group_of("1006"), Some(group));
+
+        let header = lhe.header.header.as_ref().unwrap();
+        assert!(header.ends_with("</MonteCarloMasses>"));
+        assert!(!header.contains("initrwgt"));
+    }
+
+    #[test]
+    fn without_initrwgt() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        assert_eq!(lhe.header.initrwgt, None);
+        let plain: string::LheFile =
+            LheFileGeneric::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        assert_eq!(lhe.header.header, plain.header.header);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO.lhe").unwrap();
+        let mut bytes = Vec::new();
+        lhe.write_lhe(&mut bytes).unwrap();
+        let round = LheFile::read_lhe(&bytes).to_full_result().unwrap();
+        assert_eq!(lhe, round);
+    }
+
+    #[test]
+    fn read_rwgt() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO.lhe").unwrap();
+        let extra = &lhe.events[0].extra;
+        let rwgt = extra.rwgt.as_ref().unwrap();
+        assert_eq!(rwgt.len(), 9);
+        assert_eq!(rwgt.get("1001"), Some(0.73958E+09));
+        assert_eq!(rwgt.get("1009"), Some(0.45731E+09));
+        assert!(extra.extra.starts_with("#aMCatNLO 1  0  0  1  3 "));
+        assert!(!extra.extra.contains("rwgt"));
+        assert_eq!(lhe.check_weight_ids(), vec![]);
+    }
+
+    #[test]
+    fn weight_mismatches() {
+        let mut lhe =
+            LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO.lhe").unwrap();
+        {
+            let rwgt = lhe.events[1].extra.rwgt.as_mut().unwrap();
+            rwgt.insert("2001", 1.);
+        }
+        lhe.header.initrwgt.as_mut().unwrap().groups[0]
+            .weights
+            .pop();
+        let mismatches = lhe.check_weight_ids();
+        assert_eq!(mismatches.len(), lhe.events.len() + 1);
+        assert_eq!(
+            mismatches[0],
+            (0, WeightMismatch::Undeclared("1009".to_string()))
+        );
+        assert!(mismatches.contains(&(1, WeightMismatch::Undeclared("2001".to_string()))));
+    }
+
+    #[test]
+    fn compact_weights() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO.lhe").unwrap();
+        let initrwgt = lhe.header.initrwgt.clone().unwrap();
+        let mut compact = lhe.clone();
+        compact.convert_weights(WeightFormat::Compact).unwrap();
+        let extra = &compact.events[0].extra;
+        assert_eq!(extra.rwgt, None);
+        let weights = extra.weights.as_ref().unwrap();
+        assert_eq!(weights.len(), 9);
+        assert_eq!(weights[1], 0.61966E+09);
+        assert_eq!(extra.weight(&initrwgt, "1002"), Some(0.61966E+09));
+        assert_eq!(compact.check_weight_ids(), vec![]);
+
+        let mut bytes = Vec::new();
+        compact.write_lhe(&mut bytes).unwrap();
+        assert!(str::from_utf8(&bytes)
+            .unwrap()
+            .contains("<weights> 7.3958e8 6.1966e8 "));
+        let mut round = LheFile::read_lhe(&bytes).to_full_result().unwrap();
+        assert_eq!(round, compact);
+
+        round.convert_weights(WeightFormat::Rwgt).unwrap();
+        assert_eq!(round, lhe);
+    }
+
+    #[test]
+    fn compact_weight_mismatches() {
+        let mut lhe =
+            LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO.lhe").unwrap();
+        lhe.events[2].extra.rwgt = None;
+        lhe.events[2].extra.weights = Some(vec![1., 2.]);
+        assert_eq!(
+            lhe.check_weight_ids(),
+            vec![(
+                2,
+                WeightMismatch::Count {
+                    declared: 9,
+                    found: 2,
+                }
+            )]
+        );
+        assert_eq!(lhe.convert_weights(WeightFormat::Rwgt), Err(2));
+
+        lhe.events[2].extra.weights = None;
+        lhe.events[2].extra.rwgt = Some(Rwgt::new());
+        let unchanged = lhe.clone();
+        assert!(unchanged.events[0].extra.rwgt.is_some());
+        assert_eq!(lhe.convert_weights(WeightFormat::Compact), Err(2));
+        assert_eq!(lhe, unchanged);
+    }
+
+    #[test]
+    fn scales() {
+        let mut lhe =
+            LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO.lhe").unwrap();
+        assert_eq!(lhe.events[0].shower_scale(), lhe.events[0].scale);
+        let scales = Scales {
+            muf: Some(2.),
+            mur: None,
+            mups: Some(3.5),
+            named: vec![("qcut".to_string(), 20.)],
+            particles: vec![ParticleScale {
+                name: "pt_start".to_string(),
+                particle: 3,
+                value: 1.5,
+            }],
+        };
+        lhe.events[0].extra.scales = Some(scales.clone());
+        let mut bytes = Vec::new();
+        lhe.write_lhe(&mut bytes).unwrap();
+        assert!(str::from_utf8(&bytes).unwrap().contains(
+            "<scales muf=\"2e0\" mups=\"3.5e0\" qcut=\"2e1\" pt_start_3=\"1.5e0\"></scales>"
+        ));
+        let round = LheFile::read_lhe(&bytes).to_full_result().unwrap();
+        assert_eq!(round, lhe);
+        let event = &round.events[0];
+        assert_eq!(event.factorisation_scale(), 2.);
+        assert_eq!(event.renormalisation_scale(), event.scale);
+        assert_eq!(event.shower_scale(), 3.5);
+        assert_eq!(scales.get("qcut"), Some(20.));
+        assert_eq!(scales.particle_scale("pt_start", 3), Some(1.5));
+        assert_eq!(scales.particle_scale("pt_start", 2), None);
+    }
+
+    #[test]
+    fn read_generator() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let extra = &lhe.init.extra;
+        assert_eq!(extra.generators.len(), 1);
+        let generator = &extra.generators[0];
+        assert_eq!(generator.name, Some("MadGraph5_aMC@NLO".to_string()));
+        assert_eq!(generator.version, Some("2.5.1".to_string()));
+        assert_eq!(generator.text, "please cite 1405.0301");
+        assert_eq!(extra.xsecinfo, None);
+        assert_eq!(extra.extra, "");
+    }
+
+    #[test]
+    fn init_roundtrip() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 2
+2.1 3.2E-03 1.0E+00 1
+2.1 3.2E-03 1.0E+00 2
+# unknown information
+<xsecinfo neve=\"20\" totxsec=\"4.2\" maxweight=\"2.5\" varweights=\"yes\" weightname=\"w\"/>
+<procinfo iproc=\"2\" loops=\"1\" fscheme=\"MSbar\">g g > t t~</procinfo>
+<mergeinfo iproc=\"1\" mergingscale=\"30\" maxmult=\"no\"></mergeinfo>
+<unknown tag='1'/>
+</init>
+</LesHouchesEvents>
+";
+        let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let extra = &lhe.init.extra;
+        assert_eq!(extra.extra, "# unknown information\n<unknown tag='1'/>");
+        let xsecinfo = extra.xsecinfo.as_ref().unwrap();
+        assert_eq!(xsecinfo.totxsec, 4.2);
+        assert_eq!(xsecinfo.maxweight, 2.5);
+        assert_eq!(xsecinfo.meanweight, 1.);
+        assert!(!xsecinfo.negweights);
+        assert!(xsecinfo.varweights);
+        assert_eq!(xsecinfo.attributes.get("weightname"), Some("w"));
+        let procinfo = extra.procinfo(2).unwrap();
+        assert_eq!(procinfo.loops, Some("1".to_string()));
+        assert_eq!(procinfo.qcdorder, None);
+        assert_eq!(procinfo.text, "g g > t t~");
+        assert_eq!(extra.procinfo(1), None);
+        assert_eq!(extra.mergeinfo(1).unwrap().mergingscale, Some(30.));
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        let round = LheFile::read_lhe(&written).to_full_result().unwrap();
+        assert_eq!(lhe, round);
+    }
+
+    #[test]
+    fn duplicate_init_tags() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+<xsecinfo neve=\"20\" totxsec=\"4.2\"/>
+<cutsinfo><cut type=\"eta\" p1=\"j\">-2.5 2.5</cut></cutsinfo>
+<xsecinfo neve=\"10\" totxsec=\"2.1\"/>
+<cutsinfo></cutsinfo>
+</init>
+</LesHouchesEvents>
+";
+        let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let extra = &lhe.init.extra;
+        assert_eq!(extra.xsecinfo.as_ref().unwrap().totxsec, 4.2);
+        assert_eq!(extra.cutsinfo.as_ref().unwrap().cuts.len(), 1);
+        assert_eq!(
+            extra.extra,
+            "<xsecinfo neve=\"10\" totxsec=\"2.1\"/>\n<cutsinfo></cutsinfo>"
+        );
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        let round = LheFile::read_lhe(&written).to_full_result().unwrap();
+        assert_eq!(lhe, round);
+    }
+
+    #[test]
+    fn invalid_init_tags() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+<xsecinfo totxsec=\"4.2\"/>
+</init>
+</LesHouchesEvents>
+";
+        assert!(LheFile::read_lhe_from_bytes(bytes).is_err());
+    }
+
+    fn cut(cut_type: CutType, p1: Option<&str>, p2: Option<&str>, min: f64, max: f64) -> Cut {
+        Cut {
+            cut_type,
+            p1: p1.map(|p| p.to_string()),
+            p2: p2.map(|p| p.to_string()),
+            min: Some(min),
+            max: Some(max),
+            attributes: Attributes::new(),
+        }
+    }
+
+    #[test]
+    fn evaluate_cuts() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        // e+ e- > mu+ mu-: all final state particles have an energy of 500
+        let event = &lhe.events[0];
+        let mut cutsinfo = CutsInfo {
+            ptypes: vec![ParticleType {
+                name: "muons".to_string(),
+                pdg_ids: vec![13, -13],
+                attributes: Attributes::new(),
+            }],
+            cuts: vec![
+                cut(CutType::Energy, Some("muons"), None, 499., 501.),
+                cut(CutType::Mass, Some("13"), Some("-13"), 999., 1001.),
+                cut(CutType::DeltaR, None, None, 3., 10.),
+                cut(CutType::ETmiss, Some("0"), None, 0., 1e-6),
+                cut(CutType::Other("HT".to_string()), None, None, 0., 0.),
+            ],
+        };
+        assert!(cutsinfo.passes(event));
+
+        cutsinfo
+            .cuts
+            .push(cut(CutType::Pt, Some("muons"), None, 0., 1.));
+        cutsinfo
+            .cuts
+            .push(cut(CutType::Eta, Some("11"), None, 0., 0.));
+        assert!(!cutsinfo.passes(event));
+        assert_eq!(cutsinfo.failed_cuts(event), vec![&cutsinfo.cuts[5]]);
+    }
+
+    #[test]
+    fn cutsinfo_roundtrip() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+<cutsinfo>
+<ptype name=\"ljets\">1 2 3 4 -1 -2 -3 -4 21</ptype>
+<cut type=\"ETA\" p1=\"ljets\"> -5.0 5.0 </cut>
+<cut type=\"kt\" p1=\"ljets\" min=\"20\"/>
+<cut type=\"xf\" p1=\"1\"> 0.5 </cut>
+</cutsinfo>
+</init>
+</LesHouchesEvents>
+";
+        let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let cutsinfo = lhe.init.extra.cutsinfo.as_ref().unwrap();
+        assert_eq!(cutsinfo.ptype("ljets").unwrap().pdg_ids.len(), 9);
+        assert_eq!(
+            cutsinfo.cuts[0],
+            cut(CutType::Eta, Some("ljets"), None, -5., 5.)
+        );
+        assert_eq!(cutsinfo.cuts[1].cut_type, CutType::Pt);
+        assert_eq!(cutsinfo.cuts[1].max, None);
+        assert_eq!(cutsinfo.cuts[2].cut_type, CutType::Other("xf".to_string()));
+        assert_eq!(cutsinfo.cuts[2].min, Some(0.5));
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        let round = LheFile::read_lhe(&written).to_full_result().unwrap();
+        assert_eq!(lhe, round);
+    }
+
+    #[test]
+    fn pdfinfo_and_clustering() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<event>
+5 1 1.0 91.2 0.0078 0.118
+21 -1 0 0 501 502 0.0 0.0 45.6 45.6 0.0 0. 9.
+21 -1 0 0 502 503 0.0 0.0 -45.6 45.6 0.0 0. 9.
+21 1 1 2 501 504 20.0 0.0 0.0 20.0 0.0 0. 9.
+21 1 1 2 504 505 -20.0 0.0 0.0 20.0 0.0 0. 9.
+21 1 1 2 505 503 0.0 0.0 0.0 0.0 0.0 0. 9.
+<pdfinfo p1=\"21\" p2=\"-2\" x1=\"0.1\" x2=\"0.2\" xf1=\"1.5\" scale=\"45\" pdf=\"nnpdf\"></pdfinfo>
+<clustering>
+<clus scale=\"10\"> 4 5 6 </clus>
+<clus p1=\"3\" p2=\"6\" scale=\"20\"/>
+<clus alphas=\"0.12\"> 1 3 </clus>
+<clus> 2 7 </clus>
+</clustering>
+</event>
+</LesHouchesEvents>
+";
+        let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let event = &lhe.events[0];
+        let pdfinfo = event.extra.pdfinfo.as_ref().unwrap();
+        let mut attributes = Attributes::new();
+        attributes.insert("pdf", "nnpdf".to_string());
+        assert_eq!(
+            pdfinfo,
+            &PdfInfo {
+                p1: Some(21),
+                p2: Some(-2),
+                x1: Some(0.1),
+                x2: Some(0.2),
+                xf1: Some(1.5),
+                xf2: None,
+                scale: Some(45.),
+                attributes,
+            }
+        );
+
+        let clustering = event.extra.clustering.as_ref().unwrap();
+        assert_eq!(
+            clustering.steps[1],
+            ClusteringStep {
+                p1: 3,
+                p2: 6,
+                p0: 3,
+                scale: Some(20.),
+                alphas: None,
+                attributes: Attributes::new(),
+            }
+        );
+        assert_eq!(clustering.steps[0].p0, 6);
+        assert_eq!(event.clustered_particles(0), Some((vec![3], vec![4])));
+        assert_eq!(event.clustered_particles(1), Some((vec![2], vec![3, 4])));
+        assert_eq!(event.clustered_particles(2), Some((vec![0], vec![2, 3, 4])));
+        // Entry 7 is neither a particle nor the result of an earlier step
+        assert_eq!(event.clustered_particles(3), None);
+        assert_eq!(event.clustered_particles(4), None);
+
+        // Each step clusters the result of the previous one with itself
+        let step = ClusteringStep {
+            p1: 1,
+            p2: 1,
+            p0: 1,
+            scale: None,
+            alphas: None,
+            attributes: Attributes::new(),
+        };
+        let repeated = Clustering {
+            steps: vec![step; 100],
+        };
+        assert_eq!(repeated.particles(99, 2), Some((vec![0], vec![0])));
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        let round = LheFile::read_lhe(&written).to_full_result().unwrap();
+        assert_eq!(lhe, round);
+    }
+}
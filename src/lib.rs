@@ -39,9 +39,11 @@
 //!
 //! ### Reading a file generated including extra information as strings:
 //!
-//! Specialized data structures for e.g. Madgraph do not exist, but the
-//! additional information stored in the event files written by it can
-//! still be extracted as strings:
+//! Files written by MadGraph5_aMC@NLO can be read with the typed data
+//! structures of the [`madgraph`] module, see below.
+//! For generators without a specialized module, the additional
+//! information stored in their event files can still be extracted as
+//! strings:
 //!
 //! ```rust,ignore
 //! use lhef::ReadLhe;
@@ -71,9 +73,205 @@
 //! let extra = lhe.events[4].extra.pdf.x1;
 //! ```
 //!
+//! ### Reading a large file event by event
+//!
+//! Reading a whole file at once keeps all events in memory.
+//! For large files the `LheReader` can be used instead, which reads the
+//! events one at a time:
+//!
+//! ```rust,ignore
+//! use lhef::reader::LheReader;
+//! use lhef::plain::{Comment, Header, InitExtra, EventExtra};
+//!
+//! let reader: LheReader<_, Comment, Header, InitExtra, EventExtra> =
+//!     LheReader::from_file(&"events.lhe").unwrap();
+//!
+//! for event in reader {
+//!     let event = event.unwrap();
+//!     // weight of the event
+//!     let weight = event.weight;
+//! }
+//! ```
+//!
+//! ### Writing a file event by event
+//!
+//! Events that are generated one at a time can be written using the
+//! `LheWriter`, which writes the start of the file when it is created and
+//! the closing tag when it is finished:
+//!
+//! ```rust,ignore
+//! use lhef::version::LhefVersion;
+//! use lhef::writer::LheWriter;
+//!
+//! let mut writer = LheWriter::create(
+//!     &"events.lhe",
+//!     &LhefVersion::V1,
+//!     &attributes,
+//!     &comment,
+//!     &header,
+//!     &init,
+//! ).unwrap();
+//! for event in events {
+//!     writer.write_event(&event).unwrap();
+//! }
+//! writer.finish().unwrap();
+//! ```
+//!
+//! ### Compressed files
+//!
+//! Files compressed with `gzip`, `xz` or `zstd` can be read and written
+//! directly if the corresponding cargo feature (`gzip`, `xz` or `zstd`) is
+//! enabled.
+//! When reading, the compression is detected automatically; when writing,
+//! it is chosen from the extension of the file name:
+//!
+//! ```rust,ignore
+//! use lhef::{ReadLhe, WriteLhe};
+//! use lhef::plain::LheFile;
+//!
+//! let lhe = LheFile::read_lhe_from_file(&"events.lhe.gz").unwrap();
+//! lhe.write_lhe_to_file(&"events.lhe.xz").unwrap();
+//! ```
+//!
+//! ### Reading single events by number
+//!
+//! An `EventIndex` stores the positions of all events in a file, so that
+//! single events can be read without parsing the events before them.
+//! The index can be saved next to the event file and loaded again later:
+//!
+//! ```rust,ignore
+//! use lhef::index::EventIndex;
+//! use lhef::plain::EventExtra;
+//!
+//! let index = EventIndex::build_from_file(&"events.lhe").unwrap();
+//! index.save(&EventIndex::sidecar_path(&"events.lhe")).unwrap();
+//!
+//! // the 1,234,567th event
+//! let event = index.read_event::<_, EventExtra>(&"events.lhe", 1234566).unwrap();
+//! ```
+//!
+//! ### Parsing the events in parallel
+//!
+//! With the `parallel` cargo feature enabled, the events in a file can be
+//! parsed on multiple threads using [`rayon`].
+//! The result is the same as the one of `read_lhe_from_file`:
+//!
+//! ```rust,ignore
+//! use lhef::parallel;
+//! use lhef::plain::LheFile;
+//!
+//! let lhe: LheFile = parallel::read_lhe_from_file(&"events.lhe").unwrap();
+//! ```
+//!
+//! ### Recovering the events from a truncated file
+//!
+//! If a generator was killed while writing a file, the file is truncated.
+//! The `recover` module reads all complete events from such files and
+//! reports where and why reading stopped.
+//! It can also write a repaired copy of the file:
+//!
+//! ```rust,ignore
+//! use lhef::recover;
+//! use lhef::plain::LheFile;
+//!
+//! let (lhe, report): (LheFile, _) = recover::read_lhe_from_file(&"events.lhe").unwrap();
+//! if !report.is_complete() {
+//!     println!("{}", report);
+//!     recover::repair_file(&"events.lhe", &"events_repaired.lhe").unwrap();
+//! }
+//! ```
+//!
+//! ### Finding errors in malformed files
+//!
+//! If a file can not be parsed, the error contains the line and column of
+//! the error, the section of the file (e.g. particle 3 in event 41) and the
+//! name of the field that could not be read:
+//!
+//! ```rust,ignore
+//! use lhef::{ReadError, ReadLhe};
+//! use lhef::plain::LheFile;
+//!
+//! match LheFile::read_lhe_from_file(&"events.lhe") {
+//!     Err(ReadError::Parse(err)) => println!("{}", err),
+//!     _ => {}
+//! }
+//! ```
+//!
+//! ### Skipping malformed events
+//!
+//! The `lenient` module reads a file while skipping all events that can not
+//! be parsed, and reports for each skipped event where and why parsing
+//! failed:
+//!
+//! ```rust,ignore
+//! use lhef::lenient;
+//! use lhef::plain::LheFile;
+//!
+//! let (lhe, skipped): (LheFile, _) = lenient::read_lhe_from_file(&"events.lhe").unwrap();
+//! println!("read {} events, skipped {}", lhe.events.len(), skipped.len());
+//! ```
+//!
+//! ### Event groups
+//!
+//! NLO generators following version 3 of the lhe standard write real
+//! emission events together with their counter-events in `<eventgroup>`
+//! blocks.
+//! The events in these groups are part of `LheFileGeneric::events` like
+//! any other event, while `LheFileGeneric::groups` records which events
+//! belong to which group, together with the attributes of the group:
+//!
+//! ```rust,ignore
+//! use lhef::ReadLhe;
+//! use lhef::plain::LheFile;
+//!
+//! let lhe = LheFile::read_lhe_from_file(&"events.lhe").unwrap();
+//! for group in &lhe.groups {
+//!     let events = &lhe.events[group.events.clone()];
+//!     println!("group with {} events: {:?}", events.len(), group.attributes);
+//! }
+//! ```
+//!
+//! The `LheReader` and the `LheWriter` can read and write whole groups as
+//! well, using `LheReader::items` and `LheWriter::write_group`.
+//!
+//! ### Versions of the lhe standard
+//!
+//! The version of a file is stored as a `LhefVersion`.
+//! The `version` module can check that a file only contains the tags that
+//! are allowed in its version, and files can be downgraded to an older
+//! version before writing them, removing the tags of newer versions or
+//! turning them into comments:
+//!
+//! ```rust,ignore
+//! use lhef::WriteLhe;
+//! use lhef::lhef3::LheFile;
+//! use lhef::version::{self, DowngradeMode, LhefVersion};
+//!
+//! let mut lhe: LheFile = version::read_lhe_from_file(&"events.lhe").unwrap();
+//! lhe.downgrade(LhefVersion::V1, DowngradeMode::Comment);
+//! lhe.write_lhe(&mut std::io::stdout()).unwrap();
+//! ```
+//!
+//! ### Validating events
+//!
+//! The `validate` methods of events and files check for momentum
+//! conservation, valid mother ids, paired colour tags, known status codes,
+//! energies that are consistent with the masses and known process ids:
+//!
+//! ```rust,ignore
+//! use lhef::ReadLhe;
+//! use lhef::plain::LheFile;
+//! use lhef::validate::Tolerances;
+//!
+//! let lhe = LheFile::read_lhe_from_file(&"events.lhe").unwrap();
+//! for (event, finding) in lhe.validate(&Tolerances::default()) {
+//!     println!("event {}: {}", event, finding);
+//! }
+//! ```
+//!
 //! ## Supported file types
 //!
-//! This library comes with three specialization modules to handle extra
+//! This library comes with six specialization modules to handle extra
 //! information contained in event files:
 //!
 //! ### plain
@@ -92,8 +290,8 @@
 //!
 //! The [`string`] module allows to read `lhe` files and keeping all the
 //! extra information in the files as unparsed strings.
-//! The `comment` and the `header` are kept as strings, without the
-//! start and end tags.
+//! The `comment`, the `header` and all further comments in the file are
+//! kept as strings, without the start and end tags.
 //! All extra information has leading and trailing whitespace removed.
 //! Whitespace (including linebreaks) within the strings is conserved.
 //!
@@ -107,6 +305,67 @@
 //! not contain a header, the header is a dummy object.
 //!
 //!
+//! ### lhef3
+//!
+//! The [`lhef3`] module contains structs for the additional information
+//! defined in version 3 of the `lhe` standard.
+//! The weight declarations in the `<initrwgt>` block of the header are
+//! parsed into typed groups and weights, while the rest of the header is
+//! kept as a string.
+//! The weights in the `<rwgt>` block of each event are parsed into a map
+//! from their ids to their values, which can be checked against the
+//! declarations in the header.
+//! Compact `<weights>` blocks are supported as well and can be converted
+//! to and from `<rwgt>` blocks.
+//! The `<scales>` tag is parsed into the named scales of the event and the
+//! scales of single particles.
+//! The `<pdfinfo>` tag and the `<clustering>` history of an event are
+//! parsed as well, and the steps of the clustering history can be
+//! resolved to the particles of the event.
+//! In the init section, the `<generator>`, `<xsecinfo>`, `<cutsinfo>`,
+//! `<procinfo>` and `<mergeinfo>` tags are parsed into typed structs, and
+//! the cuts defined in `<cutsinfo>` can be evaluated on events.
+//! Any other additional information is kept as a string.
+//!
+//!
+//! ### madgraph
+//!
+//! The [`madgraph`] module contains a specialized header for `lhe` files
+//! generated by `MadGraph5_aMC@NLO`.
+//! The process card in `<MG5ProcCard>`, the run card in `<MGRunCard>` and
+//! the generation information in `<MGGenerationInfo>` are parsed into
+//! typed structs, with access to the model, the multiparticle
+//! definitions, the processes and the parameters of the run.
+//! Parameters of the run card can be changed, unchanged lines are written
+//! back as they are and changed parameters in the format of the run card.
+//! The parameter card in the `<slha>` block is parsed into its `BLOCK` and
+//! `DECAY` tables by the [`slha`] module, with lookups of masses and
+//! widths, and an edited card is written back into the header.
+//! The `<mgrwt>` block of leading order events is parsed into the number
+//! and the scales of the alpha_s factors, the PDF reweighting entries of
+//! each beam and the total factor, for reweighting the events afterwards.
+//! For next-to-leading order files, `LheFileNlo` parses the `#aMCatNLO`
+//! line and the `<mgrwgt>` block with the information to recompute scale
+//! and PDF variations, and writes them exactly as `MadGraph5_aMC@NLO`
+//! does.
+//! All other blocks of the header are kept as strings, in their original
+//! order, and the init section and any other event information are kept
+//! as in the `string` module.
+//!
+//!
+//! ### powheg
+//!
+//! The [`powheg`] module contains specialized event information for `lhe`
+//! files generated by `POWHEG-BOX`.
+//! The `#rwgt` line with the state of the random number generator, the
+//! `#pdf` line with the parton momentum fractions and densities and the
+//! `#new weight` lines added by reweighting runs are parsed into typed
+//! structs, and any other event information is kept as a string.
+//! The header is read as in the `lhef3` module, and the settings of the
+//! declared weights, like the factors of the scales and the PDF set, can
+//! be parsed from their descriptions.
+//!
+//!
 //! ### Adding support for new file types
 //!
 //! To add new file types, you need to add types that implement the
@@ -118,6 +377,8 @@
 //! comments (`<!--` and `-->`) and the header (`<header>` and
 //! `</header>`) respectively, but must leave the tags for the init
 //! section and for events alone.
+//! The comment type is also used for all further comments in the file,
+//! so it should read exactly one comment if the input starts with `<!--`.
 //! With these implementations you can then use `LheFileGeneric` with
 //! your types to read and write `lhe` files.
 //!
@@ -127,13 +388,26 @@
 //! [`MG5_aMC@NLO`]: https://launchpad.net/mg5amcnlo
 //! [`HELAC_NLO`]: http://helac-phegas.web.cern.ch/helac-phegas/
 //! [`nom`]: https://github.com/Geal/nom
+//! [`rayon`]: https://github.com/rayon-rs/rayon
 //! [`plain`]: plain/index.html
 //! [`string`]: string/index.html
 //! [`helac`]: helac/index.html
+//! [`powheg`]: powheg/index.html
+//! [`madgraph`]: madgraph/index.html
+//! [`slha`]: slha/index.html
+//! [`lhef3`]: lhef3/index.html
 
+#[cfg(feature = "gzip")]
+extern crate flate2;
 extern crate lorentz_vector;
 #[macro_use]
 extern crate nom;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "xz")]
+extern crate xz2;
+#[cfg(feature = "zstd")]
+extern crate zstd;
 
 #[cfg(test)]
 #[macro_use]
@@ -148,16 +422,30 @@ extern crate serde_json;
 
 #[macro_use]
 pub mod nom_util;
+pub mod attributes;
+pub mod compression;
+pub mod error;
 pub mod generic;
 pub mod helac;
+pub mod index;
+pub mod lenient;
+pub mod lhef3;
+pub mod madgraph;
 pub mod plain;
+pub mod powheg;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod reader;
+pub mod recover;
+pub mod slha;
 pub mod string;
+pub mod validate;
+pub mod version;
+pub mod writer;
 
 use lorentz_vector::LorentzVector;
 
-use std::error;
 use std::fmt;
-use std::fs;
 use std::io;
 use std::io::Read;
 use std::marker;
@@ -168,6 +456,7 @@ use quickcheck::Arbitrary;
 #[cfg(test)]
 use quickcheck::Gen;
 
+use error::{ErrorLocator, ParseError, FLOAT, INTEGER};
 use nom_util::{parse_f64, parse_i64};
 
 /// A type to use for pdg ids
@@ -199,14 +488,43 @@ where
     /// [`nom documentation`]: http://rust.unhandledexpression.com/nom/
     fn read_lhe(&[u8]) -> nom::IResult<&[u8], Self>;
 
+    /// Read an lhe object from a byte string that contains nothing else
+    ///
+    /// If parsing fails, the error contains the position of the error
+    /// in the input, see the [`error`] module.
+    ///
+    /// [`error`]: error/index.html
+    fn read_lhe_from_bytes(input: &[u8]) -> Result<Self, ReadError> {
+        Self::read_lhe(input)
+            .to_full_result()
+            .map_err(|_| ReadError::Parse(error::locate::<Self>(&mut ErrorLocator::new(input))))
+    }
+
     /// Read an lhe object from a file
     fn read_lhe_from_file<P: AsRef<Path>>(path: &P) -> Result<Self, ReadError> {
-        let mut file = fs::File::open(path)?;
+        let mut file = compression::open(path)?;
         let mut contents = Vec::new();
         file.read_to_end(&mut contents)?;
-        Self::read_lhe(&contents)
-            .to_full_result()
-            .map_err(ReadError::Nom)
+        Self::read_lhe_from_bytes(&contents)
+    }
+
+    /// Walk over an lhe object field by field to find the first error
+    ///
+    /// This is only used to create a precise [`ParseError`] after
+    /// `read_lhe` failed, so it doesn't need to be fast.
+    /// It should walk over the input in the same way as `read_lhe` and
+    /// return an error for the first field that can not be read.
+    /// The default implementation only checks whether `read_lhe`
+    /// succeeds and reports an error at the start of the object
+    /// otherwise.
+    ///
+    /// [`ParseError`]: error/struct.ParseError.html
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        if locator.skip_parsed(Self::read_lhe) {
+            Ok(())
+        } else {
+            Err(locator.error(None, "valid input"))
+        }
     }
 }
 
@@ -222,8 +540,9 @@ pub trait WriteLhe {
 
     /// Write the object to a file
     fn write_lhe_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let mut file = fs::File::create(path)?;
-        self.write_lhe(&mut file)
+        let mut file = compression::create(&path)?;
+        self.write_lhe(&mut file)?;
+        file.finish()
     }
 }
 
@@ -234,6 +553,8 @@ pub enum ReadError {
     Io(io::Error),
     /// A parse error occured
     Nom(nom::IError),
+    /// A parse error occured, with its position in the file
+    Parse(ParseError),
 }
 
 impl From<io::Error> for ReadError {
@@ -253,21 +574,27 @@ impl fmt::Display for ReadError {
                 "Failed to read the lhe file with a parse error: {:?}",
                 err
             ),
+            ReadError::Parse(ref err) => {
+                write!(f, "Failed to read the lhe file with a parse error at {}", err)
+            }
         }
     }
 }
 
-impl error::Error for ReadError {
+impl std::error::Error for ReadError {
     fn description(&self) -> &str {
         match *self {
             ReadError::Io(..) => &"Failed to read the lhe file with an IO error",
-            ReadError::Nom(..) => &"Failed to read the lhe file with a parse error",
+            ReadError::Nom(..) | ReadError::Parse(..) => {
+                &"Failed to read the lhe file with a parse error"
+            }
         }
     }
-    fn cause(&self) -> Option<&error::Error> {
+    fn cause(&self) -> Option<&std::error::Error> {
         match *self {
             ReadError::Io(ref err) => Some(err),
             ReadError::Nom(_) => None,
+            ReadError::Parse(ref err) => Some(err),
         }
     }
 }
@@ -333,6 +660,14 @@ impl ReadLhe for ProcInfo {
             })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.field("XSECUP", FLOAT, parse_f64)?;
+        locator.field("XERRUP", FLOAT, parse_f64)?;
+        locator.field("XMAXUP", FLOAT, parse_f64)?;
+        locator.field("LPRUP", INTEGER, parse_i64)?;
+        Ok(())
+    }
 }
 
 impl WriteLhe for ProcInfo {
@@ -448,6 +783,25 @@ impl ReadLhe for Particle {
                 })
         )
     }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        for field in &[
+            "IDUP",
+            "ISTUP",
+            "MOTHUP(1)",
+            "MOTHUP(2)",
+            "ICOLUP(1)",
+            "ICOLUP(2)",
+        ] {
+            locator.field(field, INTEGER, parse_i64)?;
+        }
+        for field in &[
+            "PUP(1)", "PUP(2)", "PUP(3)", "PUP(4)", "PUP(5)", "VTIMUP", "SPINUP",
+        ] {
+            locator.field(field, FLOAT, parse_f64)?;
+        }
+        Ok(())
+    }
 }
 
 impl WriteLhe for Particle {
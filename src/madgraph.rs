@@ -0,0 +1,1811 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A specialized module for lhe files from MadGraph5_aMC@NLO
+//!
+//! MadGraph5_aMC@NLO stores the cards that were used to generate the
+//! events in the header of its lhe files.
+//! This module contains a `Header` type that parses the process card,
+//! the run card, the parameter card and the generation information into
+//! typed structures, while keeping all other blocks of the header as
+//! they are.
+//! The reweighting information in the `<mgrwt>` block of leading order
+//! events is parsed by the `EventExtra` type.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::ReadLhe;
+//! use lhef::madgraph::LheFile;
+//!
+//! let lhe = LheFile::read_lhe_from_file(&"events.lhe").unwrap();
+//!
+//! // the energy of the first beam from the run card
+//! let ebeam1 = lhe.header.run_card().and_then(|card| card.get_f64("ebeam1"));
+//! ```
+
+use {PdgId, ReadLhe, WriteLhe};
+use attributes::{find_elements, split_elements, write_element, Attributes, Element};
+use generic::LheFileGeneric;
+use slha::{parse_f64, Slha};
+use string;
+use version::{downgrade_text, Downgrade, DowngradeMode, LhefVersion};
+
+use nom;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::str;
+
+/// A type to read and write lhe files generated by MadGraph5_aMC@NLO
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::madgraph::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <header>
+/// <MGVersion>
+/// 2.5.1
+/// </MGVersion>
+/// <MG5ProcCard>
+/// import model sm
+/// define l+ = e+ mu+
+/// generate e+ e- > l+ l-
+/// </MG5ProcCard>
+/// <MGRunCard>
+///   10 = nevents ! Number of unweighted events requested
+///   6500.0 = ebeam1 ! beam 1 total energy in GeV
+///   True = use_syst ! Enable systematics studies
+/// </MGRunCard>
+/// <MGGenerationInfo>
+/// ##  Number of Events        :       10
+/// ##  Integrated weight (pb)  :       0.104223
+/// </MGGenerationInfo>
+/// </header>
+/// <init>
+/// -11 11 5.000000e+02 5.000000e+02 0 0 247000 247000 -4 1
+/// 1.042230e-01 4.052415e-04 1.042230e-01 1
+/// </init>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let proc_card = lhe.header.proc_card().unwrap();
+/// assert_eq!(proc_card.model(), Some("sm"));
+/// assert_eq!(proc_card.processes(), vec!["e+ e- > l+ l-"]);
+///
+/// let run_card = lhe.header.run_card().unwrap();
+/// assert_eq!(run_card.get_f64("ebeam1"), Some(6500.));
+/// assert_eq!(run_card.get_bool("use_syst"), Some(true));
+///
+/// let info = lhe.header.generation_info().unwrap();
+/// assert_eq!(info.n_events(), Some(10));
+/// ```
+pub type LheFile = LheFileGeneric<string::Comment, Header, string::InitExtra, EventExtra>;
+
+/// A type to read and write next-to-leading order lhe files generated by
+/// MadGraph5_aMC@NLO
+///
+/// The header is the same as for leading order files, the additional
+/// information of the events is parsed into [`EventExtraNlo`].
+///
+/// [`EventExtraNlo`]: struct.EventExtraNlo.html
+pub type LheFileNlo = LheFileGeneric<string::Comment, Header, string::InitExtra, EventExtraNlo>;
+
+/// The header of an lhe file from MadGraph5_aMC@NLO
+///
+/// The header is split into blocks, in the order in which they appear
+/// in the file.
+/// The `<MG5ProcCard>`, `<MGRunCard>`, `<slha>` and `<MGGenerationInfo>`
+/// blocks are parsed into typed structures, everything else is kept as text,
+/// with leading and trailing whitespace removed.
+/// If there are no blocks, no header is written.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Header {
+    /// The blocks of the header
+    pub blocks: Vec<HeaderBlock>,
+}
+
+impl Header {
+    fn from_text(text: &str) -> Option<Header> {
+        let names = ["MG5ProcCard", "MGRunCard", "slha", "MGGenerationInfo"];
+        let mut blocks = Vec::new();
+        let mut position = 0;
+        for (range, element) in find_elements(text, &names)? {
+            push_other(&mut blocks, &text[position..range.start]);
+            let block = if !element.attributes.is_empty() {
+                HeaderBlock::Other(text[range.clone()].to_string())
+            } else if element.name == "MG5ProcCard" {
+                HeaderBlock::ProcCard(ProcCard::from_text(element.content))
+            } else if element.name == "MGRunCard" {
+                HeaderBlock::RunCard(RunCard::from_text(element.content))
+            } else if element.name == "slha" {
+                HeaderBlock::Slha(Slha::parse(trim_card(element.content)))
+            } else {
+                HeaderBlock::GenerationInfo(GenerationInfo::from_text(element.content))
+            };
+            blocks.push(block);
+            position = range.end;
+        }
+        push_other(&mut blocks, &text[position..]);
+        Some(Header { blocks })
+    }
+
+    /// The process card, from the `<MG5ProcCard>` block
+    pub fn proc_card(&self) -> Option<&ProcCard> {
+        self.blocks.iter().filter_map(HeaderBlock::proc_card).next()
+    }
+
+    /// The run card, from the `<MGRunCard>` block
+    pub fn run_card(&self) -> Option<&RunCard> {
+        self.blocks.iter().filter_map(HeaderBlock::run_card).next()
+    }
+
+    /// The parameter card, from the `<slha>` block
+    pub fn slha(&self) -> Option<&Slha> {
+        self.blocks.iter().filter_map(HeaderBlock::slha).next()
+    }
+
+    /// The parameter card, from the `<slha>` block, for editing
+    ///
+    /// The edited card is written when the header is written.
+    pub fn slha_mut(&mut self) -> Option<&mut Slha> {
+        self.blocks
+            .iter_mut()
+            .filter_map(|block| match *block {
+                HeaderBlock::Slha(ref mut slha) => Some(slha),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// The generation information, from the `<MGGenerationInfo>` block
+    pub fn generation_info(&self) -> Option<&GenerationInfo> {
+        self.blocks
+            .iter()
+            .filter_map(HeaderBlock::generation_info)
+            .next()
+    }
+}
+
+fn push_other(blocks: &mut Vec<HeaderBlock>, text: &str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        blocks.push(HeaderBlock::Other(text.to_string()));
+    }
+}
+
+impl ReadLhe for Header {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], Header> {
+        map_opt!(
+            input,
+            string::Header::read_lhe,
+            |string::Header { header }| match header {
+                Some(header) => Header::from_text(&header),
+                None => Some(Header::default()),
+            }
+        )
+    }
+}
+
+impl WriteLhe for Header {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.blocks.is_empty() {
+            return Ok(());
+        }
+        writeln!(writer, "<header>")?;
+        for block in &self.blocks {
+            block.write_lhe(writer)?;
+        }
+        writeln!(writer, "</header>")
+    }
+}
+
+impl Downgrade for Header {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        for block in &mut self.blocks {
+            if let HeaderBlock::Other(ref mut text) = *block {
+                *text = downgrade_text(text, version, mode);
+            }
+        }
+    }
+}
+
+/// A block in the header of an lhe file from MadGraph5_aMC@NLO
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeaderBlock {
+    /// The process card in the `<MG5ProcCard>` block
+    ProcCard(ProcCard),
+    /// The run card in the `<MGRunCard>` block
+    RunCard(RunCard),
+    /// The parameter card in the `<slha>` block
+    Slha(Slha),
+    /// The generation information in the `<MGGenerationInfo>` block
+    GenerationInfo(GenerationInfo),
+    /// Any other content of the header, e.g. the version or comments
+    Other(String),
+}
+
+impl HeaderBlock {
+    fn proc_card(&self) -> Option<&ProcCard> {
+        match *self {
+            HeaderBlock::ProcCard(ref card) => Some(card),
+            _ => None,
+        }
+    }
+
+    fn run_card(&self) -> Option<&RunCard> {
+        match *self {
+            HeaderBlock::RunCard(ref card) => Some(card),
+            _ => None,
+        }
+    }
+
+    fn slha(&self) -> Option<&Slha> {
+        match *self {
+            HeaderBlock::Slha(ref slha) => Some(slha),
+            _ => None,
+        }
+    }
+
+    fn generation_info(&self) -> Option<&GenerationInfo> {
+        match *self {
+            HeaderBlock::GenerationInfo(ref info) => Some(info),
+            _ => None,
+        }
+    }
+}
+
+impl WriteLhe for HeaderBlock {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match *self {
+            HeaderBlock::ProcCard(ref card) => write_lines(writer, "MG5ProcCard", &card.commands),
+            HeaderBlock::RunCard(ref card) => write_lines(writer, "MGRunCard", &card.lines),
+            HeaderBlock::Slha(ref slha) => write!(writer, "<slha>\n{}</slha>\n", slha),
+            HeaderBlock::GenerationInfo(ref info) => {
+                write_lines(writer, "MGGenerationInfo", &info.lines)
+            }
+            HeaderBlock::Other(ref text) => writeln!(writer, "{}", text),
+        }
+    }
+}
+
+/// Write an element with the given name, with one line per item
+fn write_lines<W, T>(writer: &mut W, name: &str, lines: &[T]) -> io::Result<()>
+where
+    W: io::Write,
+    T: fmt::Display,
+{
+    writeln!(writer, "<{}>", name)?;
+    for line in lines {
+        writeln!(writer, "{}", line)?;
+    }
+    writeln!(writer, "</{}>", name)
+}
+
+/// Remove leading and trailing empty lines from the content of a card
+fn trim_card(text: &str) -> &str {
+    let text = text.trim_end();
+    let start = text.len() - text.trim_start().len();
+    let start = text[..start].rfind('\n').map_or(0, |n| n + 1);
+    &text[start..]
+}
+
+/// Split the lines of the content of a card, without leading and
+/// trailing empty lines and trailing whitespace
+fn card_lines(text: &str) -> impl Iterator<Item = &str> {
+    trim_card(text).lines().map(|line| line.trim_end())
+}
+
+/// Split the first word off of `text`
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(char::is_whitespace) {
+        Some(n) => (&text[..n], text[n..].trim()),
+        None => (text, ""),
+    }
+}
+
+/// The process card that was used to generate the events
+///
+/// The process card is a list of commands for MadGraph5_aMC@NLO.
+/// The commands that define the process are parsed, all other
+/// commands and comments are kept as they are.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcCard {
+    /// The commands in the process card, one per line
+    pub commands: Vec<ProcCommand>,
+}
+
+impl ProcCard {
+    fn from_text(text: &str) -> ProcCard {
+        ProcCard {
+            commands: card_lines(text).map(ProcCommand::from_line).collect(),
+        }
+    }
+
+    /// The options from all `set` commands
+    ///
+    /// If an option is set multiple times, the last value is used.
+    pub fn options(&self) -> HashMap<&str, &str> {
+        self.commands
+            .iter()
+            .filter_map(|command| match *command {
+                ProcCommand::Set {
+                    ref name,
+                    ref value,
+                } => Some((name.as_str(), value.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The value of the option `name`, if it is set
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options().get(name).cloned()
+    }
+
+    /// The multiparticle labels from all `define` commands, with the
+    /// particles they stand for
+    pub fn definitions(&self) -> HashMap<&str, &[String]> {
+        self.commands
+            .iter()
+            .filter_map(|command| match *command {
+                ProcCommand::Define {
+                    ref label,
+                    ref particles,
+                } => Some((label.as_str(), particles.as_slice())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The name of the imported model
+    pub fn model(&self) -> Option<&str> {
+        self.commands
+            .iter()
+            .rev()
+            .filter_map(|command| match *command {
+                ProcCommand::ImportModel(ref model) => Some(model.as_str()),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// The processes from the `generate` and `add process` commands
+    pub fn processes(&self) -> Vec<&str> {
+        self.commands
+            .iter()
+            .filter_map(|command| match *command {
+                ProcCommand::Generate(ref process) | ProcCommand::AddProcess(ref process) => {
+                    Some(process.as_str())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A single line in the process card
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcCommand {
+    /// `set <name> <value>`
+    Set { name: String, value: String },
+    /// `import model <model>`
+    ImportModel(String),
+    /// `define <label> = <particles>`
+    Define {
+        label: String,
+        particles: Vec<String>,
+    },
+    /// `generate <process>`
+    Generate(String),
+    /// `add process <process>`
+    AddProcess(String),
+    /// `output <arguments>`
+    Output(String),
+    /// Any other command, a comment or an empty line
+    Other(String),
+}
+
+impl ProcCommand {
+    fn from_line(line: &str) -> ProcCommand {
+        let (command, arguments) = split_word(line);
+        let (first, rest) = split_word(arguments);
+        match command {
+            "set" if !first.is_empty() => ProcCommand::Set {
+                name: first.to_string(),
+                value: rest.to_string(),
+            },
+            "import" if first == "model" && !rest.is_empty() => {
+                ProcCommand::ImportModel(rest.to_string())
+            }
+            "define" => match arguments.find('=') {
+                Some(n)
+                    if !split_word(&arguments[..n]).0.is_empty()
+                        && split_word(&arguments[..n]).1.is_empty() =>
+                {
+                    ProcCommand::Define {
+                        label: arguments[..n].trim().to_string(),
+                        particles: arguments[n + 1..]
+                            .split_whitespace()
+                            .map(String::from)
+                            .collect(),
+                    }
+                }
+                _ => ProcCommand::Other(line.to_string()),
+            },
+            "generate" => ProcCommand::Generate(arguments.to_string()),
+            "add" if first == "process" => ProcCommand::AddProcess(rest.to_string()),
+            "output" => ProcCommand::Output(arguments.to_string()),
+            _ => ProcCommand::Other(line.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ProcCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = match *self {
+            ProcCommand::Set {
+                ref name,
+                ref value,
+            } => format!("set {} {}", name, value),
+            ProcCommand::ImportModel(ref model) => format!("import model {}", model),
+            ProcCommand::Define {
+                ref label,
+                ref particles,
+            } => format!("define {} = {}", label, particles.join(" ")),
+            ProcCommand::Generate(ref process) => format!("generate {}", process),
+            ProcCommand::AddProcess(ref process) => format!("add process {}", process),
+            ProcCommand::Output(ref arguments) => format!("output {}", arguments),
+            ProcCommand::Other(ref line) => return write!(f, "{}", line),
+        };
+        write!(f, "{}", line.trim_end())
+    }
+}
+
+/// The run card that was used to generate the events
+///
+/// The parameters of the run card are given in lines of the form
+/// `value = name ! comment`.
+/// All other lines, e.g. comments, and the lines of parameters that are
+/// not changed with `set` are kept as they are.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::madgraph::Header;
+///
+/// let bytes = b"\
+/// <header>
+/// <MGRunCard>
+/// ## Number of events
+///   10 = nevents ! Number of unweighted events requested
+///   6500.0d0 = ebeam1 ! beam 1 total energy in GeV
+/// </MGRunCard>
+/// </header>";
+///
+/// let header = Header::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let run_card = header.run_card().unwrap();
+/// assert_eq!(run_card.get("nevents"), Some("10"));
+/// assert_eq!(run_card.get_f64("ebeam1"), Some(6500.));
+/// assert_eq!(run_card.parameters().len(), 2);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunCard {
+    /// The lines of the run card
+    pub lines: Vec<RunCardLine>,
+}
+
+impl RunCard {
+    fn from_text(text: &str) -> RunCard {
+        RunCard {
+            lines: card_lines(text).map(RunCardLine::from_line).collect(),
+        }
+    }
+
+    /// All parameters, by their name
+    pub fn parameters(&self) -> HashMap<&str, &str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match *line {
+                RunCardLine::Parameter(ref parameter) => {
+                    Some((parameter.name.as_str(), parameter.value.as_str()))
+                }
+                RunCardLine::Other(_) => None,
+            })
+            .collect()
+    }
+
+    /// The value of the parameter `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.parameter(name)
+            .map(|parameter| parameter.value.as_str())
+    }
+
+    /// The value of the parameter `name` as a floating point number
+    ///
+    /// Fortran style exponents, like in `1d0`, are supported.
+    pub fn get_f64(&self, name: &str) -> Option<f64> {
+        parse_f64(self.get(name)?)
+    }
+
+    /// The value of the parameter `name` as an integer
+    pub fn get_i64(&self, name: &str) -> Option<i64> {
+        self.get(name)?.parse().ok()
+    }
+
+    /// The value of the parameter `name` as a boolean
+    ///
+    /// `True`, `T` and `.true.` and the same values for `False` are
+    /// supported, in any case.
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)?.trim_matches('.').to_lowercase().as_str() {
+            "true" | "t" => Some(true),
+            "false" | "f" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Set the parameter `name` to `value`
+    ///
+    /// If the parameter is not in the run card yet, it is added at the
+    /// end.
+    pub fn set(&mut self, name: &str, value: &str) {
+        let existing = self.lines.iter_mut().rev().find_map(|line| match *line {
+            RunCardLine::Parameter(ref mut parameter) if parameter.name == name => Some(parameter),
+            _ => None,
+        });
+        match existing {
+            Some(parameter) => {
+                if parameter.value != value {
+                    parameter.value = value.to_string();
+                    parameter.line = None;
+                }
+            }
+            None => self.lines.push(RunCardLine::Parameter(RunParameter {
+                name: name.to_string(),
+                value: value.to_string(),
+                comment: None,
+                line: None,
+            })),
+        }
+    }
+
+    /// The parameter `name`
+    ///
+    /// If the parameter is given multiple times, the last one is used.
+    pub fn parameter(&self, name: &str) -> Option<&RunParameter> {
+        self.lines
+            .iter()
+            .rev()
+            .filter_map(|line| match *line {
+                RunCardLine::Parameter(ref parameter) if parameter.name == name => Some(parameter),
+                _ => None,
+            })
+            .next()
+    }
+}
+
+/// A single line in the run card
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunCardLine {
+    /// A line with a parameter
+    Parameter(RunParameter),
+    /// A comment, an empty line or anything else
+    Other(String),
+}
+
+impl RunCardLine {
+    fn from_line(line: &str) -> RunCardLine {
+        let (setting, comment) = match line.find(['!', '#']) {
+            Some(n) => (&line[..n], Some(line[n + 1..].trim())),
+            None => (line, None),
+        };
+        if let Some(n) = setting.find('=') {
+            let value = setting[..n].trim();
+            let name = setting[n + 1..].trim();
+            if !value.is_empty() && !name.is_empty() && !name.contains(char::is_whitespace) {
+                return RunCardLine::Parameter(RunParameter {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    comment: comment
+                        .filter(|comment| !comment.is_empty())
+                        .map(String::from),
+                    line: Some(line.to_string()),
+                });
+            }
+        }
+        RunCardLine::Other(line.to_string())
+    }
+}
+
+impl fmt::Display for RunCardLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RunCardLine::Parameter(RunParameter {
+                line: Some(ref line),
+                ..
+            }) => write!(f, "{}", line),
+            RunCardLine::Parameter(ref parameter) => {
+                write!(f, " {} = {}", parameter.value, parameter.name)?;
+                match parameter.comment {
+                    Some(ref comment) => write!(f, " ! {}", comment),
+                    None => Ok(()),
+                }
+            }
+            RunCardLine::Other(ref line) => write!(f, "{}", line),
+        }
+    }
+}
+
+/// A parameter in the run card
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunParameter {
+    /// The name of the parameter
+    pub name: String,
+    /// The value of the parameter, as it is given in the run card
+    pub value: String,
+    /// The comment after the parameter, without the `!`
+    pub comment: Option<String>,
+    /// The line of the run card that the parameter was read from
+    ///
+    /// If this is set, the parameter is written as this line, so it
+    /// has to be reset to `None` when changing the other fields.
+    /// `RunCard::set` does this when it changes the value.
+    pub line: Option<String>,
+}
+
+/// Information about the generation of the events
+///
+/// The `<MGGenerationInfo>` block contains lines of the form
+/// `#  Number of Events :  10`, which are parsed into the name and the
+/// value.
+/// All other lines are kept as they are.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GenerationInfo {
+    /// The lines of the generation information
+    pub lines: Vec<GenerationInfoLine>,
+}
+
+impl GenerationInfo {
+    fn from_text(text: &str) -> GenerationInfo {
+        GenerationInfo {
+            lines: card_lines(text)
+                .map(GenerationInfoLine::from_line)
+                .collect(),
+        }
+    }
+
+    /// All entries, by their name
+    pub fn entries(&self) -> HashMap<&str, &str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match *line {
+                GenerationInfoLine::Entry {
+                    ref name,
+                    ref value,
+                } => Some((name.as_str(), value.as_str())),
+                GenerationInfoLine::Other(_) => None,
+            })
+            .collect()
+    }
+
+    /// The value of the entry `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries().get(name).cloned()
+    }
+
+    /// The number of events, from `Number of Events`
+    pub fn n_events(&self) -> Option<u64> {
+        self.get("Number of Events")?.parse().ok()
+    }
+
+    /// The integrated weight in pb, from `Integrated weight (pb)`
+    pub fn integrated_weight(&self) -> Option<f64> {
+        self.get("Integrated weight (pb)")?.parse().ok()
+    }
+}
+
+/// A single line in the generation information
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenerationInfoLine {
+    /// A line of the form `#  name : value`
+    Entry { name: String, value: String },
+    /// Any other line
+    Other(String),
+}
+
+impl GenerationInfoLine {
+    fn from_line(line: &str) -> GenerationInfoLine {
+        let entry = line.trim_start().strip_prefix('#').and_then(|entry| {
+            let n = entry.find(':')?;
+            let (name, value) = (entry[..n].trim(), entry[n + 1..].trim());
+            if name.is_empty() || value.is_empty() {
+                return None;
+            }
+            Some(GenerationInfoLine::Entry {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+        });
+        entry.unwrap_or_else(|| GenerationInfoLine::Other(line.to_string()))
+    }
+}
+
+impl fmt::Display for GenerationInfoLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GenerationInfoLine::Entry {
+                ref name,
+                ref value,
+            } => write!(f, "#  {} : {}", name, value),
+            GenerationInfoLine::Other(ref line) => write!(f, "{}", line),
+        }
+    }
+}
+
+/// The additional information of an event from MadGraph5_aMC@NLO at
+/// leading order
+///
+/// The reweighting information in the `<mgrwt>` block is parsed into
+/// an [`MgRwt`], everything else is kept as a string.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::madgraph::EventExtra;
+///
+/// let bytes = b"\
+/// <mgrwt>
+/// <rscale>  2 0.91188000E+02</rscale>
+/// <asrwt>  1 0.25000000E+02</asrwt>
+/// <pdfrwt beam=\"1\">  1       21 0.17000000E-01 0.91188000E+02</pdfrwt>
+/// <pdfrwt beam=\"2\">  1       -2 0.23000000E+00 0.91188000E+02</pdfrwt>
+/// <totfact> 0.10000000E+01</totfact>
+/// </mgrwt>
+/// </event>";
+///
+/// let extra = EventExtra::read_lhe(bytes).to_full_result().unwrap();
+/// let mgrwt = extra.mgrwt.unwrap();
+/// assert_eq!(mgrwt.n_alpha_s, 2);
+/// assert_eq!(mgrwt.alpha_s_scales, vec![25.]);
+/// assert_eq!(mgrwt.pdfrwt[1][0].flavour, -2);
+/// assert_eq!(mgrwt.pdfrwt[1][0].x, 0.23);
+/// ```
+///
+/// [`MgRwt`]: struct.MgRwt.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventExtra {
+    /// The reweighting information in the `<mgrwt>` block
+    pub mgrwt: Option<MgRwt>,
+    /// Any other additional event information
+    pub extra: String,
+}
+
+impl ReadLhe for EventExtra {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], EventExtra> {
+        map_opt!(input, take_until!("</event>"), |x| str::from_utf8(x)
+            .ok()
+            .and_then(split_event_extra))
+    }
+}
+
+fn split_event_extra(content: &str) -> Option<EventExtra> {
+    let (elements, extra) = split_elements(content, &["mgrwt"])?;
+    let mut event_extra = EventExtra {
+        extra,
+        ..Default::default()
+    };
+    for element in &elements {
+        if event_extra.mgrwt.is_some() {
+            return None;
+        }
+        event_extra.mgrwt = Some(MgRwt::from_element(element)?);
+    }
+    Some(event_extra)
+}
+
+impl WriteLhe for EventExtra {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if !self.extra.is_empty() {
+            writeln!(writer, "{}", self.extra)?;
+        }
+        if let Some(ref mgrwt) = self.mgrwt {
+            mgrwt.write_lhe(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Downgrade for EventExtra {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        self.extra = downgrade_text(&self.extra, version, mode);
+    }
+}
+
+/// The information to reweight a leading order event, given in an
+/// `<mgrwt>` block
+///
+/// MadGraph5_aMC@NLO writes this block when `use_syst` is set in the
+/// run card, so that the scales and the PDFs of the event can be
+/// varied afterwards.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MgRwt {
+    /// The number of factors of alpha_s that are evaluated at the
+    /// renormalization scale, from `<rscale>`
+    pub n_alpha_s: u32,
+    /// The renormalization scale, from `<rscale>`
+    pub renormalization_scale: f64,
+    /// The scales of the additional factors of alpha_s, e.g. for the
+    /// emissions in merged samples, from `<asrwt>`
+    pub alpha_s_scales: Vec<f64>,
+    /// The PDF reweighting entries for the first and second beam, from
+    /// the `<pdfrwt>` tags
+    pub pdfrwt: [Vec<PdfRwt>; 2],
+    /// The total factor of the event weight, from `<totfact>`
+    pub totfact: f64,
+}
+
+impl MgRwt {
+    fn from_element(element: &Element) -> Option<MgRwt> {
+        let names = ["rscale", "asrwt", "pdfrwt", "totfact"];
+        let (elements, rest) = split_elements(element.content, &names)?;
+        if !rest.is_empty() {
+            return None;
+        }
+        let mut rscale = None;
+        let mut asrwt = None;
+        let mut pdfrwt = [None, None];
+        let mut totfact = None;
+        for element in &elements {
+            let mut fields = element.content.split_whitespace();
+            match element.name {
+                "rscale" if rscale.is_none() => {
+                    let n_alpha_s = fields.next()?.parse().ok()?;
+                    rscale = Some((n_alpha_s, parse_f64(fields.next()?)?));
+                }
+                "asrwt" if asrwt.is_none() => {
+                    let n: usize = fields.next()?.parse().ok()?;
+                    let scales: Vec<_> = fields.by_ref().map(parse_f64).collect::<Option<_>>()?;
+                    if scales.len() != n {
+                        return None;
+                    }
+                    asrwt = Some(scales);
+                }
+                "pdfrwt" => {
+                    let beam: usize = element.attributes.get("beam")?.parse().ok()?;
+                    let entries = pdfrwt.get_mut(beam.checked_sub(1)?)?;
+                    if entries.is_some() {
+                        return None;
+                    }
+                    *entries = Some(PdfRwt::read_entries(&mut fields)?);
+                }
+                "totfact" if totfact.is_none() => {
+                    totfact = Some(parse_f64(fields.next()?)?);
+                }
+                _ => return None,
+            }
+            if fields.next().is_some() {
+                return None;
+            }
+        }
+        let [pdfrwt1, pdfrwt2] = pdfrwt;
+        let (n_alpha_s, renormalization_scale) = rscale?;
+        Some(MgRwt {
+            n_alpha_s,
+            renormalization_scale,
+            alpha_s_scales: asrwt?,
+            pdfrwt: [pdfrwt1?, pdfrwt2?],
+            totfact: totfact?,
+        })
+    }
+}
+
+impl WriteLhe for MgRwt {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "<mgrwt>")?;
+        writeln!(
+            writer,
+            "<rscale> {} {:e}</rscale>",
+            self.n_alpha_s, self.renormalization_scale
+        )?;
+        write!(writer, "<asrwt> {}", self.alpha_s_scales.len())?;
+        for scale in &self.alpha_s_scales {
+            write!(writer, " {:e}", scale)?;
+        }
+        writeln!(writer, "</asrwt>")?;
+        for (beam, entries) in self.pdfrwt.iter().enumerate() {
+            let mut attributes = Attributes::new();
+            attributes.insert("beam", (beam + 1).to_string());
+            write_element(
+                writer,
+                "pdfrwt",
+                &attributes,
+                &PdfRwt::write_entries(entries),
+            )?;
+        }
+        writeln!(writer, "<totfact> {:e}</totfact>", self.totfact)?;
+        writeln!(writer, "</mgrwt>")
+    }
+}
+
+/// A PDF reweighting entry of one beam, given in a `<pdfrwt>` tag
+///
+/// Each entry is a parton density that was evaluated for the event,
+/// e.g. for the incoming parton and for the partons at the clustering
+/// steps in merged samples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdfRwt {
+    /// The PDG id of the parton
+    pub flavour: PdgId,
+    /// The momentum fraction of the parton
+    pub x: f64,
+    /// The factorization scale
+    pub scale: f64,
+}
+
+impl PdfRwt {
+    /// Read the entries of a `<pdfrwt>` tag
+    ///
+    /// The tag contains the number of entries, followed by all
+    /// flavours, all momentum fractions and all scales.
+    fn read_entries<'a, I: Iterator<Item = &'a str>>(fields: &mut I) -> Option<Vec<PdfRwt>> {
+        let n: usize = fields.next()?.parse().ok()?;
+        let mut flavours = Vec::with_capacity(n);
+        for _ in 0..n {
+            flavours.push(fields.next()?.parse().ok()?);
+        }
+        let mut x = Vec::with_capacity(n);
+        for _ in 0..n {
+            x.push(parse_f64(fields.next()?)?);
+        }
+        let mut entries = Vec::with_capacity(n);
+        for (flavour, x) in flavours.into_iter().zip(x) {
+            entries.push(PdfRwt {
+                flavour,
+                x,
+                scale: parse_f64(fields.next()?)?,
+            });
+        }
+        Some(entries)
+    }
+
+    fn write_entries(entries: &[PdfRwt]) -> String {
+        let mut content = format!(" {}", entries.len());
+        for entry in entries {
+            content += &format!(" {}", entry.flavour);
+        }
+        for entry in entries {
+            content += &format!(" {:e}", entry.x);
+        }
+        for entry in entries {
+            content += &format!(" {:e}", entry.scale);
+        }
+        content
+    }
+}
+
+/// The additional information of an event from MadGraph5_aMC@NLO at
+/// next-to-leading order
+///
+/// The `#aMCatNLO` line is parsed into an [`AmcatnloInfo`] and the
+/// `<mgrwgt>` block into an [`MgRwgt`].
+/// Everything else, e.g. a `<rwgt>` block, is kept as a string that
+/// keeps the indentation of its first line.
+/// When written, the typed information uses the same formats as
+/// MadGraph5_aMC@NLO, so that the additional information of an event is
+/// written exactly as it was read.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::madgraph::EventExtraNlo;
+///
+/// let bytes = b"\
+/// ##aMCatNLO 1  0  0  2  1 0.60627164E+02 0.00000000E+00 -9  4  0 0.00000000E+00 0.00000000E+00 0.00000000E+00 0.00000000E+00 0.00000000E+00
+///   <mgrwgt>
+///  0.6942526300D+08    1    1    0
+///  0.500000000000000D+01 0.000000000000000D+00 0.000000000000000D+00 0.500000000000000D+01
+///  0.500000000000000D+01 0.000000000000000D+00 0.000000000000000D+00 -.500000000000000D+01
+///  0.500000000000000D+01 0.300000000000000D+01 0.000000000000000D+00 0.400000000000000D+01
+///  0.500000000000000D+01 -.300000000000000D+01 0.000000000000000D+00 -.400000000000000D+01
+/// 0.104486511661D+00 0.000000000000D+00 0.000000000000D+00 0.347355539454D+01 0.000000000000D+00  4 21 21 5 -5 2 0.15394400D-02 0.93223773D-03 0.39673563D+02 0.39673563D+02 0.39673563D+02 0.15946303D+01  1  1  1  1  0  0        0 0.620926011671D+08
+///   </mgrwgt>
+/// </event>";
+///
+/// let extra = EventExtraNlo::read_lhe(bytes).to_full_result().unwrap();
+/// let amcatnlo = extra.amcatnlo.unwrap();
+/// assert_eq!(amcatnlo.isorh, 1);
+/// assert_eq!(amcatnlo.scale1, 60.627164);
+/// let mgrwgt = extra.mgrwgt.unwrap();
+/// assert_eq!(mgrwgt.momenta[0][3], [5., -3., 0., -4.]);
+/// assert_eq!(mgrwgt.contributions[0].pdg, vec![21, 21, 5, -5]);
+/// assert_eq!(mgrwgt.contributions[0].g_strong, 1.5946303);
+/// ```
+///
+/// [`AmcatnloInfo`]: struct.AmcatnloInfo.html
+/// [`MgRwgt`]: struct.MgRwgt.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventExtraNlo {
+    /// The information in the `#aMCatNLO` line
+    pub amcatnlo: Option<AmcatnloInfo>,
+    /// The reweighting information in the `<mgrwgt>` block
+    pub mgrwgt: Option<MgRwgt>,
+    /// Any other additional event information
+    pub extra: String,
+}
+
+impl ReadLhe for EventExtraNlo {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], EventExtraNlo> {
+        map_opt!(input, take_until!("</event>"), |x| str::from_utf8(x)
+            .ok()
+            .and_then(split_event_extra_nlo))
+    }
+}
+
+fn split_event_extra_nlo(content: &str) -> Option<EventExtraNlo> {
+    let mut event_extra = EventExtraNlo::default();
+    let mut rest = content.to_string();
+    if let Some((line, without)) = take_line(&rest, "#aMCatNLO") {
+        event_extra.amcatnlo = Some(AmcatnloInfo::from_line(line)?);
+        rest = without;
+    }
+    let elements = find_elements(&rest, &["mgrwgt"])?;
+    if elements.len() > 1 {
+        return None;
+    }
+    if let Some((range, element)) = elements.first() {
+        if !element.attributes.is_empty() {
+            return None;
+        }
+        event_extra.mgrwgt = Some(MgRwgt::from_text(element.content)?);
+        rest = format!("{}{}", &rest[..range.start], &rest[range.end..]);
+    }
+    event_extra.extra = trim_card(&rest).to_string();
+    Some(event_extra)
+}
+
+/// Find the first line that starts with `prefix`, ignoring leading
+/// whitespace, and return it together with the text without it
+fn take_line<'a>(text: &'a str, prefix: &str) -> Option<(&'a str, String)> {
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().starts_with(prefix) {
+            let without = format!("{}{}", &text[..start], &text[start + line.len()..]);
+            return Some((line.trim(), without));
+        }
+        start += line.len();
+    }
+    None
+}
+
+impl WriteLhe for EventExtraNlo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if let Some(ref amcatnlo) = self.amcatnlo {
+            amcatnlo.write_lhe(writer)?;
+        }
+        if let Some(ref mgrwgt) = self.mgrwgt {
+            mgrwgt.write_lhe(writer)?;
+        }
+        if !self.extra.is_empty() {
+            writeln!(writer, "{}", self.extra)?;
+        }
+        Ok(())
+    }
+}
+
+impl Downgrade for EventExtraNlo {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        self.extra = downgrade_text(&self.extra, version, mode);
+    }
+}
+
+/// The information in the `#aMCatNLO` line of an event
+///
+/// The fields are named after the variables in MadGraph5_aMC@NLO.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmcatnloInfo {
+    /// Whether the event is an S event (`1`) or an H event (`2`)
+    pub isorh: i32,
+    /// The FKS parton `i`
+    pub ifks: i32,
+    /// The FKS parton `j`
+    pub jfks: i32,
+    /// The position of the father of the FKS partons
+    pub fksfather: i32,
+    /// The position of the colour partner of the father
+    pub ipartner: i32,
+    /// The starting scale of the shower
+    pub scale1: f64,
+    /// The second shower scale, for H events
+    pub scale2: f64,
+    /// The format of the reweighting information, e.g. `-9` if the
+    /// `<mgrwgt>` block is written
+    pub jwgtinfo: i32,
+    /// The maximal number of particles in the event
+    pub mexternal: i32,
+    /// The number of partons used for the reweighting information
+    pub iwgtnumpartn: i32,
+    /// The central weight
+    pub wgtcentral: f64,
+    /// The minimal weight of the scale variations
+    pub wgtmumin: f64,
+    /// The maximal weight of the scale variations
+    pub wgtmumax: f64,
+    /// The minimal weight of the PDF variations
+    pub wgtpdfmin: f64,
+    /// The maximal weight of the PDF variations
+    pub wgtpdfmax: f64,
+}
+
+impl AmcatnloInfo {
+    fn from_line(line: &str) -> Option<AmcatnloInfo> {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.len() != 16 || fields[0] != "#aMCatNLO" {
+            return None;
+        }
+        let int = |n: usize| fields[n].parse().ok();
+        let float = |n: usize| parse_f64(fields[n]);
+        Some(AmcatnloInfo {
+            isorh: int(1)?,
+            ifks: int(2)?,
+            jfks: int(3)?,
+            fksfather: int(4)?,
+            ipartner: int(5)?,
+            scale1: float(6)?,
+            scale2: float(7)?,
+            jwgtinfo: int(8)?,
+            mexternal: int(9)?,
+            iwgtnumpartn: int(10)?,
+            wgtcentral: float(11)?,
+            wgtmumin: float(12)?,
+            wgtmumax: float(13)?,
+            wgtpdfmin: float(14)?,
+            wgtpdfmax: float(15)?,
+        })
+    }
+}
+
+impl WriteLhe for AmcatnloInfo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(
+            writer,
+            "#aMCatNLO {} {:2} {:2} {:2} {:2}",
+            self.isorh, self.ifks, self.jfks, self.fksfather, self.ipartner
+        )?;
+        for &scale in &[self.scale1, self.scale2] {
+            write!(writer, " {}", fortran_float(scale, 8, 'E'))?;
+        }
+        write!(
+            writer,
+            " {:2} {:2} {:2}",
+            self.jwgtinfo, self.mexternal, self.iwgtnumpartn
+        )?;
+        let weights = [
+            self.wgtcentral,
+            self.wgtmumin,
+            self.wgtmumax,
+            self.wgtpdfmin,
+            self.wgtpdfmax,
+        ];
+        for &weight in &weights {
+            write!(writer, " {}", fortran_float(weight, 8, 'E'))?;
+        }
+        writeln!(writer)
+    }
+}
+
+/// The information to recompute the scale and PDF variations of a
+/// next-to-leading order event, given in an `<mgrwgt>` block
+///
+/// The fields are named after the variables in MadGraph5_aMC@NLO.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MgRwgt {
+    /// The reference weight of the event
+    pub ref_wgt: f64,
+    /// The power of the coupling of the weight
+    pub wgtcpower: i32,
+    /// The momentum configurations that the contributions refer to
+    ///
+    /// Each configuration contains the momenta `(E, px, py, pz)` of all
+    /// particles.
+    pub momenta: Vec<Vec<[f64; 4]>>,
+    /// The contributions to the weight of the event
+    pub contributions: Vec<MgRwgtContribution>,
+}
+
+impl MgRwgt {
+    fn from_text(text: &str) -> Option<MgRwgt> {
+        let lines: Vec<_> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let (first, lines) = lines.split_first()?;
+        let fields: Vec<_> = first.split_whitespace().collect();
+        if fields.len() != 4 {
+            return None;
+        }
+        let n_contributions: usize = fields[1].parse().ok()?;
+        let n_configurations: usize = fields[2].parse().ok()?;
+        let n_momenta = lines.len().checked_sub(n_contributions)?;
+        let (momenta, contributions) = lines.split_at(n_momenta);
+        let momenta: Vec<_> = momenta
+            .iter()
+            .map(|line| read_momentum(line))
+            .collect::<Option<_>>()?;
+        let momenta = match n_configurations {
+            0 if momenta.is_empty() => Vec::new(),
+            0 => return None,
+            n if momenta.len() % n == 0 => momenta
+                .chunks(momenta.len() / n)
+                .map(|configuration| configuration.to_vec())
+                .collect(),
+            _ => return None,
+        };
+        Some(MgRwgt {
+            ref_wgt: parse_f64(fields[0])?,
+            wgtcpower: fields[3].parse().ok()?,
+            momenta,
+            contributions: contributions
+                .iter()
+                .map(|line| MgRwgtContribution::from_line(line))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+fn read_momentum(line: &str) -> Option<[f64; 4]> {
+    let mut fields = line.split_whitespace();
+    let mut momentum = [0.; 4];
+    for component in &mut momentum {
+        *component = parse_f64(fields.next()?)?;
+    }
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(momentum)
+}
+
+impl WriteLhe for MgRwgt {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "  <mgrwgt>")?;
+        writeln!(
+            writer,
+            " {} {:4} {:4} {:4}",
+            fortran_float(self.ref_wgt, 10, 'D'),
+            self.contributions.len(),
+            self.momenta.len(),
+            self.wgtcpower
+        )?;
+        for momentum in self.momenta.iter().flat_map(|momenta| momenta.iter()) {
+            for &component in momentum {
+                write!(writer, " {}", fortran_float(component, 15, 'D'))?;
+            }
+            writeln!(writer)?;
+        }
+        for contribution in &self.contributions {
+            contribution.write_lhe(writer)?;
+        }
+        writeln!(writer, "  </mgrwgt>")
+    }
+}
+
+/// A contribution to the weight of an event in the `<mgrwgt>` block
+///
+/// The fields are named after the variables in MadGraph5_aMC@NLO.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MgRwgtContribution {
+    /// The coefficients of the weight that do not depend on the scales,
+    /// and that depend on the logarithm of the renormalization and the
+    /// factorization scale
+    pub wgt: [f64; 3],
+    /// The tree-level matrix elements
+    pub wgt_me_tree: [f64; 2],
+    /// The PDG ids of the partons of the contribution
+    pub pdg: Vec<PdgId>,
+    /// The power of the strong coupling
+    pub qcdpower: i32,
+    /// The Bjorken x of the two incoming partons
+    pub bjx: [f64; 2],
+    /// The squared renormalization scale and the squared factorization
+    /// scales of the two beams
+    pub scales2: [f64; 3],
+    /// The strong coupling constant `g`
+    pub g_strong: f64,
+    /// The positions of the momentum configurations for the matrix
+    /// elements and for the PDFs, starting at `1`
+    pub momenta_conf: [i32; 2],
+    /// The type of the contribution
+    pub itype: i32,
+    /// The number of the FKS configuration
+    pub nfks: i32,
+    /// The FKS parton `i`
+    pub fks_i: i32,
+    /// The FKS parton `j`
+    pub fks_j: i32,
+    /// The PDG id of the FKS parton `j` in the underlying Born process
+    pub parton_pdg_uborn: PdgId,
+    /// The weight of the contribution to the process
+    pub parton_iproc: f64,
+}
+
+impl MgRwgtContribution {
+    fn from_line(line: &str) -> Option<MgRwgtContribution> {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let n_particles: usize = fields.get(5)?.parse().ok()?;
+        if fields.len() != n_particles + 21 {
+            return None;
+        }
+        let int = |n: usize| fields[n].parse().ok();
+        let float = |n: usize| parse_f64(fields[n]);
+        let pdg = fields[6..6 + n_particles]
+            .iter()
+            .map(|id| id.parse().ok())
+            .collect::<Option<_>>()?;
+        let n = 6 + n_particles;
+        Some(MgRwgtContribution {
+            wgt: [float(0)?, float(1)?, float(2)?],
+            wgt_me_tree: [float(3)?, float(4)?],
+            pdg,
+            qcdpower: int(n)?,
+            bjx: [float(n + 1)?, float(n + 2)?],
+            scales2: [float(n + 3)?, float(n + 4)?, float(n + 5)?],
+            g_strong: float(n + 6)?,
+            momenta_conf: [int(n + 7)?, int(n + 8)?],
+            itype: int(n + 9)?,
+            nfks: int(n + 10)?,
+            fks_i: int(n + 11)?,
+            fks_j: int(n + 12)?,
+            parton_pdg_uborn: int(n + 13)? as PdgId,
+            parton_iproc: float(n + 14)?,
+        })
+    }
+}
+
+impl WriteLhe for MgRwgtContribution {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let weights = self.wgt.iter().chain(&self.wgt_me_tree);
+        let weights: Vec<_> = weights.map(|&w| fortran_float(w, 12, 'D')).collect();
+        write!(writer, "{} {:2}", weights.join(" "), self.pdg.len())?;
+        for id in &self.pdg {
+            write!(writer, " {}", id)?;
+        }
+        write!(writer, " {}", self.qcdpower)?;
+        let values = self
+            .bjx
+            .iter()
+            .chain(&self.scales2)
+            .chain(Some(&self.g_strong));
+        for &value in values {
+            write!(writer, " {}", fortran_float(value, 8, 'D'))?;
+        }
+        let flags = [
+            self.momenta_conf[0],
+            self.momenta_conf[1],
+            self.itype,
+            self.nfks,
+            self.fks_i,
+            self.fks_j,
+        ];
+        for flag in &flags {
+            write!(writer, " {:2}", flag)?;
+        }
+        writeln!(
+            writer,
+            " {:8} {}",
+            self.parton_pdg_uborn,
+            fortran_float(self.parton_iproc, 12, 'D')
+        )
+    }
+}
+
+/// Format a number like the Fortran edit descriptors `Ew.d` and `Dw.d`
+/// with `w = d + 6`, as used by MadGraph5_aMC@NLO
+///
+/// The mantissa has `digits` digits after the decimal point and is
+/// written as `0.ddd`, or as `-.ddd` for negative numbers, since the
+/// width leaves no room for the leading zero.
+/// Infinite values and NaN are written as `Infinity`, `-Infinity` and
+/// `NaN`, right-justified in the width of the field.
+fn fortran_float(value: f64, digits: usize, exponent_char: char) -> String {
+    if value.is_nan() {
+        return format!("{:>1$}", "NaN", digits + 6);
+    } else if value.is_infinite() {
+        let sign = if value < 0. { "-" } else { "" };
+        return format!("{:>1$}", format!("{}Infinity", sign), digits + 6);
+    }
+    let sign = if value.is_sign_negative() { "-" } else { "0" };
+    if value == 0. {
+        return format!("{}.{}{}+00", sign, "0".repeat(digits), exponent_char);
+    }
+    let scientific = format!("{:.*e}", digits - 1, value.abs());
+    let (mantissa, exponent) = scientific.split_at(
+        scientific
+            .find('e')
+            .expect("BUG: Scientific format without an exponent"),
+    );
+    let exponent: i32 = exponent[1..]
+        .parse()
+        .expect("BUG: Scientific format with an invalid exponent");
+    let exponent = exponent + 1;
+    let exponent = if exponent.abs() < 100 {
+        format!("{}{:+03}", exponent_char, exponent)
+    } else {
+        format!("{:+04}", exponent)
+    };
+    format!("{}.{}{}", sign, mantissa.replace('.', ""), exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, str};
+
+    use {ReadLhe, WriteLhe};
+    use generic::LheFileGeneric;
+    use string;
+
+    use super::{
+        fortran_float, AmcatnloInfo, EventExtra, Header, HeaderBlock, LheFile, LheFileNlo, PdfRwt,
+        ProcCommand, RunCardLine,
+    };
+
+    const SAMPLE_FILES: &[&str] = &[
+        "tests/real_world_files/mg5_aMC.lhe",
+        "tests/real_world_files/mg5_aMC_NLO.lhe",
+        "tests/real_world_files/mg5_aMC_NLO_rwgt.lhe",
+    ];
+
+    #[test]
+    fn read_header() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let header = &lhe.header;
+
+        let proc_card = header.proc_card().unwrap();
+        assert_eq!(proc_card.model(), Some("sm"));
+        assert_eq!(proc_card.option("gauge"), Some("unitary"));
+        assert_eq!(proc_card.option("loop_optimized_output"), Some("True"));
+        let definitions = proc_card.definitions();
+        assert_eq!(definitions.len(), 6);
+        assert_eq!(
+            definitions["l+"],
+            &["e+".to_string(), "mu+".to_string()][..]
+        );
+        assert_eq!(proc_card.processes(), vec!["e+ e- > mu+ mu-"]);
+        assert!(proc_card
+            .commands
+            .contains(&ProcCommand::Output("foo".to_string())));
+
+        let run_card = header.run_card().unwrap();
+        assert_eq!(run_card.get("run_tag"), Some("tag_1"));
+        assert_eq!(run_card.get_i64("nevents"), Some(10));
+        assert_eq!(run_card.get_f64("ebeam1"), Some(500.));
+        assert_eq!(run_card.get_bool("use_syst"), Some(true));
+        assert_eq!(run_card.get("sys_scalefact"), Some("0.5 1 2"));
+        assert_eq!(run_card.get("sys_pdf"), Some("NNPDF23_lo_as_0130_qed"));
+        let parameter = run_card.parameter("run_tag").unwrap();
+        assert_eq!(parameter.comment, Some("name of the run".to_string()));
+
+        let info = header.generation_info().unwrap();
+        assert_eq!(info.n_events(), Some(10));
+        assert_eq!(info.integrated_weight(), Some(0.104223));
+
+        let others: Vec<_> = header
+            .blocks
+            .iter()
+            .filter_map(|block| match *block {
+                HeaderBlock::Other(ref text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(others.len(), 2);
+        assert!(others[0].ends_with("<MGVersion>\n2.5.1\n</MGVersion>"));
+        assert!(others[1].starts_with("<MGProcCard>"));
+
+        let slha = header.slha().unwrap();
+        assert_eq!(slha.mass(6), Some(173.));
+        assert_eq!(slha.mass(24), Some(80.419002));
+        assert_eq!(slha.width(25), Some(6.382339e-03));
+        assert_eq!(slha.block("sminputs").unwrap().value(&[3]), Some(0.118));
+    }
+
+    #[test]
+    fn read_nlo_header() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO.lhe").unwrap();
+        let run_card = lhe.header.run_card().unwrap();
+        assert_eq!(run_card.get_i64("nevents"), Some(10));
+        assert_eq!(run_card.get_f64("req_acc"), Some(-1.));
+        assert_eq!(run_card.get_i64("nevt_job"), Some(-1));
+        assert!(lhe.header.proc_card().is_some());
+        assert!(lhe.header.generation_info().is_none());
+        match lhe.header.blocks.last() {
+            Some(HeaderBlock::Other(text)) => assert!(text.ends_with("</initrwgt>")),
+            _ => panic!("Expected the rest of the header as text"),
+        }
+    }
+
+    #[test]
+    fn set_parameter() {
+        let mut header = Header::read_lhe(
+            b"<header>\n<MGRunCard>\n  10 = nevents ! events\n  1 = lpp1 ! beam 1\n</MGRunCard>\n</header>",
+        )
+        .to_full_result()
+        .unwrap();
+        match header.blocks[0] {
+            HeaderBlock::RunCard(ref mut run_card) => {
+                run_card.set("nevents", "20");
+                run_card.set("lpp1", "1");
+                run_card.set("iseed", "42");
+            }
+            _ => panic!("Expected a run card"),
+        }
+        let mut bytes = Vec::new();
+        header.write_lhe(&mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "<header>\n<MGRunCard>\n 20 = nevents ! events\n  1 = lpp1 ! beam 1\n 42 = iseed\n</MGRunCard>\n</header>\n"
+        );
+    }
+
+    #[test]
+    fn edit_slha() {
+        let mut lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        lhe.header.slha_mut().unwrap().set_mass(6, 172.5);
+        lhe.header.slha_mut().unwrap().set_width(6, 1.33);
+
+        let mut bytes = Vec::new();
+        lhe.write_lhe(&mut bytes).unwrap();
+        let round = LheFile::read_lhe(&bytes).to_full_result().unwrap();
+        let slha = round.header.slha().unwrap();
+        assert_eq!(slha.mass(6), Some(172.5));
+        assert_eq!(slha.width(6), Some(1.33));
+        assert_eq!(slha.mass(5), Some(4.7));
+        assert_eq!(round, lhe);
+    }
+
+    #[test]
+    fn other_lines() {
+        assert_eq!(
+            RunCardLine::from_line("                 !  (-1= no split)."),
+            RunCardLine::Other("                 !  (-1= no split).".to_string())
+        );
+        assert_eq!(
+            ProcCommand::from_line("define p g u"),
+            ProcCommand::Other("define p g u".to_string())
+        );
+        assert_eq!(
+            ProcCommand::from_line("add process p p > t t~ @1"),
+            ProcCommand::AddProcess("p p > t t~ @1".to_string())
+        );
+    }
+
+    #[test]
+    fn same_events_as_string() {
+        for file_name in SAMPLE_FILES {
+            let lhe = LheFile::read_lhe_from_file(file_name).unwrap();
+            let plain: string::LheFile = LheFileGeneric::read_lhe_from_file(file_name).unwrap();
+            assert_eq!(lhe.events.len(), plain.events.len());
+            for (event, plain) in lhe.events.iter().zip(&plain.events) {
+                assert_eq!(event.particles, plain.particles);
+                if event.extra.mgrwt.is_none() {
+                    assert_eq!(event.extra.extra, plain.extra.0);
+                }
+            }
+            assert!(lhe.header.run_card().is_some());
+        }
+    }
+
+    #[test]
+    fn read_mgrwt() {
+        let lhe = LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        for event in &lhe.events {
+            assert!(event.extra.mgrwt.is_some());
+            assert_eq!(event.extra.extra, "");
+        }
+        let mgrwt = lhe.events[0].extra.mgrwt.as_ref().unwrap();
+        assert_eq!(mgrwt.n_alpha_s, 0);
+        assert_eq!(mgrwt.renormalization_scale, 1000.);
+        assert!(mgrwt.alpha_s_scales.is_empty());
+        let pdfrwt = |flavour| {
+            vec![PdfRwt {
+                flavour,
+                x: 1.,
+                scale: 1000.,
+            }]
+        };
+        assert_eq!(mgrwt.pdfrwt, [pdfrwt(-11), pdfrwt(11)]);
+        assert_eq!(mgrwt.totfact, 1.);
+    }
+
+    #[test]
+    fn read_mgrwt_entries() {
+        let bytes = b"\
+# merged event
+<mgrwt>
+<rscale>  2 0.12000000E+03</rscale>
+<asrwt>  2 0.25000000E+02 0.40000000E+02</asrwt>
+<pdfrwt beam=\"2\">  1       -1 0.30000000E+00 0.25000000E+02</pdfrwt>
+<pdfrwt beam=\"1\">  2       21        2 0.10000000E-01 0.20000000E-01 0.40000000E+02 0.25000000E+02</pdfrwt>
+<totfact> 0.95000000E+00</totfact>
+</mgrwt>
+</event>";
+        let extra = EventExtra::read_lhe(bytes).to_full_result().unwrap();
+        assert_eq!(extra.extra, "# merged event");
+        let mgrwt = extra.mgrwt.as_ref().unwrap();
+        assert_eq!(mgrwt.n_alpha_s, 2);
+        assert_eq!(mgrwt.renormalization_scale, 120.);
+        assert_eq!(mgrwt.alpha_s_scales, vec![25., 40.]);
+        let beam1 = vec![
+            PdfRwt {
+                flavour: 21,
+                x: 0.01,
+                scale: 40.,
+            },
+            PdfRwt {
+                flavour: 2,
+                x: 0.02,
+                scale: 25.,
+            },
+        ];
+        assert_eq!(mgrwt.pdfrwt[0], beam1);
+        assert_eq!(mgrwt.pdfrwt[1][0].flavour, -1);
+        assert_eq!(mgrwt.totfact, 0.95);
+
+        let mut bytes = Vec::new();
+        extra.write_lhe(&mut bytes).unwrap();
+        bytes.extend_from_slice(b"</event>");
+        assert_eq!(EventExtra::read_lhe(&bytes).to_full_result(), Ok(extra));
+    }
+
+    #[test]
+    fn read_nlo_extras() {
+        let lhe =
+            LheFileNlo::read_lhe_from_file(&"tests/real_world_files/mg5_aMC_NLO_rwgt.lhe").unwrap();
+        let extra = &lhe.events[0].extra;
+        let amcatnlo = extra.amcatnlo.as_ref().unwrap();
+        assert_eq!((amcatnlo.isorh, amcatnlo.ifks, amcatnlo.jfks), (1, 0, 0));
+        assert_eq!((amcatnlo.fksfather, amcatnlo.ipartner), (2, 1));
+        assert_eq!(amcatnlo.scale1, 60.627164);
+        assert_eq!(amcatnlo.jwgtinfo, -9);
+        assert_eq!(amcatnlo.mexternal, 5);
+
+        let mgrwgt = extra.mgrwgt.as_ref().unwrap();
+        assert_eq!(mgrwgt.ref_wgt, 0.69425263e8);
+        assert_eq!(mgrwgt.wgtcpower, 0);
+        assert_eq!(mgrwgt.momenta.len(), 2);
+        assert!(mgrwgt.momenta.iter().all(|momenta| momenta.len() == 5));
+        assert_eq!(mgrwgt.momenta[0][4], [0.; 4]);
+        assert!(mgrwgt.momenta[0][4][2].is_sign_negative());
+        assert_eq!(mgrwgt.momenta[1][4][0], 92.5854177959223);
+        assert_eq!(mgrwgt.contributions.len(), 3);
+        let contribution = &mgrwgt.contributions[1];
+        assert_eq!(contribution.wgt[0], -0.261795294138e-2);
+        assert_eq!(contribution.wgt_me_tree, [0.347355539454e1, 0.]);
+        assert_eq!(contribution.pdg, vec![21, 21, 5, -5, 21]);
+        assert_eq!(contribution.qcdpower, 6);
+        assert_eq!(contribution.bjx, [0.15394400e-2, 0.93223773e-3]);
+        assert_eq!(contribution.scales2, [0.39673563e2; 3]);
+        assert_eq!(contribution.momenta_conf, [1, 2]);
+        assert_eq!((contribution.itype, contribution.nfks), (3, 2));
+        assert_eq!((contribution.fks_i, contribution.fks_j), (5, 2));
+        assert_eq!(contribution.parton_pdg_uborn, 21);
+        assert_eq!(contribution.parton_iproc, -0.395604755631e7);
+
+        assert!(extra.extra.starts_with("  <rwgt>\n   <wgt id='1001'>"));
+        assert!(extra.extra.ends_with("</rwgt>"));
+    }
+
+    #[test]
+    fn nlo_extras_byte_for_byte() {
+        for file_name in &SAMPLE_FILES[1..] {
+            let lhe = LheFileNlo::read_lhe_from_file(file_name).unwrap();
+            let file = fs::read(file_name).unwrap();
+            let file = str::from_utf8(&file).unwrap();
+            let events: Vec<_> = file.split("<event>").skip(1).collect();
+            assert_eq!(events.len(), lhe.events.len());
+            for (event, text) in lhe.events.iter().zip(events) {
+                assert!(event.extra.amcatnlo.is_some());
+                // The extra information starts after the rest of the
+                // `<event>` line, the event line and the particle lines
+                // and ends before the line of the closing tag
+                let text = &text[..text.find("</event>").unwrap()];
+                let text = &text[..text.rfind('\n').unwrap() + 1];
+                let extra: String = text
+                    .split_inclusive('\n')
+                    .skip(2 + event.particles.len())
+                    .collect();
+                let mut bytes = Vec::new();
+                event.extra.write_lhe(&mut bytes).unwrap();
+                assert_eq!(String::from_utf8(bytes).unwrap(), extra);
+            }
+
+            let mut bytes = Vec::new();
+            lhe.write_lhe(&mut bytes).unwrap();
+            assert_eq!(LheFileNlo::read_lhe(&bytes).to_full_result(), Ok(lhe));
+        }
+    }
+
+    #[test]
+    fn format_fortran_floats() {
+        assert_eq!(fortran_float(60.627164, 8, 'E'), "0.60627164E+02");
+        assert_eq!(
+            fortran_float(-7.78678299709749, 15, 'D'),
+            "-.778678299709749D+01"
+        );
+        assert_eq!(fortran_float(0.0015394400, 8, 'D'), "0.15394400D-02");
+        assert_eq!(fortran_float(0., 8, 'E'), "0.00000000E+00");
+        assert_eq!(fortran_float(-0., 12, 'D'), "-.000000000000D+00");
+        assert_eq!(fortran_float(0.99999999999, 4, 'E'), "0.1000E+01");
+        assert_eq!(fortran_float(1.5e-120, 4, 'D'), "0.1500-119");
+        assert_eq!(fortran_float(f64::INFINITY, 8, 'E'), "      Infinity");
+        assert_eq!(fortran_float(f64::NEG_INFINITY, 4, 'D'), " -Infinity");
+        assert_eq!(fortran_float(f64::NAN, 8, 'D'), "           NaN");
+    }
+
+    #[test]
+    fn write_non_finite_amcatnlo() {
+        let line = "#aMCatNLO 1  0  0  2  1 Infinity 0.00000000E+00 -9  5  0 \
+                    NaN 0.00000000E+00 0.00000000E+00 0.00000000E+00 -Infinity";
+        let info = AmcatnloInfo::from_line(line).unwrap();
+        assert_eq!(info.scale1, f64::INFINITY);
+        assert!(info.wgtcentral.is_nan());
+        let mut bytes = Vec::new();
+        info.write_lhe(&mut bytes).unwrap();
+        let written = String::from_utf8(bytes).unwrap();
+        assert_eq!(
+            written.split_whitespace().collect::<Vec<_>>(),
+            line.split_whitespace().collect::<Vec<_>>()
+        );
+        let round = AmcatnloInfo::from_line(&written).unwrap();
+        assert_eq!(round.scale1, f64::INFINITY);
+        assert_eq!(round.wgtpdfmax, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn invalid_mgrwt() {
+        let blocks: &[&[u8]] = &[
+            b"<mgrwt><rscale>0 1E+03</rscale><asrwt>1</asrwt></mgrwt></event>",
+            b"<mgrwt><rscale>0 1E+03</rscale><asrwt>0</asrwt>\
+              <pdfrwt beam=\"1\">1 21 0.1 1E+03</pdfrwt>\
+              <totfact>1</totfact></mgrwt></event>",
+            b"<mgrwt><rscale>0 1E+03</rscale><asrwt>0</asrwt>\
+              <pdfrwt beam=\"1\">1 21 0.1 1E+03</pdfrwt>\
+              <pdfrwt beam=\"3\">1 21 0.1 1E+03</pdfrwt>\
+              <totfact>1</totfact></mgrwt></event>",
+            b"<mgrwt><rscale>0 1E+03</rscale><asrwt>0</asrwt>\
+              <pdfrwt beam=\"1\">2 21 0.1 1E+03</pdfrwt>\
+              <pdfrwt beam=\"2\">1 21 0.1 1E+03</pdfrwt>\
+              <totfact>1</totfact></mgrwt></event>",
+        ];
+        for bytes in blocks {
+            assert!(EventExtra::read_lhe(bytes).to_full_result().is_err());
+        }
+    }
+
+    #[test]
+    fn roundtrip_sample_files() {
+        for file_name in SAMPLE_FILES {
+            let lhe = LheFile::read_lhe_from_file(file_name).unwrap();
+            let mut bytes = Vec::new();
+            lhe.write_lhe(&mut bytes).unwrap();
+            let round = match LheFile::read_lhe(&bytes).to_full_result() {
+                Ok(l) => l,
+                Err(e) => panic!("Failed to read roundtrip for {}: {:?}", file_name, e),
+            };
+            assert_eq!(lhe, round);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parse the events in lhe files in parallel
+//!
+//! This module is only available if the `parallel` feature is enabled.
+//!
+//! The start of the file, i.e. the comment, the header and the init
+//! section, is parsed as usual.
+//! The rest of the file is then split into the individual `<event>`
+//! blocks, which are parsed in parallel using [`rayon`].
+//! The `<eventgroup>` tags around events are taken into account when
+//! splitting the file.
+//! Files with comments between the events are parsed sequentially.
+//! The events are returned in the order in which they appear in the
+//! file, so the result is the same as the one of the sequential
+//! `LheFileGeneric::read_lhe`.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::parallel;
+//! use lhef::plain::LheFile;
+//!
+//! let lhe: LheFile = parallel::read_lhe_from_file(&"events.lhe").unwrap();
+//! ```
+//!
+//! [`rayon`]: https://github.com/rayon-rs/rayon
+
+use attributes::{read_opening_tag, Attributes};
+use compression;
+use generic::{read_closing_file_tag, read_file_start, EventGeneric, EventGroupRange,
+              LheFileGeneric};
+use index::EventIndex;
+use reader::trim_start;
+use ReadError;
+use ReadLhe;
+
+use nom;
+use rayon::prelude::*;
+use std::io::Read;
+use std::path::Path;
+
+/// Read a complete lhe file, parsing the events in parallel
+///
+/// This gives the same result as `LheFileGeneric::read_lhe_from_bytes`.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::parallel;
+/// use lhef::plain::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"1.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// </event>
+/// <event>
+/// 2 1 2.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 2.0 2.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -2.0 2.0 0.0 0. 9.
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe: LheFile = parallel::read_lhe(bytes).unwrap();
+/// assert_eq!(lhe, LheFile::read_lhe(bytes).to_full_result().unwrap());
+/// ```
+pub fn read_lhe<Comment, Header, InitExtra, EventExtra>(
+    input: &[u8],
+) -> Result<LheFileGeneric<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    Comment: ReadLhe + PartialEq,
+    Header: ReadLhe + PartialEq,
+    InitExtra: ReadLhe + PartialEq,
+    EventExtra: ReadLhe + Send,
+{
+    // Any input that doesn't have the expected structure is handed to
+    // the sequential parser, which reports the error.
+    let (rest, start) = match read_file_start(input) {
+        nom::IResult::Done(rest, start) => (rest, start),
+        _ => return read_sequential(input),
+    };
+    let (events, groups) = match split_events(rest) {
+        Some(split) => split,
+        None => return read_sequential(input),
+    };
+    let events: Result<Vec<_>, _> = events
+        .par_iter()
+        .map(|event| EventGeneric::read_lhe(event).to_full_result())
+        .collect();
+    match events {
+        Ok(events) => {
+            let mut lhe = LheFileGeneric::from_start(start, Vec::new());
+            lhe.events = events;
+            lhe.groups = groups;
+            Ok(lhe)
+        }
+        Err(_) => read_sequential(input),
+    }
+}
+
+/// Read a complete lhe file from the file at `path`, parsing the events
+/// in parallel
+///
+/// Compressed files are decompressed on the fly, see the
+/// [`compression`] module.
+///
+/// [`compression`]: ../compression/index.html
+pub fn read_lhe_from_file<P, Comment, Header, InitExtra, EventExtra>(
+    path: &P,
+) -> Result<LheFileGeneric<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    P: AsRef<Path>,
+    Comment: ReadLhe + PartialEq,
+    Header: ReadLhe + PartialEq,
+    InitExtra: ReadLhe + PartialEq,
+    EventExtra: ReadLhe + Send,
+{
+    let mut file = compression::open(path)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    read_lhe(&contents)
+}
+
+fn read_sequential<Comment, Header, InitExtra, EventExtra>(
+    input: &[u8],
+) -> Result<LheFileGeneric<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    Comment: ReadLhe + PartialEq,
+    Header: ReadLhe + PartialEq,
+    InitExtra: ReadLhe + PartialEq,
+    EventExtra: ReadLhe,
+{
+    LheFileGeneric::read_lhe_from_bytes(input)
+}
+
+/// Split the event section of a file into the individual events and
+/// the event groups
+///
+/// Returns `None` if there is anything but whitespace and the tags of
+/// event groups between the events, if the groups are nested or not
+/// closed, or if the events are not followed by the closing tag of the
+/// file.
+fn split_events(input: &[u8]) -> Option<(Vec<&[u8]>, Vec<EventGroupRange>)> {
+    let index = EventIndex::build(input).ok()?;
+    let mut events = Vec::with_capacity(index.len());
+    let mut groups = Vec::new();
+    let mut group = None;
+    let mut position = 0;
+    for range in index.ranges() {
+        let (start, end) = (range.start as usize, range.end as usize);
+        let gap = &input[position..start];
+        if !skip_group_tags(gap, events.len(), &mut group, &mut groups)?.is_empty() {
+            return None;
+        }
+        events.push(&input[start..end]);
+        position = end;
+    }
+    let rest = skip_group_tags(&input[position..], events.len(), &mut group, &mut groups)?;
+    if group.is_some() {
+        return None;
+    }
+    match read_closing_file_tag(rest) {
+        nom::IResult::Done(&[], _) => Some((events, groups)),
+        _ => None,
+    }
+}
+
+/// Skip the whitespace and the tags of event groups at the start of
+/// `input`, opening and closing the groups before the `n_events`th event
+///
+/// Returns `None` if the groups are nested or closed without being
+/// opened.
+fn skip_group_tags<'a>(
+    mut input: &'a [u8],
+    n_events: usize,
+    group: &mut Option<(Attributes, usize)>,
+    groups: &mut Vec<EventGroupRange>,
+) -> Option<&'a [u8]> {
+    loop {
+        input = trim_start(input);
+        if input.starts_with(b"</eventgroup>") {
+            let (attributes, start) = group.take()?;
+            groups.push(EventGroupRange {
+                attributes,
+                events: start..n_events,
+            });
+            input = &input[b"</eventgroup>".len()..];
+        } else if input.starts_with(b"<eventgroup") && group.is_none() {
+            match read_opening_tag(input, "eventgroup") {
+                nom::IResult::Done(rest, attributes) => {
+                    *group = Some((attributes, n_events));
+                    input = rest;
+                }
+                _ => return None,
+            }
+        } else {
+            return Some(input);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generic::LheFileGeneric;
+    use helac;
+    use plain;
+    use string;
+    use ReadLhe;
+
+    use super::read_lhe;
+
+    use std::fs;
+
+    const SAMPLE_FILES: &[&str] = &[
+        "tests/real_world_files/helac_1loop_tree.lhe",
+        "tests/real_world_files/helac_1loop_virt.lhe",
+        "tests/real_world_files/helac_dipoles_i.lhe",
+        "tests/real_world_files/helac_dipoles_kp.lhe",
+        "tests/real_world_files/helac_dipoles_rs.lhe",
+        "tests/real_world_files/mg5_aMC.lhe",
+        "tests/real_world_files/mg5_aMC_NLO.lhe",
+        "tests/real_world_files/mg5_aMC_NLO_rwgt.lhe",
+    ];
+
+    #[test]
+    fn same_as_sequential() {
+        for file in SAMPLE_FILES {
+            let bytes = fs::read(file).unwrap();
+            let sequential = string::LheFile::read_lhe(&bytes).to_full_result().unwrap();
+            let parallel: string::LheFile = read_lhe(&bytes).unwrap();
+            assert_eq!(sequential, parallel);
+            let sequential = plain::LheFile::read_lhe(&bytes).to_full_result().unwrap();
+            let parallel: plain::LheFile = read_lhe(&bytes).unwrap();
+            assert_eq!(sequential, parallel);
+        }
+    }
+
+    #[test]
+    fn same_as_sequential_helac() {
+        let file = "tests/real_world_files/helac_dipoles_rs.lhe";
+        let sequential: helac::LheFileRS = LheFileGeneric::read_lhe_from_file(&file).unwrap();
+        let parallel: helac::LheFileRS = super::read_lhe_from_file(&file).unwrap();
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn error_like_sequential() {
+        let bytes = fs::read("tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let truncated = &bytes[..bytes.len() / 2];
+        let sequential = string::LheFile::read_lhe(truncated).to_full_result();
+        let parallel =
+            read_lhe::<string::Comment, string::Header, string::InitExtra, string::EventExtra>(
+                truncated,
+            );
+        assert!(sequential.is_err());
+        assert!(parallel.is_err());
+    }
+
+    #[test]
+    fn event_groups() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<eventgroup nreal=\"1\" ncounter=\"1\">
+<event>
+0 1 1.0 91.2 0.0078 0.118
+</event>
+<event>
+0 1 -1.0 91.2 0.0078 0.118
+</event>
+</eventgroup>
+<event>
+0 1 2.0 91.2 0.0078 0.118
+</event>
+<eventgroup>
+<event>
+0 1 3.0 91.2 0.0078 0.118
+</event>
+</eventgroup>
+</LesHouchesEvents>
+";
+        let sequential = plain::LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let parallel: plain::LheFile = read_lhe(bytes).unwrap();
+        assert_eq!(sequential.groups.len(), 2);
+        assert_eq!(sequential, parallel);
+
+        let unclosed = String::from_utf8(bytes.to_vec())
+            .unwrap()
+            .replacen("</eventgroup>\n", "", 1);
+        assert!(read_lhe::<plain::Comment, plain::Header, plain::InitExtra, plain::EventExtra>(
+            unclosed.as_bytes()
+        ).is_err());
+    }
+}
@@ -58,7 +58,7 @@
 //!
 //! let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
 //!
-//! assert_eq!(lhe.version, "1.0".to_string());
+//! assert_eq!(lhe.version.as_str(), "1.0");
 //!
 //! assert_eq!(lhe.comment, Comment {});
 //!
@@ -77,6 +77,8 @@
 
 use {ReadLhe, WriteLhe};
 use generic::LheFileGeneric;
+use nom_util::take_until_closing_tag;
+use version::Downgrade;
 
 use nom;
 use std::io;
@@ -122,7 +124,7 @@ use quickcheck::Gen;
 ///
 /// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
 ///
-/// assert_eq!(lhe.version, "1.0".to_string());
+/// assert_eq!(lhe.version.as_str(), "1.0");
 ///
 /// assert_eq!(lhe.comment, Comment {});
 ///
@@ -203,6 +205,8 @@ impl WriteLhe for Comment {
     }
 }
 
+impl Downgrade for Comment {}
+
 #[cfg(test)]
 impl Arbitrary for Comment {
     fn arbitrary<G: Gen>(_gen: &mut G) -> Comment {
@@ -264,7 +268,7 @@ impl ReadLhe for Header {
             input,
             opt!(delimited!(
                 tag!("<header>"),
-                take_until!("</header>"),
+                call!(take_until_closing_tag, "</header>"),
                 tag!("</header>")
             )) >> (Header {})
         )
@@ -277,6 +281,8 @@ impl WriteLhe for Header {
     }
 }
 
+impl Downgrade for Header {}
+
 #[cfg(test)]
 impl Arbitrary for Header {
     fn arbitrary<G: Gen>(_gen: &mut G) -> Header {
@@ -325,6 +331,8 @@ impl WriteLhe for InitExtra {
     }
 }
 
+impl Downgrade for InitExtra {}
+
 #[cfg(test)]
 impl Arbitrary for InitExtra {
     fn arbitrary<G: Gen>(_gen: &mut G) -> InitExtra {
@@ -382,6 +390,8 @@ impl WriteLhe for EventExtra {
     }
 }
 
+impl Downgrade for EventExtra {}
+
 #[cfg(test)]
 impl Arbitrary for EventExtra {
     fn arbitrary<G: Gen>(_gen: &mut G) -> EventExtra {
@@ -397,8 +407,10 @@ mod tests {
     use std::str;
 
     use {Particle, ProcInfo, ReadLhe, WriteLhe};
+    use attributes::Attributes;
     use generic::{EventGeneric, InitGeneric, LheFileGeneric};
     use lorentz_vector::LorentzVector;
+    use version::LhefVersion;
 
     use super::*;
 
@@ -501,6 +513,7 @@ extra line 2
                 },
             ],
             extra: InitExtra {},
+            attributes: Attributes::new(),
         };
         let result = InitGeneric::<InitExtra>::read_lhe(bytes)
             .to_full_result()
@@ -561,6 +574,7 @@ extra line 2
                 },
             ],
             extra: EventExtra {},
+            attributes: Attributes::new(),
         };
         let result = EventGeneric::<EventExtra>::read_lhe(bytes)
             .to_full_result()
@@ -581,7 +595,8 @@ extra line 2
 </event>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {},
             header: Header {},
             init: InitGeneric {
@@ -596,6 +611,7 @@ extra line 2
                 weighting_strategy: 60,
                 process_info: vec![],
                 extra: InitExtra {},
+                attributes: Attributes::new(),
             },
             events: vec![
                 EventGeneric {
@@ -624,8 +640,11 @@ extra line 2
                         },
                     ],
                     extra: EventExtra {},
+                    attributes: Attributes::new(),
                 },
             ],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -643,7 +662,8 @@ Generated using numbers
 </init>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {},
             header: Header {},
             init: InitGeneric {
@@ -658,8 +678,11 @@ Generated using numbers
                 weighting_strategy: 60,
                 process_info: vec![],
                 extra: InitExtra {},
+                attributes: Attributes::new(),
             },
             events: vec![],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -679,7 +702,8 @@ header line 1
 </header>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {},
             header: Header {},
             init: InitGeneric {
@@ -694,8 +718,11 @@ header line 1
                 weighting_strategy: 60,
                 process_info: vec![],
                 extra: InitExtra {},
+                attributes: Attributes::new(),
             },
             events: vec![],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -718,7 +745,8 @@ header line 1
 </init>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {},
             header: Header {},
             init: InitGeneric {
@@ -733,8 +761,11 @@ header line 1
                 weighting_strategy: 60,
                 process_info: vec![],
                 extra: InitExtra {},
+                attributes: Attributes::new(),
             },
             events: vec![],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -772,7 +803,8 @@ header line 1
 </event>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {},
             header: Header {},
             init: InitGeneric {
@@ -800,6 +832,7 @@ header line 1
                     },
                 ],
                 extra: InitExtra {},
+                attributes: Attributes::new(),
             },
             events: vec![
                 EventGeneric {
@@ -845,6 +878,7 @@ header line 1
                         },
                     ],
                     extra: EventExtra {},
+                    attributes: Attributes::new(),
                 },
                 EventGeneric {
                     process_id: 33,
@@ -872,8 +906,11 @@ header line 1
                         },
                     ],
                     extra: EventExtra {},
+                    attributes: Attributes::new(),
                 },
             ],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -911,7 +948,8 @@ Generated using numbers
 </event>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {},
             header: Header {},
             init: InitGeneric {
@@ -939,6 +977,7 @@ Generated using numbers
                     },
                 ],
                 extra: InitExtra {},
+                attributes: Attributes::new(),
             },
             events: vec![
                 EventGeneric {
@@ -984,6 +1023,7 @@ Generated using numbers
                         },
                     ],
                     extra: EventExtra {},
+                    attributes: Attributes::new(),
                 },
                 EventGeneric {
                     process_id: 33,
@@ -1011,8 +1051,11 @@ Generated using numbers
                         },
                     ],
                     extra: EventExtra {},
+                    attributes: Attributes::new(),
                 },
             ],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -0,0 +1,715 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A specialized module for lhe files from POWHEG-BOX
+//!
+//! POWHEG-BOX writes additional information for reweighting as lines
+//! starting with `#` at the end of each event:
+//! the `#rwgt` line with the state of the random number generator, the
+//! `#pdf` line with the parton densities and one `#new weight` line for
+//! each weight that was added by a reweighting run.
+//! These lines are parsed into specialized types, while the header with
+//! the weight declarations is read as in the `lhef3` module.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::ReadLhe;
+//! use lhef::powheg::LheFile;
+//!
+//! let lhe = LheFile::read_lhe_from_file(&"pwgevents.lhe").unwrap();
+//!
+//! // the factorization scale of the 1st event
+//! let scale = lhe.events[0].extra.pdf.as_ref().map(|pdf| pdf.scale);
+//! ```
+
+use {PdgId, ReadLhe, WriteLhe};
+use error::{ErrorLocator, ParseError, FLOAT, INTEGER};
+use generic::LheFileGeneric;
+use lhef3;
+use nom_util::{parse_f64, parse_i64};
+use slha;
+use string;
+use version::{downgrade_text, Downgrade, DowngradeMode, LhefVersion};
+
+use nom;
+use std::io;
+use std::str;
+
+#[cfg(test)]
+use quickcheck::Arbitrary;
+#[cfg(test)]
+use quickcheck::Gen;
+
+/// A type to read and write lhe files generated by POWHEG-BOX
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::powheg::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <init>
+/// 2212 2212 6.5e3 6.5e3 -1 -1 -1 -1 -4 1
+/// 1.0e1 1.0e-1 1.0 10001
+/// </init>
+/// <event>
+/// 2 10001 1.0e1 9.1e1 -1.0 1.18e-1
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// ##pdf    21    21  0.12E-01  0.34E-01  0.91E+02  0.56E+01  0.78E+00
+/// ##rwgt            1          45  0.73415937412458E-01    12345678           3           0
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let extra = &lhe.events[0].extra;
+/// assert_eq!(extra.pdf.as_ref().unwrap().x2, 0.034);
+/// assert_eq!(extra.rwgt.as_ref().unwrap().random_state.seed, 12345678);
+/// ```
+pub type LheFile = LheFileGeneric<string::Comment, lhef3::Header, string::InitExtra, EventExtra>;
+
+/// Settings of a weight declared in the header
+///
+/// POWHEG-BOX describes the weights in the `<initrwgt>` block of the
+/// header by settings of the form `renscfact=2d0 facscfact=2d0
+/// lhapdf=13100`.
+/// The factors of the scales and the PDF set are parsed, all other
+/// settings are kept as pairs of their name and value.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::powheg::{LheFile, WeightSettings};
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"3.0\">
+/// <header>
+/// <initrwgt>
+/// <weight id='1'> renscfact=2d0 facscfact=0.5d0 </weight>
+/// <weight id='2'> lhapdf=13100 </weight>
+/// </initrwgt>
+/// </header>
+/// <init>
+/// 2212 2212 6.5e3 6.5e3 -1 -1 -1 -1 -4 1
+/// 1.0e1 1.0e-1 1.0 10001
+/// </init>
+/// </LesHouchesEvents>";
+///
+/// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+///
+/// let initrwgt = lhe.header.initrwgt.as_ref().unwrap();
+/// let settings = WeightSettings::from_weight(initrwgt.weight("1").unwrap());
+/// assert_eq!(settings.renscfact, Some(2.));
+/// assert_eq!(settings.facscfact, Some(0.5));
+/// let settings = WeightSettings::from_weight(initrwgt.weight("2").unwrap());
+/// assert_eq!(settings.lhapdf, Some(13100));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeightSettings {
+    /// The factor of the renormalization scale
+    pub renscfact: Option<f64>,
+    /// The factor of the factorization scale
+    pub facscfact: Option<f64>,
+    /// The LHAPDF id of the PDF set
+    pub lhapdf: Option<i64>,
+    /// All other settings, with their name and value
+    pub other: Vec<(String, String)>,
+}
+
+impl WeightSettings {
+    /// Parse the settings from the description of a weight
+    ///
+    /// Words that are not of the form `name=value`, like `default`, are
+    /// ignored.
+    pub fn from_weight(weight: &lhef3::Weight) -> WeightSettings {
+        let mut settings = WeightSettings::default();
+        for word in weight.text.split_whitespace() {
+            let n = match word.find('=') {
+                Some(n) => n,
+                None => continue,
+            };
+            let (name, value) = (&word[..n], &word[n + 1..]);
+            let factor = slha::parse_f64(value);
+            let id = value.parse().ok();
+            match name {
+                "renscfact" if settings.renscfact.is_none() && factor.is_some() => {
+                    settings.renscfact = factor;
+                }
+                "facscfact" if settings.facscfact.is_none() && factor.is_some() => {
+                    settings.facscfact = factor;
+                }
+                "lhapdf" if settings.lhapdf.is_none() && id.is_some() => settings.lhapdf = id,
+                _ => settings.other.push((name.to_string(), value.to_string())),
+            }
+        }
+        settings
+    }
+}
+
+/// The additional information of an event from POWHEG-BOX
+///
+/// Everything apart from the `#rwgt`, `#pdf` and `#new weight` lines,
+/// e.g. an `<rwgt>` block, is kept as a string.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadLhe;
+/// use lhef::powheg::EventExtra;
+///
+/// let bytes = b"\
+/// ##rwgt            1          45  0.73415937412458E-01    12345678           3           0
+/// ##new weight,renfact,facfact,pdf1,pdf2  0.11E+02  0.200E+01  0.200E+01    13100    13100 lha
+/// ##new weight,renfact,facfact,pdf1,pdf2  0.92E+01  0.500E+00  0.500E+00    13100    13100 lha
+/// </event>";
+///
+/// let extra = EventExtra::read_lhe(bytes).to_full_result().unwrap();
+/// assert!(extra.pdf.is_none());
+/// assert_eq!(extra.rwgt.unwrap().rad_kinreg, 45);
+/// assert_eq!(extra.new_weights.len(), 2);
+/// assert_eq!(extra.new_weights[1].renfact, 0.5);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventExtra {
+    /// The information for reweighting in the `#rwgt` line
+    pub rwgt: Option<RwgtInfo>,
+    /// The parton densities in the `#pdf` line
+    pub pdf: Option<PdfInfo>,
+    /// The weights in the `#new weight` lines
+    pub new_weights: Vec<NewWeight>,
+    /// Any other additional event information
+    pub extra: String,
+}
+
+impl ReadLhe for EventExtra {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], EventExtra> {
+        map_opt!(input, take_until!("</event>"), |x| str::from_utf8(x)
+            .ok()
+            .and_then(split_event_extra))
+    }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        loop {
+            locator.skip_whitespace();
+            let remaining = locator.remaining();
+            if remaining.is_empty() || remaining.starts_with(b"</event>") {
+                return Ok(());
+            } else if remaining.starts_with(b"#rwgt") {
+                locator.object::<RwgtInfo>()?;
+            } else if remaining.starts_with(b"#pdf") {
+                locator.object::<PdfInfo>()?;
+            } else if remaining.starts_with(b"#new weight") {
+                locator.object::<NewWeight>()?;
+            } else {
+                let line_length = remaining.iter().take_while(|c| **c != b'\n').count();
+                locator.advance(line_length);
+            }
+        }
+    }
+}
+
+fn split_event_extra(content: &str) -> Option<EventExtra> {
+    let mut event_extra = EventExtra::default();
+    let mut extra = Vec::new();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("#rwgt") && event_extra.rwgt.is_none() {
+            event_extra.rwgt = Some(read_line(trimmed)?);
+        } else if trimmed.starts_with("#pdf") && event_extra.pdf.is_none() {
+            event_extra.pdf = Some(read_line(trimmed)?);
+        } else if trimmed.starts_with("#new weight") {
+            event_extra.new_weights.push(read_line(trimmed)?);
+        } else {
+            extra.push(line);
+        }
+    }
+    event_extra.extra = extra.join("\n").trim().to_string();
+    Some(event_extra)
+}
+
+/// Read a whole line with the parser of `T`
+fn read_line<T: ReadLhe>(line: &str) -> Option<T> {
+    // Terminate the line, so that the nom parsers don't return
+    // `Incomplete` at the end of the input
+    let line = format!("{}\n", line);
+    T::read_lhe(line.as_bytes()).to_full_result().ok()
+}
+
+impl WriteLhe for EventExtra {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if let Some(ref rwgt) = self.rwgt {
+            rwgt.write_lhe(writer)?;
+        }
+        if let Some(ref pdf) = self.pdf {
+            pdf.write_lhe(writer)?;
+        }
+        for new_weight in &self.new_weights {
+            new_weight.write_lhe(writer)?;
+        }
+        if !self.extra.is_empty() {
+            writeln!(writer, "{}", self.extra)?;
+        }
+        Ok(())
+    }
+}
+
+impl Downgrade for EventExtra {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        self.extra = downgrade_text(&self.extra, version, mode);
+    }
+}
+
+#[cfg(test)]
+impl Arbitrary for EventExtra {
+    fn arbitrary<G: Gen>(gen: &mut G) -> EventExtra {
+        let extra = ["", "# comment", "<rwgt>\n<wgt id='1'> 1.0 </wgt>\n</rwgt>"];
+        EventExtra {
+            rwgt: Arbitrary::arbitrary(gen),
+            pdf: Arbitrary::arbitrary(gen),
+            new_weights: Arbitrary::arbitrary(gen),
+            extra: gen.choose(&extra).unwrap().to_string(),
+        }
+    }
+}
+
+/// The information for reweighting an event, given in the `#rwgt` line
+///
+/// With the state of the random number generator, POWHEG-BOX can
+/// generate the radiation of the event again when reweighting it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RwgtInfo {
+    /// The type of the event, `1` for events from the `btilde`
+    /// function, `2` for remnant and `3` for regular events
+    pub rad_type: i64,
+    /// The kinematic region of the radiation
+    pub rad_kinreg: i64,
+    /// The weight of the underlying Born configuration
+    pub weight: f64,
+    /// The state of the random number generator before the event was
+    /// generated
+    pub random_state: RandomState,
+}
+
+impl ReadLhe for RwgtInfo {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], RwgtInfo> {
+        do_parse!(
+            input,
+            ws!(tag!("#rwgt")) >> rad_type: ws!(parse_i64) >> rad_kinreg: ws!(parse_i64)
+                >> weight: ws!(parse_f64) >> seed: ws!(parse_i64) >> n1: ws!(parse_i64)
+                >> n2: ws!(parse_i64) >> (RwgtInfo {
+                rad_type,
+                rad_kinreg,
+                weight,
+                random_state: RandomState { seed, n1, n2 },
+            })
+        )
+    }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#rwgt")?;
+        locator.field("rad_type", INTEGER, parse_i64)?;
+        locator.field("rad_kinreg", INTEGER, parse_i64)?;
+        locator.field("weight", FLOAT, parse_f64)?;
+        locator.field("random_state.seed", INTEGER, parse_i64)?;
+        locator.field("random_state.n1", INTEGER, parse_i64)?;
+        locator.field("random_state.n2", INTEGER, parse_i64)?;
+        Ok(())
+    }
+}
+
+impl WriteLhe for RwgtInfo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "#rwgt {} {} {:e} {} {} {}",
+            self.rad_type,
+            self.rad_kinreg,
+            self.weight,
+            self.random_state.seed,
+            self.random_state.n1,
+            self.random_state.n2
+        )
+    }
+}
+
+#[cfg(test)]
+impl Arbitrary for RwgtInfo {
+    fn arbitrary<G: Gen>(gen: &mut G) -> RwgtInfo {
+        RwgtInfo {
+            rad_type: Arbitrary::arbitrary(gen),
+            rad_kinreg: Arbitrary::arbitrary(gen),
+            weight: Arbitrary::arbitrary(gen),
+            random_state: Arbitrary::arbitrary(gen),
+        }
+    }
+}
+
+/// The state of the random number generator of POWHEG-BOX
+///
+/// The state consists of the seed and the two counters of the random
+/// numbers that were drawn since the generator was initialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RandomState {
+    /// The seed of the generator
+    pub seed: i64,
+    /// The number of random numbers drawn, modulo `10^9`
+    pub n1: i64,
+    /// The number of random numbers drawn, divided by `10^9`
+    pub n2: i64,
+}
+
+#[cfg(test)]
+impl Arbitrary for RandomState {
+    fn arbitrary<G: Gen>(gen: &mut G) -> RandomState {
+        RandomState {
+            seed: Arbitrary::arbitrary(gen),
+            n1: Arbitrary::arbitrary(gen),
+            n2: Arbitrary::arbitrary(gen),
+        }
+    }
+}
+
+/// The parton densities of an event, given in the `#pdf` line
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdfInfo {
+    /// The PDG id of the first incoming parton
+    pub id1: PdgId,
+    /// The PDG id of the second incoming parton
+    pub id2: PdgId,
+    /// The momentum fraction of the first incoming parton
+    pub x1: f64,
+    /// The momentum fraction of the second incoming parton
+    pub x2: f64,
+    /// The factorization scale
+    pub scale: f64,
+    /// The parton density of the first incoming parton, multiplied by
+    /// `x1`
+    pub xf1: f64,
+    /// The parton density of the second incoming parton, multiplied by
+    /// `x2`
+    pub xf2: f64,
+}
+
+impl ReadLhe for PdfInfo {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], PdfInfo> {
+        do_parse!(
+            input,
+            ws!(tag!("#pdf")) >> id1: ws!(parse_i64) >> id2: ws!(parse_i64) >> x1: ws!(parse_f64)
+                >> x2: ws!(parse_f64) >> scale: ws!(parse_f64) >> xf1: ws!(parse_f64)
+                >> xf2: ws!(parse_f64) >> (PdfInfo {
+                id1,
+                id2,
+                x1,
+                x2,
+                scale,
+                xf1,
+                xf2,
+            })
+        )
+    }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag("#pdf")?;
+        locator.field("id1", INTEGER, parse_i64)?;
+        locator.field("id2", INTEGER, parse_i64)?;
+        locator.field("x1", FLOAT, parse_f64)?;
+        locator.field("x2", FLOAT, parse_f64)?;
+        locator.field("scale", FLOAT, parse_f64)?;
+        locator.field("xf1", FLOAT, parse_f64)?;
+        locator.field("xf2", FLOAT, parse_f64)?;
+        Ok(())
+    }
+}
+
+impl WriteLhe for PdfInfo {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "#pdf {} {} {:e} {:e} {:e} {:e} {:e}",
+            self.id1, self.id2, self.x1, self.x2, self.scale, self.xf1, self.xf2
+        )
+    }
+}
+
+#[cfg(test)]
+impl Arbitrary for PdfInfo {
+    fn arbitrary<G: Gen>(gen: &mut G) -> PdfInfo {
+        PdfInfo {
+            id1: Arbitrary::arbitrary(gen),
+            id2: Arbitrary::arbitrary(gen),
+            x1: Arbitrary::arbitrary(gen),
+            x2: Arbitrary::arbitrary(gen),
+            scale: Arbitrary::arbitrary(gen),
+            xf1: Arbitrary::arbitrary(gen),
+            xf2: Arbitrary::arbitrary(gen),
+        }
+    }
+}
+
+/// A weight added by a reweighting run, given in a `#new weight` line
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewWeight {
+    /// The new weight of the event
+    pub weight: f64,
+    /// The factor of the renormalization scale
+    pub renfact: f64,
+    /// The factor of the factorization scale
+    pub facfact: f64,
+    /// The id of the PDF set of the first beam
+    pub pdf1: i64,
+    /// The id of the PDF set of the second beam
+    pub pdf2: i64,
+    /// The PDF package that was used, e.g. `lha` for LHAPDF or `mlm`
+    /// for the built-in PDFs
+    pub pdf_package: String,
+}
+
+const NEW_WEIGHT_TAG: &str = "#new weight,renfact,facfact,pdf1,pdf2";
+
+named!(
+    parse_word<String>,
+    map!(
+        map_res!(
+            take_while1!(|c| !nom::is_space(c) && c != b'\n' && c != b'\r'),
+            str::from_utf8
+        ),
+        str::to_string
+    )
+);
+
+impl ReadLhe for NewWeight {
+    fn read_lhe(input: &[u8]) -> nom::IResult<&[u8], NewWeight> {
+        do_parse!(
+            input,
+            ws!(tag!(NEW_WEIGHT_TAG)) >> weight: ws!(parse_f64) >> renfact: ws!(parse_f64)
+                >> facfact: ws!(parse_f64) >> pdf1: ws!(parse_i64) >> pdf2: ws!(parse_i64)
+                >> pdf_package: ws!(parse_word) >> (NewWeight {
+                weight,
+                renfact,
+                facfact,
+                pdf1,
+                pdf2,
+                pdf_package,
+            })
+        )
+    }
+
+    fn diagnose_lhe(locator: &mut ErrorLocator) -> Result<(), ParseError> {
+        locator.tag(NEW_WEIGHT_TAG)?;
+        locator.field("weight", FLOAT, parse_f64)?;
+        locator.field("renfact", FLOAT, parse_f64)?;
+        locator.field("facfact", FLOAT, parse_f64)?;
+        locator.field("pdf1", INTEGER, parse_i64)?;
+        locator.field("pdf2", INTEGER, parse_i64)?;
+        locator.field("pdf_package", "a word", parse_word)?;
+        Ok(())
+    }
+}
+
+impl WriteLhe for NewWeight {
+    fn write_lhe<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "{} {:e} {:e} {:e} {} {} {}",
+            NEW_WEIGHT_TAG,
+            self.weight,
+            self.renfact,
+            self.facfact,
+            self.pdf1,
+            self.pdf2,
+            self.pdf_package
+        )
+    }
+}
+
+#[cfg(test)]
+impl Arbitrary for NewWeight {
+    fn arbitrary<G: Gen>(gen: &mut G) -> NewWeight {
+        NewWeight {
+            weight: Arbitrary::arbitrary(gen),
+            renfact: Arbitrary::arbitrary(gen),
+            facfact: Arbitrary::arbitrary(gen),
+            pdf1: Arbitrary::arbitrary(gen),
+            pdf2: Arbitrary::arbitrary(gen),
+            pdf_package: gen.choose(&["lha", "mlm"]).unwrap().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck;
+    use std::str;
+
+    use {ReadError, ReadLhe, WriteLhe};
+    use super::*;
+    use error::Section;
+
+    macro_rules! roundtrip_qc {
+        ($name:ident, $ty:ident) => {
+            quickcheck! {
+                fn $name(start: $ty) -> quickcheck::TestResult {
+                    let mut bytes = Vec::new();
+                    start.write_lhe(&mut bytes).unwrap();
+                    bytes.extend_from_slice(b"</event>");
+                    let round = match $ty::read_lhe(&bytes).to_result() {
+                        Ok(r) => r,
+                        Err(err) => {
+                            println!("{}", str::from_utf8(&bytes).unwrap());
+                            panic!("Failed to read roundtrip: {:?}", err);
+                        },
+                    };
+                    if start == round {
+                        quickcheck::TestResult::passed()
+                    } else {
+                        println!("After: {:?}", round);
+                        quickcheck::TestResult::failed()
+                    }
+                }
+            }
+        };
+    }
+
+    roundtrip_qc!(rwgtinfo_roundtrip_qc, RwgtInfo);
+    roundtrip_qc!(pdfinfo_roundtrip_qc, PdfInfo);
+    roundtrip_qc!(newweight_roundtrip_qc, NewWeight);
+    roundtrip_qc!(eventextra_roundtrip_qc, EventExtra);
+
+    const FILE: &[u8] = b"\
+<LesHouchesEvents version=\"3.0\">
+<header>
+<initrwgt>
+<weightgroup name='scales' combine='envelope'>
+<weight id='1'> default </weight>
+<weight id='2'> renscfact=2d0 facscfact=2d0 </weight>
+</weightgroup>
+<weight id='3'> lhapdf=13100 renscfact=1d0 foo=bar </weight>
+</initrwgt>
+POWHEG-BOX input
+</header>
+<init>
+2212 2212 6.5e3 6.5e3 -1 -1 -1 -1 -4 1
+1.0e1 1.0e-1 1.0 10001
+</init>
+<event>
+4 10001 1.0e1 9.1e1 -1.0 1.18e-1
+21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+-2 -1 0 0 0 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+-11 1 1 2 0 0 0.5 0.0 0.0 0.5 0.0 0. 9.
+11 1 1 2 0 502 -0.5 0.0 0.0 0.5 0.0 0. 9.
+#pdf    21    -2  0.12E-01  0.34E-01  0.91E+02  0.56E+01  0.78E+00
+#rwgt            1          45  0.73415937412458E-01    12345678           3           0
+#new weight,renfact,facfact,pdf1,pdf2  0.11E+02  0.200E+01  0.200E+01    13100    13100 lha
+<rwgt>
+<wgt id='1'> 1.0e1 </wgt>
+<wgt id='2'> 1.1e1 </wgt>
+<wgt id='3'> 0.9e1 </wgt>
+</rwgt>
+</event>
+</LesHouchesEvents>
+";
+
+    #[test]
+    fn read_file() {
+        let lhe = LheFile::read_lhe(FILE).to_full_result().unwrap();
+
+        let initrwgt = lhe.header.initrwgt.as_ref().unwrap();
+        let settings: Vec<_> = initrwgt
+            .weights()
+            .map(WeightSettings::from_weight)
+            .collect();
+        assert_eq!(settings[0], WeightSettings::default());
+        assert_eq!(settings[1].renscfact, Some(2.));
+        assert_eq!(settings[1].facscfact, Some(2.));
+        assert_eq!(
+            settings[2],
+            WeightSettings {
+                renscfact: Some(1.),
+                facscfact: None,
+                lhapdf: Some(13100),
+                other: vec![("foo".to_string(), "bar".to_string())],
+            }
+        );
+
+        let extra = &lhe.events[0].extra;
+        assert_eq!(
+            extra.pdf,
+            Some(PdfInfo {
+                id1: 21,
+                id2: -2,
+                x1: 0.012,
+                x2: 0.034,
+                scale: 91.,
+                xf1: 5.6,
+                xf2: 0.78,
+            })
+        );
+        assert_eq!(
+            extra.rwgt,
+            Some(RwgtInfo {
+                rad_type: 1,
+                rad_kinreg: 45,
+                weight: 0.73415937412458E-01,
+                random_state: RandomState {
+                    seed: 12345678,
+                    n1: 3,
+                    n2: 0,
+                },
+            })
+        );
+        assert_eq!(
+            extra.new_weights,
+            vec![NewWeight {
+                weight: 11.,
+                renfact: 2.,
+                facfact: 2.,
+                pdf1: 13100,
+                pdf2: 13100,
+                pdf_package: "lha".to_string(),
+            }]
+        );
+        assert!(extra.extra.starts_with("<rwgt>"));
+        assert!(extra.extra.ends_with("</rwgt>"));
+
+        let mut bytes = Vec::new();
+        lhe.write_lhe(&mut bytes).unwrap();
+        assert_eq!(LheFile::read_lhe(&bytes).to_full_result(), Ok(lhe));
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let lines: &[&[u8]] = &[
+            b"#rwgt 1 45 0.7E-01 12345678 3\n</event>",
+            b"#pdf 21 -2 0.12E-01 0.34E-01 0.91E+02 0.56E+01 x\n</event>",
+            b"#new weight,renfact,facfact,pdf1,pdf2 0.11E+02 2 2 13100\n</event>",
+        ];
+        for bytes in lines {
+            assert!(EventExtra::read_lhe(bytes).to_full_result().is_err());
+        }
+    }
+
+    #[test]
+    fn diagnose_invalid_line() {
+        let file = str::from_utf8(FILE)
+            .unwrap()
+            .replace("0.200E+01    13100", "0.200E+01    x");
+        match LheFile::read_lhe_from_bytes(file.as_bytes()) {
+            Err(ReadError::Parse(err)) => {
+                assert_eq!(err.line, 24);
+                assert_eq!(err.section, Section::EventExtra(0));
+                assert_eq!(err.field, Some("pdf1".to_string()));
+            }
+            _ => panic!("Expected a parse error"),
+        }
+    }
+}
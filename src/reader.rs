@@ -0,0 +1,895 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Read events from lhe files one at a time
+//!
+//! Reading a file with `ReadLhe::read_lhe_from_file` keeps the whole
+//! file and all events in memory.
+//! The [`LheReader`] in this module only reads the start of the file,
+//! i.e. the comment, the header and the init section, up front and
+//! then reads the events one by one, so that only a single event has
+//! to be kept in memory at any time.
+//! Events in an `<eventgroup>` are read together, so that the whole
+//! group is kept in memory until all of its events were returned.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::reader::LheReader;
+//! use lhef::plain::{Comment, Header, InitExtra, EventExtra};
+//!
+//! let reader: LheReader<_, Comment, Header, InitExtra, EventExtra> =
+//!     LheReader::from_file(&"events.lhe").unwrap();
+//!
+//! // Energy of beam 1
+//! let beam_1_energy = reader.init().beam_1_energy;
+//!
+//! for event in reader {
+//!     let event = event.unwrap();
+//!     // pz of the 4th particle in the event
+//!     let pz = event.particles[3].momentum.pz;
+//! }
+//! ```
+//!
+//! [`LheReader`]: struct.LheReader.html
+
+use attributes::Attributes;
+use compression;
+use error::{ErrorLocator, Section};
+use generic::{locate_file_start, read_closing_file_tag, read_entry, read_file_start,
+              skip_comments, CommentPosition, Entry, EventGeneric, EventGroup, EventItem,
+              FileComment, InitGeneric};
+use version::LhefVersion;
+use ReadError;
+use ReadLhe;
+
+use nom;
+use std::collections::VecDeque;
+use std::io;
+use std::io::BufRead;
+use std::mem;
+use std::path::Path;
+
+/// A reader for the events in an lhe file
+///
+/// The comment, the header and the init section are read when the
+/// reader is created and can be accessed using the corresponding
+/// methods.
+/// The events are then read lazily, by iterating over the reader.
+/// The iterator stops after the closing `</LesHouchesEvents>` tag.
+/// If an error is encountered, it is returned and the iteration stops.
+///
+/// The iterator returns the events in `<eventgroup>` blocks like any
+/// other event, the group of the last returned event is available from
+/// `group`.
+/// To read the groups as a whole, use `items` instead.
+///
+/// Comments before the first event are read when the reader is created,
+/// comments between the events and inside of event groups are read
+/// together with the following event or group.
+/// All of them are kept and available from `comments`, or can be
+/// removed with `take_comments` to avoid keeping them in memory.
+///
+/// The types used for the comment, the header and the additional
+/// information are the same ones that are used in `LheFileGeneric`, so
+/// the types from the [`plain`], [`string`] and [`helac`] modules can
+/// all be used.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::reader::LheReader;
+/// use lhef::string::{Comment, Header, InitExtra, EventExtra};
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"1.0\">
+/// <!-- Process: e+ e- > mu+ mu- -->
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 4 1 +1.04e-01 1.00e+03 7.54e-03 8.68e-02
+/// -11 -1 0 0 0 0 +0.00e+00 +0.00e+00 +5.00e+02 5.00e+02 0.00e+00 0.00e+00 -1.00e+00
+///  11 -1 0 0 0 0 -0.00e+00 -0.00e+00 -5.00e+02 5.00e+02 0.00e+00 0.00e+00  1.00e+00
+/// -13  1 1 2 0 0 -1.97e+02 -4.52e+02 -7.94e+01 5.00e+02 0.00e+00 0.00e+00 -1.00e+00
+///  13  1 1 2 0 0 +1.97e+02 +4.52e+02 +7.94e+01 5.00e+02 0.00e+00 0.00e+00  1.00e+00
+/// ## Additional event information
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let reader: LheReader<_, Comment, Header, InitExtra, EventExtra> =
+///     LheReader::new(&bytes[..]).unwrap();
+/// assert_eq!(reader.version().as_str(), "1.0");
+/// assert_eq!(reader.init().beam_1_id, 2212);
+///
+/// let events: Vec<_> = reader.map(|e| e.unwrap()).collect();
+/// assert_eq!(events.len(), 1);
+/// assert_eq!(events[0].particles[3].pdg_id, 13);
+/// let EventExtra(ref extra) = events[0].extra;
+/// assert_eq!(extra, &"# Additional event information".to_string());
+/// ```
+///
+/// [`plain`]: ../plain/index.html
+/// [`string`]: ../string/index.html
+/// [`helac`]: ../helac/index.html
+#[derive(Debug)]
+pub struct LheReader<R, Comment, Header, InitExtra, EventExtra> {
+    reader: R,
+    buffer: Vec<u8>,
+    version: LhefVersion,
+    attributes: Attributes,
+    comment: Comment,
+    header: Header,
+    init: InitGeneric<InitExtra>,
+    comments: Vec<FileComment<Comment>>,
+    finished: bool,
+    offset: usize,
+    line: usize,
+    events_read: usize,
+    groups_read: usize,
+    group: Option<Attributes>,
+    pending: VecDeque<EventGeneric<EventExtra>>,
+}
+
+impl<R, Comment, Header, InitExtra, EventExtra> LheReader<R, Comment, Header, InitExtra, EventExtra>
+where
+    R: BufRead,
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    /// Create a new reader
+    ///
+    /// This reads the opening tag of the file, the comment, the header
+    /// and the init section from `reader`.
+    pub fn new(
+        mut reader: R,
+    ) -> Result<LheReader<R, Comment, Header, InitExtra, EventExtra>, ReadError> {
+        let mut buffer = Vec::new();
+        let starts_events = |line: &[u8]| {
+            let line = trim_start(line);
+            line.starts_with(b"<event") || line.starts_with(b"</LesHouchesEvents>")
+        };
+        let start_error =
+            |buffer: &[u8]| ReadError::Parse(locate_file_start::<Comment, Header, InitExtra>(buffer));
+        if !read_lines_until(&mut reader, &mut buffer, starts_events)? {
+            return Err(start_error(&buffer));
+        }
+        let (consumed, start) = loop {
+            match read_file_start(&buffer) {
+                nom::IResult::Done(rest, start) => break (buffer.len() - rest.len(), start),
+                nom::IResult::Error(_) => return Err(start_error(&buffer)),
+                nom::IResult::Incomplete(_) => {
+                    if !read_line(&mut reader, &mut buffer)? {
+                        return Err(start_error(&buffer));
+                    }
+                }
+            }
+        };
+        let line = 1 + count_lines(&buffer[..consumed]);
+        buffer.drain(..consumed);
+        let (version, attributes, comment, header, init, comments) = start;
+        Ok(LheReader {
+            reader,
+            buffer,
+            version,
+            attributes,
+            comment,
+            header,
+            init,
+            comments,
+            finished: false,
+            offset: consumed,
+            line,
+            events_read: 0,
+            groups_read: 0,
+            group: None,
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl<Comment, Header, InitExtra, EventExtra>
+    LheReader<compression::Reader, Comment, Header, InitExtra, EventExtra>
+where
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    /// Create a new reader for the file at `path`
+    ///
+    /// Compressed files are decompressed on the fly, see the
+    /// [`compression`] module.
+    ///
+    /// [`compression`]: ../compression/index.html
+    pub fn from_file<P: AsRef<Path>>(
+        path: &P,
+    ) -> Result<LheReader<compression::Reader, Comment, Header, InitExtra, EventExtra>, ReadError>
+    {
+        LheReader::new(compression::open(path)?)
+    }
+}
+
+impl<R, Comment, Header, InitExtra, EventExtra>
+    LheReader<R, Comment, Header, InitExtra, EventExtra>
+{
+    /// The version contained in the opening tag of the lhe file
+    pub fn version(&self) -> &LhefVersion {
+        &self.version
+    }
+
+    /// Any other attributes of the opening tag of the lhe file
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    /// The comment in the lhe file
+    pub fn comment(&self) -> &Comment {
+        &self.comment
+    }
+
+    /// The header section of the lhe file
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// The init section of the lhe file
+    pub fn init(&self) -> &InitGeneric<InitExtra> {
+        &self.init
+    }
+
+    /// All further comments in the lhe file that were read so far
+    ///
+    /// This includes the comments before the first event and the
+    /// comments between the events and inside of the event groups that
+    /// were already returned, with their positions as in
+    /// `LheFileGeneric::comments`.
+    pub fn comments(&self) -> &[FileComment<Comment>] {
+        &self.comments
+    }
+
+    /// Remove the comments that were read so far and return them
+    pub fn take_comments(&mut self) -> Vec<FileComment<Comment>> {
+        mem::take(&mut self.comments)
+    }
+
+    /// The attributes of the `<eventgroup>` that the event that was last
+    /// returned by the iterator belongs to, if any
+    pub fn group(&self) -> Option<&Attributes> {
+        self.group.as_ref()
+    }
+
+    /// Turn the reader into an iterator over single events and whole
+    /// event groups
+    ///
+    /// If the reader already returned some of the events of a group,
+    /// the remaining events are returned as a group with the same
+    /// attributes first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lhef::generic::EventItem;
+    /// use lhef::reader::LheReader;
+    /// use lhef::plain::{Comment, Header, InitExtra, EventExtra};
+    ///
+    /// let bytes = b"\
+    /// <LesHouchesEvents version=\"3.0\">
+    /// <init>
+    /// 2212 2212 6500 6500 0 0 13100 13100 3 1
+    /// 2.1 3.2E-03 1.0E+00 1
+    /// </init>
+    /// <eventgroup nreal=\"1\" ncounter=\"1\">
+    /// <event>
+    /// 0 1 1.0 91.2 0.0078 0.118
+    /// </event>
+    /// <event>
+    /// 0 1 -1.0 91.2 0.0078 0.118
+    /// </event>
+    /// </eventgroup>
+    /// <event>
+    /// 0 1 2.0 91.2 0.0078 0.118
+    /// </event>
+    /// </LesHouchesEvents>";
+    ///
+    /// let reader: LheReader<_, Comment, Header, InitExtra, EventExtra> =
+    ///     LheReader::new(&bytes[..]).unwrap();
+    /// let items: Vec<_> = reader.items().map(|item| item.unwrap()).collect();
+    /// assert_eq!(items.len(), 2);
+    /// match items[0] {
+    ///     EventItem::Group(ref group) => assert_eq!(group.n_counter(), Some(1)),
+    ///     EventItem::Event(_) => panic!("expected an event group"),
+    /// }
+    /// assert_eq!(items[1].events()[0].weight, 2.0);
+    /// ```
+    pub fn items(self) -> EventItems<R, Comment, Header, InitExtra, EventExtra> {
+        EventItems { reader: self }
+    }
+}
+
+impl<R, Comment, Header, InitExtra, EventExtra> Iterator
+    for LheReader<R, Comment, Header, InitExtra, EventExtra>
+where
+    R: BufRead,
+    Comment: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    type Item = Result<EventGeneric<EventExtra>, ReadError>;
+
+    fn next(&mut self) -> Option<Result<EventGeneric<EventExtra>, ReadError>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            match self.next_item()? {
+                Ok(EventItem::Event(event)) => {
+                    self.group = None;
+                    return Some(Ok(event));
+                }
+                Ok(EventItem::Group(group)) => {
+                    self.group = Some(group.attributes);
+                    self.pending.extend(group.events);
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// An iterator over the single events and the event groups in an lhe
+/// file
+///
+/// This is created by `LheReader::items`.
+#[derive(Debug)]
+pub struct EventItems<R, Comment, Header, InitExtra, EventExtra> {
+    reader: LheReader<R, Comment, Header, InitExtra, EventExtra>,
+}
+
+impl<R, Comment, Header, InitExtra, EventExtra> EventItems<R, Comment, Header, InitExtra, EventExtra> {
+    /// The underlying reader
+    pub fn reader(&self) -> &LheReader<R, Comment, Header, InitExtra, EventExtra> {
+        &self.reader
+    }
+}
+
+impl<R, Comment, Header, InitExtra, EventExtra> Iterator
+    for EventItems<R, Comment, Header, InitExtra, EventExtra>
+where
+    R: BufRead,
+    Comment: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    type Item = Result<EventItem<EventExtra>, ReadError>;
+
+    fn next(&mut self) -> Option<Result<EventItem<EventExtra>, ReadError>> {
+        let reader = &mut self.reader;
+        if !reader.pending.is_empty() {
+            return Some(Ok(EventItem::Group(EventGroup {
+                attributes: reader.group.clone().unwrap_or_default(),
+                events: reader.pending.drain(..).collect(),
+            })));
+        }
+        let item = reader.next_item();
+        reader.group = match item {
+            Some(Ok(EventItem::Group(ref group))) => Some(group.attributes.clone()),
+            _ => None,
+        };
+        item
+    }
+}
+
+impl<R, Comment, Header, InitExtra, EventExtra> LheReader<R, Comment, Header, InitExtra, EventExtra>
+where
+    R: BufRead,
+    Comment: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    fn next_item(&mut self) -> Option<Result<EventItem<EventExtra>, ReadError>> {
+        if self.finished {
+            return None;
+        }
+        let result = self.read_item();
+        if let Some(Ok(_)) = result {
+            return result;
+        }
+        self.finished = true;
+        result
+    }
+
+    fn read_item(&mut self) -> Option<Result<EventItem<EventExtra>, ReadError>> {
+        let ends_event =
+            |line: &[u8]| contains(line, b"</event>") || contains(line, b"</LesHouchesEvents>");
+        if !ends_event(&self.buffer) {
+            match read_lines_until(&mut self.reader, &mut self.buffer, ends_event) {
+                Ok(true) => {}
+                Ok(false) => return Some(Err(self.event_error())),
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+        loop {
+            if let nom::IResult::Done(_, _) = read_closing_file_tag(&self.buffer) {
+                return None;
+            }
+            let (consumed, entry) = match read_entry(&self.buffer) {
+                nom::IResult::Done(rest, entry) => (self.buffer.len() - rest.len(), entry),
+                nom::IResult::Error(_) => return Some(Err(self.event_error())),
+                nom::IResult::Incomplete(_) => {
+                    if let Err(err) = self.read_more() {
+                        return Some(Err(err));
+                    }
+                    continue;
+                }
+            };
+            self.offset += consumed;
+            self.line += count_lines(&self.buffer[..consumed]);
+            self.buffer.drain(..consumed);
+            let item = match entry {
+                Entry::Comment(comment) => {
+                    self.comments.push(FileComment {
+                        position: CommentPosition::BeforeEvent(self.events_read),
+                        comment,
+                    });
+                    continue;
+                }
+                Entry::Event(event) => EventItem::Event(event),
+                Entry::Group(group, comments) => {
+                    let (index, start) = (self.groups_read, self.events_read);
+                    self.comments
+                        .extend(comments.into_iter().map(|(n, comment)| FileComment {
+                            position: CommentPosition::InGroup(index, start + n),
+                            comment,
+                        }));
+                    self.groups_read += 1;
+                    EventItem::Group(group)
+                }
+            };
+            self.events_read += item.events().len();
+            return Some(Ok(item));
+        }
+    }
+
+    /// Append another line to the buffered input of the current event
+    fn read_more(&mut self) -> Result<(), ReadError> {
+        match read_line(&mut self.reader, &mut self.buffer) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(self.event_error()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Find the first error in the buffered input of the current event
+    fn event_error(&self) -> ReadError {
+        let mut locator = ErrorLocator::with_origin(
+            &self.buffer,
+            self.offset,
+            self.line,
+            Section::Event(self.events_read),
+        );
+        locator.skip_parsed(skip_comments);
+        locator.skip_whitespace();
+        let err = if locator.remaining().starts_with(b"<event") {
+            match locator.object::<EventItem<EventExtra>>() {
+                Err(err) => err,
+                Ok(()) => locator.error(None, "valid input"),
+            }
+        } else {
+            locator.set_section(Section::File);
+            locator.error(None, "`<event>`, `<eventgroup>` or `</LesHouchesEvents>`")
+        };
+        ReadError::Parse(err)
+    }
+}
+
+pub(crate) fn count_lines(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|c| **c == b'\n').count()
+}
+
+/// Append a single line to `buffer`, returning `false` at the end of the input
+fn read_line<R: BufRead>(reader: &mut R, buffer: &mut Vec<u8>) -> io::Result<bool> {
+    reader.read_until(b'\n', buffer).map(|n| n > 0)
+}
+
+/// Append lines to `buffer` until a line for which `is_last` is true was read
+///
+/// Returns `false` if the end of the input was reached before.
+fn read_lines_until<R, F>(reader: &mut R, buffer: &mut Vec<u8>, is_last: F) -> io::Result<bool>
+where
+    R: BufRead,
+    F: Fn(&[u8]) -> bool,
+{
+    loop {
+        let start = buffer.len();
+        if !read_line(reader, buffer)? {
+            return Ok(false);
+        }
+        if is_last(&buffer[start..]) {
+            return Ok(true);
+        }
+    }
+}
+
+pub(crate) fn trim_start(line: &[u8]) -> &[u8] {
+    let start = line
+        .iter()
+        .position(|c| !nom::is_space(*c) && *c != b'\r' && *c != b'\n')
+        .unwrap_or(line.len());
+    &line[start..]
+}
+
+pub(crate) fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use error::Section;
+    use generic::{CommentPosition, EventGeneric, LheFileGeneric};
+    use helac;
+    use plain;
+    use string;
+    use ReadError;
+    use ReadLhe;
+
+    use super::LheReader;
+
+    macro_rules! reader_matches_file {
+        ($name:ident, $file_name:expr, $comment:ty, $header:ty, $init:ty, $event:ty) => {
+            #[test]
+            fn $name() {
+                let lhe: LheFileGeneric<$comment, $header, $init, $event> =
+                    LheFileGeneric::read_lhe_from_file(&$file_name).unwrap();
+                let reader: LheReader<_, $comment, $header, $init, $event> =
+                    LheReader::from_file(&$file_name).unwrap();
+                assert_eq!(reader.version(), &lhe.version);
+                assert_eq!(reader.comment(), &lhe.comment);
+                assert_eq!(reader.header(), &lhe.header);
+                assert_eq!(reader.init(), &lhe.init);
+                let events: Vec<_> = reader.map(|e| e.unwrap()).collect();
+                assert_eq!(events, lhe.events);
+            }
+        };
+    }
+
+    const SAMPLE_FILES: &[&str] = &[
+        "tests/real_world_files/helac_1loop_tree.lhe",
+        "tests/real_world_files/helac_1loop_virt.lhe",
+        "tests/real_world_files/helac_dipoles_i.lhe",
+        "tests/real_world_files/helac_dipoles_kp.lhe",
+        "tests/real_world_files/helac_dipoles_rs.lhe",
+        "tests/real_world_files/mg5_aMC.lhe",
+        "tests/real_world_files/mg5_aMC_NLO.lhe",
+        "tests/real_world_files/mg5_aMC_NLO_rwgt.lhe",
+    ];
+
+    #[test]
+    fn read_sample_files_plain() {
+        for file_name in SAMPLE_FILES {
+            let lhe = plain::LheFile::read_lhe_from_file(file_name).unwrap();
+            let reader: LheReader<_, plain::Comment, plain::Header, plain::InitExtra, _> =
+                LheReader::from_file(file_name).unwrap();
+            let events: Vec<_> = reader.map(|e| e.unwrap()).collect();
+            assert_eq!(events, lhe.events);
+        }
+    }
+
+    #[test]
+    fn read_sample_files_string() {
+        for file_name in SAMPLE_FILES {
+            let lhe = string::LheFile::read_lhe_from_file(file_name).unwrap();
+            let reader: LheReader<_, string::Comment, string::Header, string::InitExtra, _> =
+                LheReader::from_file(file_name).unwrap();
+            assert_eq!(reader.header(), &lhe.header);
+            assert_eq!(reader.init(), &lhe.init);
+            let events: Vec<_> = reader.map(|e| e.unwrap()).collect();
+            assert_eq!(events, lhe.events);
+        }
+    }
+
+    reader_matches_file!(
+        read_rs,
+        "tests/real_world_files/helac_dipoles_rs.lhe",
+        helac::Comment,
+        helac::Header,
+        helac::InitExtraRS,
+        helac::EventExtraRS
+    );
+    reader_matches_file!(
+        read_i,
+        "tests/real_world_files/helac_dipoles_i.lhe",
+        helac::Comment,
+        helac::Header,
+        helac::PdfSum,
+        helac::EventExtraI
+    );
+    reader_matches_file!(
+        read_kp,
+        "tests/real_world_files/helac_dipoles_kp.lhe",
+        helac::Comment,
+        helac::Header,
+        helac::PdfSumKP,
+        helac::EventExtraKP
+    );
+    reader_matches_file!(
+        read_1loop,
+        "tests/real_world_files/helac_1loop_virt.lhe",
+        helac::Comment,
+        helac::Header,
+        helac::InitExtra1loop,
+        helac::EventExtra1loop
+    );
+
+    #[test]
+    fn read_header_after_init() {
+        let bytes = b"\
+<LesHouchesEvents version=\"1.0\">
+<init>
+52 61 54. 55. 56 57 58 59 60 0
+</init>
+<header>
+header line 1
+</header>
+<event>
+1 1 3. 4. 5. 6.
+7 8 9 10 11 12 13. 14. 15. 16. 17. 18. 19.
+</event>
+</LesHouchesEvents>";
+        let lhe = string::LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let reader: LheReader<_, string::Comment, string::Header, string::InitExtra, _> =
+            LheReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.header(), &lhe.header);
+        let events: Vec<EventGeneric<string::EventExtra>> = reader.map(|e| e.unwrap()).collect();
+        assert_eq!(events, lhe.events);
+    }
+
+    #[test]
+    fn read_comments() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<!-- first -->
+<!-- second -->
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<event>
+0 1 1.0 91.2 0.0078 0.118
+</event>
+<!-- between
+     events -->
+<eventgroup nreal=\"1\" ncounter=\"1\">
+<!-- in group -->
+<event>
+0 1 2.0 91.2 0.0078 0.118
+</event>
+<event>
+0 1 -2.0 91.2 0.0078 0.118
+</event>
+</eventgroup>
+<!-- at the end -->
+</LesHouchesEvents>
+";
+        let lhe = string::LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let mut reader: LheReader<_, string::Comment, string::Header, string::InitExtra, _> =
+            LheReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.comment(), &lhe.comment);
+        assert_eq!(reader.comments(), &lhe.comments[..1]);
+        let events: Vec<EventGeneric<string::EventExtra>> =
+            reader.by_ref().map(|e| e.unwrap()).collect();
+        assert_eq!(events, lhe.events);
+        assert_eq!(reader.comments(), &lhe.comments[..]);
+        assert_eq!(
+            reader.comments()[2].position,
+            CommentPosition::InGroup(0, 1)
+        );
+        assert_eq!(reader.take_comments().len(), 4);
+        assert!(reader.comments().is_empty());
+    }
+
+    #[test]
+    fn read_no_events() {
+        let bytes = b"\
+<LesHouchesEvents version=\"1.0\">
+<init>
+52 61 54. 55. 56 57 58 59 60 0
+</init>
+</LesHouchesEvents>";
+        let reader: LheReader<
+            _,
+            plain::Comment,
+            plain::Header,
+            plain::InitExtra,
+            plain::EventExtra,
+        > = LheReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.count(), 0);
+    }
+
+    #[test]
+    fn read_truncated() {
+        let bytes = b"\
+<LesHouchesEvents version=\"1.0\">
+<init>
+52 61 54. 55. 56 57 58 59 60 0
+</init>
+<event>
+1 1 3. 4. 5. 6.
+7 8 9 10 11 12 13. 14. 15. 16. 17. 18. 19.
+</event>
+<event>
+1 1 3. 4. 5. 6.
+";
+        let mut reader: LheReader<
+            _,
+            plain::Comment,
+            plain::Header,
+            plain::InitExtra,
+            plain::EventExtra,
+        > = LheReader::new(&bytes[..]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn error_position() {
+        let bytes = b"\
+<LesHouchesEvents version=\"1.0\">
+<init>
+52 61 54. 55. 56 57 58 59 60 0
+</init>
+<event>
+1 1 3. 4. 5. 6.
+7 8 9 10 11 12 13. 14. 15. 16. 17. 18. 19.
+</event>
+<event>
+2 1 3. 4. 5. 6.
+7 8 9 10 11 12 13. 14. 15. 16. 17. 18. 19.
+7 8 9 10 11 12 13. 14. 15. 16. 17. 18.x 19.
+</event>
+</LesHouchesEvents>";
+        let expected = match plain::LheFile::read_lhe_from_bytes(bytes) {
+            Err(ReadError::Parse(err)) => err,
+            _ => panic!("Expected a parse error"),
+        };
+        let mut reader: LheReader<
+            _,
+            plain::Comment,
+            plain::Header,
+            plain::InitExtra,
+            plain::EventExtra,
+        > = LheReader::new(&bytes[..]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        match reader.next() {
+            Some(Err(ReadError::Parse(err))) => assert_eq!(err, expected),
+            _ => panic!("Expected a parse error"),
+        }
+        assert_eq!(expected.line, 12);
+        assert_eq!(expected.field, Some("VTIMUP".to_string()));
+    }
+
+    #[test]
+    fn read_event_groups() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+52 61 54. 55. 56 57 58 59 60 0
+</init>
+<event>
+0 1 1. 4. 5. 6.
+</event>
+<eventgroup nreal=\"1\" ncounter=\"1\">
+<event>
+0 1 2. 4. 5. 6.
+</event>
+<event>
+0 1 3. 4. 5. 6.
+</event>
+</eventgroup>
+<eventgroup>
+</eventgroup>
+<event>
+0 1 4. 4. 5. 6.
+</event>
+</LesHouchesEvents>";
+        let lhe = plain::LheFile::read_lhe(bytes).to_full_result().unwrap();
+        let mut reader: LheReader<_, plain::Comment, plain::Header, plain::InitExtra, _> =
+            LheReader::new(&bytes[..]).unwrap();
+        let mut events = Vec::new();
+        let mut in_group = Vec::new();
+        while let Some(event) = reader.next() {
+            events.push(event.unwrap());
+            in_group.push(reader.group().is_some());
+        }
+        assert_eq!(events, lhe.events);
+        assert_eq!(in_group, vec![false, true, true, false]);
+
+        let mut reader: LheReader<_, plain::Comment, plain::Header, plain::InitExtra, _> =
+            LheReader::new(&bytes[..]).unwrap();
+        reader.next().unwrap().unwrap();
+        reader.next().unwrap().unwrap();
+        let items: Vec<_> = reader.items().map(|item| item.unwrap()).collect();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].events(), &lhe.events[2..3]);
+        assert_eq!(items[0].group_attributes(), Some(&lhe.groups[0].attributes));
+        assert_eq!(items[1].events().len(), 0);
+        assert_eq!(items[2].group_attributes(), None);
+    }
+
+    #[test]
+    fn error_in_event_group() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+52 61 54. 55. 56 57 58 59 60 0
+</init>
+<event>
+0 1 1. 4. 5. 6.
+</event>
+<eventgroup>
+<event>
+0 1 2. 4. 5. 6.
+</event>
+<event>
+0 1 3. x 5. 6.
+</event>
+</eventgroup>
+</LesHouchesEvents>";
+        let expected = match plain::LheFile::read_lhe_from_bytes(bytes) {
+            Err(ReadError::Parse(err)) => err,
+            _ => panic!("Expected a parse error"),
+        };
+        let mut reader: LheReader<
+            _,
+            plain::Comment,
+            plain::Header,
+            plain::InitExtra,
+            plain::EventExtra,
+        > = LheReader::new(&bytes[..]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        match reader.next() {
+            Some(Err(ReadError::Parse(err))) => assert_eq!(err, expected),
+            _ => panic!("Expected a parse error"),
+        }
+        assert!(reader.next().is_none());
+        assert_eq!(expected.line, 13);
+        assert_eq!(expected.section, Section::Event(2));
+        assert_eq!(expected.field, Some("SCALUP".to_string()));
+    }
+
+    #[test]
+    fn read_unterminated_comment() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+52 61 54. 55. 56 57 58 59 60 0
+</init>
+<event>
+0 1 1. 4. 5. 6.
+</event>
+<!-- oops
+<event>
+0 1 2. 4. 5. 6.
+</event>
+</LesHouchesEvents>";
+        let mut reader: LheReader<
+            _,
+            plain::Comment,
+            plain::Header,
+            plain::InitExtra,
+            plain::EventExtra,
+        > = LheReader::new(&bytes[..]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        match reader.next() {
+            Some(Err(ReadError::Parse(err))) => assert_eq!(err.line, 8),
+            _ => panic!("Expected a parse error"),
+        }
+        assert!(reader.next().is_none());
+    }
+}
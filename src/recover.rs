@@ -0,0 +1,511 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Recover the events from truncated lhe files
+//!
+//! When a generator is killed while it writes an event file, the file
+//! lacks the closing `</LesHouchesEvents>` tag and may end in the middle
+//! of an event.
+//! `ReadLhe::read_lhe` fails for such files, while the functions in this
+//! module return the start of the file and all complete events, together
+//! with a [`RecoveryReport`] that describes where and why reading
+//! stopped.
+//! Since the events in an `<eventgroup>` are correlated, a group is only
+//! kept if it is complete.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::recover;
+//! use lhef::plain::LheFile;
+//!
+//! let (lhe, report): (LheFile, _) = recover::read_lhe_from_file(&"events.lhe").unwrap();
+//! if !report.is_complete() {
+//!     println!("{}", report);
+//!     recover::repair_file(&"events.lhe", &"events_repaired.lhe").unwrap();
+//! }
+//! ```
+//!
+//! [`RecoveryReport`]: struct.RecoveryReport.html
+
+use compression;
+use generic::{
+    locate_file_start, read_closing_file_tag, read_entry, read_file_start, write_closing_file_tag,
+    FileStart, LheFileGeneric,
+};
+use plain;
+use reader::{contains, trim_start};
+use ReadError;
+use ReadLhe;
+
+use nom;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// The reason why reading a file stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The file is complete
+    Complete,
+    /// All events are complete, but the closing tag of the file is missing
+    MissingClosingTag,
+    /// The file ends in the middle of an event
+    TruncatedEvent,
+    /// An event is complete, but could not be parsed
+    InvalidEvent,
+    /// There is something other than an event or the closing tag of the file
+    UnexpectedContent,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match *self {
+            StopReason::Complete => "the file is complete",
+            StopReason::MissingClosingTag => "the closing tag of the file is missing",
+            StopReason::TruncatedEvent => "the file ends in the middle of an event",
+            StopReason::InvalidEvent => "an event could not be parsed",
+            StopReason::UnexpectedContent => "unexpected content after the last event",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// A description of where and why reading a file stopped
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// The reason why reading stopped
+    pub reason: StopReason,
+    /// The number of events that were read
+    pub events: usize,
+    /// The byte offset in the file at which reading stopped
+    pub offset: usize,
+    /// The line in the file at which reading stopped, counting from one
+    pub line: usize,
+}
+
+impl RecoveryReport {
+    /// Check whether the whole file could be read
+    pub fn is_complete(&self) -> bool {
+        self.reason == StopReason::Complete
+    }
+}
+
+impl fmt::Display for RecoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "reading stopped at line {} (byte {}) after {} events: {}",
+            self.line, self.offset, self.events, self.reason
+        )
+    }
+}
+
+/// A file with all events before the point where it is broken, and the
+/// report where and why reading stopped
+pub type Recovered<Comment, Header, InitExtra, EventExtra> = (
+    LheFileGeneric<Comment, Header, InitExtra, EventExtra>,
+    RecoveryReport,
+);
+
+/// Read an lhe file, keeping all events before the point where it is
+/// broken
+///
+/// An error is only returned if the start of the file, i.e. the
+/// opening tag, the comment, the header and the init section, can not be
+/// read.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::recover::{self, StopReason};
+/// use lhef::plain::LheFile;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"1.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// </event>
+/// <event>
+/// 2 1 2.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 2.0 2.0 0.0 0. 9.
+/// 21 -1 0 0 502";
+///
+/// let (lhe, report): (LheFile, _) = recover::read_lhe(bytes).unwrap();
+/// assert_eq!(lhe.events.len(), 1);
+/// assert_eq!(report.reason, StopReason::TruncatedEvent);
+/// assert_eq!(report.line, 11);
+/// ```
+pub fn read_lhe<Comment, Header, InitExtra, EventExtra>(
+    input: &[u8],
+) -> Result<Recovered<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    read_events(input).map(|(recovered, _)| recovered)
+}
+
+/// A recovered file and the offset of the end of its last complete event
+type RecoveredWithEnd<Comment, Header, InitExtra, EventExtra> =
+    (Recovered<Comment, Header, InitExtra, EventExtra>, usize);
+
+/// Read the start of the file and all events until reading fails
+///
+/// Also returns the offset of the end of the last complete event.
+fn read_events<Comment, Header, InitExtra, EventExtra>(
+    input: &[u8],
+) -> Result<RecoveredWithEnd<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    let (mut rest, start) = match read_recovered_start(input) {
+        Some((rest, start)) => (rest, start),
+        None => {
+            return Err(ReadError::Parse(locate_file_start::<
+                Comment,
+                Header,
+                InitExtra,
+            >(input)))
+        }
+    };
+    let mut lhe = LheFileGeneric::from_start(start, Vec::new());
+    let mut events_end = input.len() - rest.len();
+    let reason = loop {
+        if let nom::IResult::Done(remaining, _) = read_closing_file_tag(rest) {
+            if remaining.is_empty() {
+                break StopReason::Complete;
+            }
+            rest = remaining;
+            break StopReason::UnexpectedContent;
+        }
+        if let nom::IResult::Done(remaining, entry) = read_entry(rest) {
+            lhe.push_entry(entry);
+            rest = remaining;
+            events_end = input.len() - rest.len();
+            continue;
+        }
+        break classify_stop(rest);
+    };
+    let offset = input.len() - rest.len();
+    let report = RecoveryReport {
+        reason,
+        events: lhe.events.len(),
+        offset,
+        line: input[..offset].iter().filter(|c| **c == b'\n').count() + 1,
+    };
+    Ok(((lhe, report), events_end))
+}
+
+/// Read an lhe file from the file at `path`, keeping all events before
+/// the point where it is broken
+///
+/// Compressed files are decompressed on the fly, see the
+/// [`compression`] module.
+///
+/// [`compression`]: ../compression/index.html
+pub fn read_lhe_from_file<P, Comment, Header, InitExtra, EventExtra>(
+    path: &P,
+) -> Result<Recovered<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    P: AsRef<Path>,
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+    EventExtra: ReadLhe,
+{
+    read_lhe(&read_file(path)?)
+}
+
+/// Write a repaired copy of the lhe file at `input` to `output`
+///
+/// The repaired file contains the content of the original file up to
+/// the end of the last event that could be read, unchanged, followed by
+/// the closing `</LesHouchesEvents>` tag.
+/// Both files are (de)compressed according to the [`compression`]
+/// module.
+///
+/// [`compression`]: ../compression/index.html
+pub fn repair_file<P, Q>(input: &P, output: &Q) -> Result<RecoveryReport, ReadError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let contents = read_file(input)?;
+    let ((_, report), events_end): ((plain::LheFile, _), _) = read_events(&contents)?;
+    let mut writer = compression::create(output)?;
+    let kept = &contents[..events_end];
+    writer.write_all(kept)?;
+    if !kept.ends_with(b"\n") {
+        writer.write_all(b"\n")?;
+    }
+    write_closing_file_tag(&mut writer)?;
+    writer.finish()?;
+    Ok(report)
+}
+
+fn read_file<P: AsRef<Path>>(path: &P) -> Result<Vec<u8>, ReadError> {
+    let mut file = compression::open(path)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// The rest of the input after the start of a file, and the start itself
+type StartWithRest<'a, Comment, Header, InitExtra> =
+    (&'a [u8], FileStart<Comment, Header, InitExtra>);
+
+/// Read everything in the file up to the first event, which may also
+/// be the end of the input
+///
+/// `read_file_start` returns `Incomplete` if the input ends right after
+/// the init section, since more comments or a header could follow.
+/// For a truncated file, this just means that it has no events.
+fn read_recovered_start<Comment, Header, InitExtra>(
+    input: &[u8],
+) -> Option<StartWithRest<Comment, Header, InitExtra>>
+where
+    Comment: ReadLhe,
+    Header: ReadLhe,
+    InitExtra: ReadLhe,
+{
+    match read_file_start(input) {
+        nom::IResult::Done(rest, start) => Some((rest, start)),
+        nom::IResult::Error(_) => None,
+        nom::IResult::Incomplete(_) => {
+            let closed = [input, &b"\n</LesHouchesEvents>"[..]].concat();
+            match read_file_start(&closed) {
+                nom::IResult::Done(rest, start) if rest == &b"</LesHouchesEvents>"[..] => {
+                    Some((&input[input.len()..], start))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Find out why neither an event nor the closing tag could be read
+fn classify_stop(rest: &[u8]) -> StopReason {
+    let trimmed = trim_start(rest);
+    if b"</LesHouchesEvents>".starts_with(trimmed) {
+        StopReason::MissingClosingTag
+    } else if trimmed.starts_with(b"<!--") && !contains(trimmed, b"-->") {
+        // The file ends inside a comment after the last event
+        StopReason::MissingClosingTag
+    } else if b"<eventgroup".starts_with(trimmed) {
+        // The file ends in the opening tag of an event or event group
+        StopReason::TruncatedEvent
+    } else if !trimmed.starts_with(b"<event") {
+        StopReason::UnexpectedContent
+    } else if trimmed.starts_with(b"<eventgroup") {
+        if contains(trimmed, b"</eventgroup>") {
+            StopReason::InvalidEvent
+        } else {
+            StopReason::TruncatedEvent
+        }
+    } else if contains(trimmed, b"</event>") {
+        StopReason::InvalidEvent
+    } else {
+        StopReason::TruncatedEvent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generic::LheFileGeneric;
+    use helac;
+    use string;
+    use ReadLhe;
+
+    use super::{read_lhe, repair_file, StopReason};
+
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    const SAMPLE_FILES: &[&str] = &[
+        "tests/real_world_files/helac_1loop_tree.lhe",
+        "tests/real_world_files/helac_1loop_virt.lhe",
+        "tests/real_world_files/helac_dipoles_i.lhe",
+        "tests/real_world_files/helac_dipoles_kp.lhe",
+        "tests/real_world_files/helac_dipoles_rs.lhe",
+        "tests/real_world_files/mg5_aMC.lhe",
+        "tests/real_world_files/mg5_aMC_NLO.lhe",
+        "tests/real_world_files/mg5_aMC_NLO_rwgt.lhe",
+    ];
+
+    #[test]
+    fn complete_files() {
+        for file in SAMPLE_FILES {
+            let lhe = string::LheFile::read_lhe_from_file(file).unwrap();
+            let (recovered, report): (string::LheFile, _) =
+                super::read_lhe_from_file(file).unwrap();
+            assert!(report.is_complete());
+            assert_eq!(report.events, lhe.events.len());
+            assert_eq!(recovered, lhe);
+        }
+    }
+
+    #[test]
+    fn truncated_files() {
+        let bytes = fs::read("tests/real_world_files/helac_dipoles_rs.lhe").unwrap();
+        let lhe: helac::LheFileRS = LheFileGeneric::read_lhe(&bytes).to_full_result().unwrap();
+        let closing = bytes.len() - "</LesHouchesEvents>\n".len();
+
+        let (recovered, report): (helac::LheFileRS, _) = read_lhe(&bytes[..closing]).unwrap();
+        assert_eq!(report.reason, StopReason::MissingClosingTag);
+        assert_eq!(recovered, lhe);
+
+        let (recovered, report): (helac::LheFileRS, _) = read_lhe(&bytes[..closing - 30]).unwrap();
+        assert_eq!(report.reason, StopReason::TruncatedEvent);
+        assert_eq!(report.events, lhe.events.len() - 1);
+        assert_eq!(recovered.events[..], lhe.events[..lhe.events.len() - 1]);
+    }
+
+    #[test]
+    fn invalid_event() {
+        let bytes = fs::read_to_string("tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let lhe = string::LheFile::read_lhe(bytes.as_bytes())
+            .to_full_result()
+            .unwrap();
+        let second = bytes.match_indices("<event>").nth(1).unwrap().0;
+        let broken = format!("{}<event>broken{}", &bytes[..second], &bytes[second + 7..]);
+        let (recovered, report): (string::LheFile, _) = read_lhe(broken.as_bytes()).unwrap();
+        assert_eq!(report.reason, StopReason::InvalidEvent);
+        assert_eq!(report.events, 1);
+        assert_eq!(recovered.events[..], lhe.events[..1]);
+    }
+
+    #[test]
+    fn repair() {
+        let bytes = fs::read("tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let lhe = string::LheFile::read_lhe(&bytes).to_full_result().unwrap();
+        let truncated = env::temp_dir().join(format!("lhef-{}-truncated.lhe", process::id()));
+        let repaired = env::temp_dir().join(format!("lhef-{}-repaired.lhe", process::id()));
+        fs::write(&truncated, &bytes[..bytes.len() - 100]).unwrap();
+
+        let report = repair_file(&truncated, &repaired).unwrap();
+        let round = string::LheFile::read_lhe_from_file(&repaired).unwrap();
+        fs::remove_file(&truncated).unwrap();
+        fs::remove_file(&repaired).unwrap();
+
+        assert_eq!(report.reason, StopReason::TruncatedEvent);
+        assert_eq!(report.events, lhe.events.len() - 1);
+        assert_eq!(round.events[..], lhe.events[..lhe.events.len() - 1]);
+        assert_eq!(round.header, lhe.header);
+    }
+
+    #[test]
+    fn repair_complete_file() {
+        let file = "tests/real_world_files/mg5_aMC.lhe";
+        let repaired = env::temp_dir().join(format!("lhef-{}-complete.lhe", process::id()));
+        let report = repair_file(&file, &repaired).unwrap();
+        let bytes = fs::read(&repaired).unwrap();
+        fs::remove_file(&repaired).unwrap();
+        assert!(report.is_complete());
+        assert_eq!(bytes, fs::read(file).unwrap());
+    }
+
+    #[test]
+    fn truncated_group() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<eventgroup nreal=\"1\" ncounter=\"1\">
+<event>
+0 1 1.0 91.2 0.0078 0.118
+</event>
+<event>
+0 1 -1.0 91.2 0.0078 0.118
+</event>
+</eventgroup>
+<eventgroup nreal=\"1\" ncounter=\"1\">
+<event>
+0 1 2.0 91.2 0.0078 0.118
+</event>
+<event>
+0 1 -2.0 91.2 0.0078 0.118
+</event>
+";
+        let (recovered, report): (string::LheFile, _) = read_lhe(bytes).unwrap();
+        assert_eq!(report.reason, StopReason::TruncatedEvent);
+        assert_eq!(report.events, 2);
+        assert_eq!(report.line, 14);
+        assert_eq!(recovered.events.len(), 2);
+        assert_eq!(recovered.groups.len(), 1);
+    }
+
+    #[test]
+    fn truncated_comment() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<event>
+0 1 1.0 91.2 0.0078 0.118
+</event>
+<!-- the generator
+";
+        let (recovered, report): (string::LheFile, _) = read_lhe(bytes).unwrap();
+        assert_eq!(report.reason, StopReason::MissingClosingTag);
+        assert_eq!(report.events, 1);
+        assert_eq!(report.line, 9);
+        assert_eq!(recovered.events.len(), 1);
+    }
+
+    #[test]
+    fn no_events() {
+        let bytes = b"\
+<LesHouchesEvents version=\"1.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+";
+        let (recovered, report): (string::LheFile, _) = read_lhe(bytes).unwrap();
+        assert_eq!(report.reason, StopReason::MissingClosingTag);
+        assert_eq!(report.events, 0);
+        assert_eq!(report.line, 6);
+        assert!(recovered.events.is_empty());
+    }
+
+    #[test]
+    fn truncated_opening_tag() {
+        let bytes = b"\
+<LesHouchesEvents version=\"1.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<event>
+0 1 1.0 91.2 0.0078 0.118
+</event>
+<ev";
+        let (recovered, report): (string::LheFile, _) = read_lhe(bytes).unwrap();
+        assert_eq!(report.reason, StopReason::TruncatedEvent);
+        assert_eq!(report.events, 1);
+        assert_eq!(report.line, 9);
+        assert_eq!(recovered.events.len(), 1);
+    }
+}
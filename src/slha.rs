@@ -0,0 +1,676 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A module for parameter cards in the SUSY Les Houches Accord format
+//!
+//! The SLHA format, described in [`hep-ph/0311123`], stores the
+//! parameters of a model in `BLOCK` tables, and the widths and
+//! branching ratios of particles in `DECAY` tables.
+//! Generators like MadGraph5_aMC@NLO embed their parameter card in
+//! this format in the `<slha>` block of the header.
+//!
+//! Comments and any lines that are not understood are kept, so that a
+//! card can be edited and written again.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use lhef::slha::Slha;
+//!
+//! let mut card = Slha::parse("\
+//! Block mass
+//!     6 1.730000e+02 # MT
+//!    25 1.250000e+02 # MH
+//! DECAY   6 1.491500e+00 # WT
+//! ");
+//!
+//! assert_eq!(card.mass(6), Some(173.));
+//! assert_eq!(card.width(6), Some(1.4915));
+//!
+//! card.set_mass(25, 125.1);
+//! assert_eq!(card.mass(25), Some(125.1));
+//! ```
+//!
+//! [`hep-ph/0311123`]: https://arxiv.org/abs/hep-ph/0311123
+
+use PdgId;
+
+use std::fmt;
+
+/// A parameter card in the SLHA format
+///
+/// The card consists of the `BLOCK` and `DECAY` tables in the order in
+/// which they appear in the card.
+/// Comments and other lines before the first table are kept as
+/// `SlhaItem::Other`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Slha {
+    /// The tables and lines of the card
+    pub items: Vec<SlhaItem>,
+}
+
+impl Slha {
+    /// Parse a parameter card
+    ///
+    /// Parsing never fails, lines that can not be parsed are kept as
+    /// they are, without trailing whitespace.
+    pub fn parse(text: &str) -> Slha {
+        let mut items = Vec::new();
+        for line in text.lines().map(str::trim_end) {
+            if let Some(item) = SlhaItem::from_line(line) {
+                items.push(item);
+                continue;
+            }
+            match items.last_mut() {
+                Some(&mut SlhaItem::Block(ref mut block)) => {
+                    block.lines.push(BlockLine::from_line(line))
+                }
+                Some(&mut SlhaItem::Decay(ref mut decay)) => {
+                    decay.lines.push(DecayLine::from_line(line))
+                }
+                _ => items.push(SlhaItem::Other(line.to_string())),
+            }
+        }
+        Slha { items }
+    }
+
+    /// The block `name`
+    ///
+    /// Block names are compared ignoring case.
+    pub fn block(&self, name: &str) -> Option<&Block> {
+        self.items.iter().find_map(|item| match *item {
+            SlhaItem::Block(ref block) if block.name.eq_ignore_ascii_case(name) => Some(block),
+            _ => None,
+        })
+    }
+
+    /// The block `name`, for editing
+    pub fn block_mut(&mut self, name: &str) -> Option<&mut Block> {
+        self.items.iter_mut().find_map(|item| match *item {
+            SlhaItem::Block(ref mut block) if block.name.eq_ignore_ascii_case(name) => Some(block),
+            _ => None,
+        })
+    }
+
+    /// The decay table of the particle `particle`
+    pub fn decay(&self, particle: PdgId) -> Option<&Decay> {
+        self.items.iter().find_map(|item| match *item {
+            SlhaItem::Decay(ref decay) if decay.particle == particle => Some(decay),
+            _ => None,
+        })
+    }
+
+    /// The decay table of the particle `particle`, for editing
+    pub fn decay_mut(&mut self, particle: PdgId) -> Option<&mut Decay> {
+        self.items.iter_mut().find_map(|item| match *item {
+            SlhaItem::Decay(ref mut decay) if decay.particle == particle => Some(decay),
+            _ => None,
+        })
+    }
+
+    /// The mass of the particle `particle` from the `MASS` block
+    pub fn mass(&self, particle: PdgId) -> Option<f64> {
+        self.block("mass")?.value(&[particle])
+    }
+
+    /// The width of the particle `particle` from its decay table
+    pub fn width(&self, particle: PdgId) -> Option<f64> {
+        parse_f64(&self.decay(particle)?.width)
+    }
+
+    /// Set the mass of the particle `particle` in the `MASS` block
+    ///
+    /// If there is no `MASS` block, it is added at the end of the card.
+    pub fn set_mass(&mut self, particle: PdgId, mass: f64) {
+        if self.block("mass").is_none() {
+            self.items.push(SlhaItem::Block(Block::new("MASS")));
+        }
+        self.block_mut("mass")
+            .expect("BUG: The mass block has to exist")
+            .set(&[particle], mass);
+    }
+
+    /// Set the width of the particle `particle`
+    ///
+    /// If there is no decay table for the particle, one without decay
+    /// channels is added at the end of the card.
+    pub fn set_width(&mut self, particle: PdgId, width: f64) {
+        if let Some(decay) = self.decay_mut(particle) {
+            decay.width = format!("{:e}", width);
+            return;
+        }
+        self.items.push(SlhaItem::Decay(Decay {
+            particle,
+            width: format!("{:e}", width),
+            comment: None,
+            lines: Vec::new(),
+        }));
+    }
+}
+
+/// Write the card, with a newline after each line
+impl fmt::Display for Slha {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for item in &self.items {
+            match *item {
+                SlhaItem::Block(ref block) => write!(f, "{}", block)?,
+                SlhaItem::Decay(ref decay) => write!(f, "{}", decay)?,
+                SlhaItem::Other(ref line) => writeln!(f, "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A table or a line in a parameter card
+#[derive(Clone, Debug, PartialEq)]
+pub enum SlhaItem {
+    /// A `BLOCK` table
+    Block(Block),
+    /// A `DECAY` table
+    Decay(Decay),
+    /// A line before the first table, e.g. a comment
+    Other(String),
+}
+
+impl SlhaItem {
+    /// Parse the first line of a table
+    fn from_line(line: &str) -> Option<SlhaItem> {
+        let (content, comment) = split_comment(line);
+        let (keyword, rest) = split_word(content);
+        if keyword.eq_ignore_ascii_case("block") {
+            let (name, rest) = split_word(rest);
+            if name.is_empty() {
+                return None;
+            }
+            let mut scale = None;
+            let mut arguments = Vec::new();
+            let mut rest = rest;
+            while !rest.is_empty() {
+                let value = rest
+                    .strip_prefix(['Q', 'q'])
+                    .and_then(|value| value.trim_start().strip_prefix('='))
+                    .map(split_word);
+                if let Some((value, next)) = value {
+                    if let Some(value) = parse_f64(value) {
+                        scale = Some(value);
+                        rest = next;
+                        continue;
+                    }
+                }
+                let (argument, next) = split_word(rest);
+                arguments.push(argument.to_string());
+                rest = next;
+            }
+            Some(SlhaItem::Block(Block {
+                name: name.to_string(),
+                arguments,
+                scale,
+                comment,
+                lines: Vec::new(),
+            }))
+        } else if keyword.eq_ignore_ascii_case("decay") {
+            let mut fields = rest.split_whitespace();
+            let particle = fields.next()?.parse().ok()?;
+            let width = fields.next()?;
+            if fields.next().is_some() {
+                return None;
+            }
+            Some(SlhaItem::Decay(Decay {
+                particle,
+                width: width.to_string(),
+                comment,
+                lines: Vec::new(),
+            }))
+        } else {
+            None
+        }
+    }
+}
+
+/// A `BLOCK` table
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::slha::Slha;
+///
+/// let card = Slha::parse("\
+/// BLOCK yukawa Q= 9.118800e+01 # at the Z mass
+///     5 4.700000e+00 # ymb
+///     6 1.730000e+02 # ymt
+/// ");
+///
+/// let yukawa = card.block("YUKAWA").unwrap();
+/// assert_eq!(yukawa.scale, Some(91.188));
+/// assert_eq!(yukawa.value(&[6]), Some(173.));
+/// assert_eq!(yukawa.entry(&[5]).unwrap().comment, Some("ymb".to_string()));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    /// The name of the block, as it is given in the card
+    pub name: String,
+    /// Further arguments after the name other than the scale, e.g. the
+    /// PDG id in `BLOCK QNUMBERS 9000006`
+    pub arguments: Vec<String>,
+    /// The scale `Q` at which the parameters are given
+    pub scale: Option<f64>,
+    /// The comment after the name of the block, without the `#`
+    pub comment: Option<String>,
+    /// The lines of the block
+    pub lines: Vec<BlockLine>,
+}
+
+impl Block {
+    /// Create an empty block
+    pub fn new(name: &str) -> Block {
+        Block {
+            name: name.to_string(),
+            arguments: Vec::new(),
+            scale: None,
+            comment: None,
+            lines: Vec::new(),
+        }
+    }
+
+    /// The entries of the block
+    pub fn entries(&self) -> impl Iterator<Item = &BlockEntry> {
+        self.lines.iter().filter_map(|line| match *line {
+            BlockLine::Entry(ref entry) => Some(entry),
+            BlockLine::Other(_) => None,
+        })
+    }
+
+    /// The entry with the indices `indices`
+    pub fn entry(&self, indices: &[i64]) -> Option<&BlockEntry> {
+        self.entries().find(|entry| entry.indices == indices)
+    }
+
+    /// The value of the entry with the indices `indices` as a floating
+    /// point number
+    pub fn value(&self, indices: &[i64]) -> Option<f64> {
+        parse_f64(&self.entry(indices)?.value)
+    }
+
+    /// Set the value of the entry with the indices `indices`
+    ///
+    /// If there is no such entry, it is added at the end of the block.
+    pub fn set(&mut self, indices: &[i64], value: f64) {
+        let value = format!("{:e}", value);
+        let existing = self.lines.iter_mut().find_map(|line| match *line {
+            BlockLine::Entry(ref mut entry) if entry.indices == indices => Some(entry),
+            _ => None,
+        });
+        match existing {
+            Some(entry) => entry.value = value,
+            None => self.lines.push(BlockLine::Entry(BlockEntry {
+                indices: indices.to_vec(),
+                value,
+                comment: None,
+            })),
+        }
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Block {}", self.name)?;
+        for argument in &self.arguments {
+            write!(f, " {}", argument)?;
+        }
+        if let Some(scale) = self.scale {
+            write!(f, " Q= {:e}", scale)?;
+        }
+        write_comment(f, &self.comment)?;
+        for line in &self.lines {
+            match *line {
+                BlockLine::Entry(ref entry) => {
+                    write!(f, " ")?;
+                    for index in &entry.indices {
+                        write!(f, " {}", index)?;
+                    }
+                    write!(f, " {}", entry.value)?;
+                    write_comment(f, &entry.comment)?;
+                }
+                BlockLine::Other(ref line) => writeln!(f, "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A line in a `BLOCK` table
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockLine {
+    /// An entry of the block
+    Entry(BlockEntry),
+    /// A comment, an empty line or anything else
+    Other(String),
+}
+
+impl BlockLine {
+    fn from_line(line: &str) -> BlockLine {
+        let (content, comment) = split_comment(line);
+        let mut fields: Vec<_> = content.split_whitespace().collect();
+        let value = match fields.pop() {
+            Some(value) => value,
+            None => return BlockLine::Other(line.to_string()),
+        };
+        let indices: Result<Vec<i64>, _> = fields.iter().map(|index| index.parse()).collect();
+        match indices {
+            Ok(indices) => BlockLine::Entry(BlockEntry {
+                indices,
+                value: value.to_string(),
+                comment,
+            }),
+            Err(_) => BlockLine::Other(line.to_string()),
+        }
+    }
+}
+
+/// An entry in a `BLOCK` table
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockEntry {
+    /// The indices of the entry, e.g. the PDG id in the `MASS` block
+    pub indices: Vec<i64>,
+    /// The value of the entry, as it is given in the card
+    pub value: String,
+    /// The comment after the entry, without the `#`
+    pub comment: Option<String>,
+}
+
+/// A `DECAY` table
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::slha::Slha;
+///
+/// let card = Slha::parse("\
+/// DECAY  25 4.070000e-03 # WH
+///     5.770000e-01 2 5 -5 # H -> b b~
+///     2.150000e-01 2 24 -24
+/// ");
+///
+/// let decay = card.decay(25).unwrap();
+/// assert_eq!(decay.comment, Some("WH".to_string()));
+/// let channels: Vec<_> = decay.channels().collect();
+/// assert_eq!(channels[0].branching_ratio, 0.577);
+/// assert_eq!(channels[1].daughters, vec![24, -24]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decay {
+    /// The PDG id of the decaying particle
+    pub particle: PdgId,
+    /// The width of the particle, as it is given in the card
+    ///
+    /// This is kept as a string, since MadGraph5_aMC@NLO allows
+    /// `Auto` as the width.
+    pub width: String,
+    /// The comment after the width, without the `#`
+    pub comment: Option<String>,
+    /// The lines of the table
+    pub lines: Vec<DecayLine>,
+}
+
+impl Decay {
+    /// The decay channels of the particle
+    pub fn channels(&self) -> impl Iterator<Item = &DecayChannel> {
+        self.lines.iter().filter_map(|line| match *line {
+            DecayLine::Channel(ref channel) => Some(channel),
+            DecayLine::Other(_) => None,
+        })
+    }
+}
+
+impl fmt::Display for Decay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DECAY {} {}", self.particle, self.width)?;
+        write_comment(f, &self.comment)?;
+        for line in &self.lines {
+            match *line {
+                DecayLine::Channel(ref channel) => {
+                    write!(
+                        f,
+                        "  {:e} {}",
+                        channel.branching_ratio,
+                        channel.daughters.len()
+                    )?;
+                    for daughter in &channel.daughters {
+                        write!(f, " {}", daughter)?;
+                    }
+                    write_comment(f, &channel.comment)?;
+                }
+                DecayLine::Other(ref line) => writeln!(f, "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A line in a `DECAY` table
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecayLine {
+    /// A decay channel
+    Channel(DecayChannel),
+    /// A comment, an empty line or anything else
+    Other(String),
+}
+
+impl DecayLine {
+    fn from_line(line: &str) -> DecayLine {
+        let (content, comment) = split_comment(line);
+        match DecayChannel::from_fields(content, comment) {
+            Some(channel) => DecayLine::Channel(channel),
+            None => DecayLine::Other(line.to_string()),
+        }
+    }
+}
+
+/// A decay channel in a `DECAY` table
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecayChannel {
+    /// The branching ratio of the channel
+    pub branching_ratio: f64,
+    /// The PDG ids of the decay products
+    pub daughters: Vec<PdgId>,
+    /// The comment after the channel, without the `#`
+    pub comment: Option<String>,
+}
+
+impl DecayChannel {
+    fn from_fields(content: &str, comment: Option<String>) -> Option<DecayChannel> {
+        let mut fields = content.split_whitespace();
+        let branching_ratio = parse_f64(fields.next()?)?;
+        let n_daughters: usize = fields.next()?.parse().ok()?;
+        let daughters: Vec<PdgId> = fields.map(|id| id.parse().ok()).collect::<Option<_>>()?;
+        if daughters.len() != n_daughters {
+            return None;
+        }
+        Some(DecayChannel {
+            branching_ratio,
+            daughters,
+            comment,
+        })
+    }
+}
+
+/// Parse a floating point number, supporting Fortran style exponents
+/// like in `1d0`
+pub(crate) fn parse_f64(text: &str) -> Option<f64> {
+    text.trim().replace(['d', 'D'], "e").parse().ok()
+}
+
+/// Split a line into its content and the comment after a `#`
+fn split_comment(line: &str) -> (&str, Option<String>) {
+    match line.find('#') {
+        Some(n) => {
+            let comment = line[n + 1..].trim();
+            (
+                &line[..n],
+                Some(comment).filter(|c| !c.is_empty()).map(String::from),
+            )
+        }
+        None => (line, None),
+    }
+}
+
+/// Split the first word off of `text`
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(char::is_whitespace) {
+        Some(n) => (&text[..n], text[n..].trim()),
+        None => (text, ""),
+    }
+}
+
+/// Write the comment, if there is one, and end the line
+fn write_comment(f: &mut fmt::Formatter, comment: &Option<String>) -> fmt::Result {
+    match *comment {
+        Some(ref comment) => writeln!(f, " # {}", comment),
+        None => writeln!(f),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockLine, DecayLine, Slha, SlhaItem};
+
+    const CARD: &str = "\
+######################################################################
+## PARAM_CARD AUTOMATICALY GENERATED BY MG5 FOLLOWING UFO MODEL   ####
+######################################################################
+
+Block mass
+    5 4.700000e+00 # MB
+    6 1.730000e+02 # MT
+## Dependent parameters, given by model restrictions.
+  24 80.419002 # w+ : cmath.sqrt(MZ__exp__2/2.)
+BLOCK NMIX Q= 1.0d3
+  1  1     9.86e-01   # N_11
+  1  2    -5.31e-02   # N_12
+Block spinfo
+    1 SOFTSUSY
+    2 3.8.0 # version
+    3 some warning
+BLOCK ALPHA
+  -1.1e-01
+DECAY   6 1.491500e+00 # WT
+DECAY  25 Auto
+   5.770000e-01 2 5 -5 # H -> b b~
+   2.150000e-01 2 24
+";
+
+    #[test]
+    fn parse_card() {
+        let card = Slha::parse(CARD);
+        assert_eq!(card.items.len(), 10);
+        assert_eq!(
+            card.items[0],
+            SlhaItem::Other(
+                "######################################################################"
+                    .to_string()
+            )
+        );
+
+        assert_eq!(card.mass(5), Some(4.7));
+        assert_eq!(card.mass(24), Some(80.419002));
+        assert_eq!(card.mass(1), None);
+        let mass = card.block("MASS").unwrap();
+        assert_eq!(mass.scale, None);
+        assert_eq!(mass.entries().count(), 3);
+        match mass.lines[2] {
+            BlockLine::Other(ref line) => assert!(line.starts_with("## Dependent")),
+            _ => panic!("Expected a comment"),
+        }
+
+        let nmix = card.block("nmix").unwrap();
+        assert_eq!(nmix.scale, Some(1000.));
+        assert_eq!(nmix.value(&[1, 2]), Some(-5.31e-2));
+        assert_eq!(
+            nmix.entry(&[1, 1]).unwrap().comment,
+            Some("N_11".to_string())
+        );
+
+        let spinfo = card.block("SPINFO").unwrap();
+        assert_eq!(spinfo.entry(&[1]).unwrap().value, "SOFTSUSY");
+        assert_eq!(spinfo.entry(&[2]).unwrap().value, "3.8.0");
+        assert_eq!(
+            spinfo.lines[2],
+            BlockLine::Other("    3 some warning".to_string())
+        );
+
+        assert_eq!(card.block("alpha").unwrap().value(&[]), Some(-0.11));
+
+        assert_eq!(card.width(6), Some(1.4915));
+        assert_eq!(card.width(25), None);
+        let higgs = card.decay(25).unwrap();
+        assert_eq!(higgs.width, "Auto");
+        assert_eq!(higgs.channels().count(), 1);
+        assert_eq!(
+            higgs.lines[1],
+            DecayLine::Other("   2.150000e-01 2 24".to_string())
+        );
+    }
+
+    #[test]
+    fn edit_card() {
+        let mut card = Slha::parse(CARD);
+        card.set_mass(6, 172.5);
+        card.set_mass(1000022, 97.);
+        card.set_width(25, 4.07e-3);
+        card.set_width(23, 2.4414);
+        card.block_mut("nmix").unwrap().set(&[2, 1], 0.1);
+
+        let round = Slha::parse(&card.to_string());
+        assert_eq!(card, round);
+        assert_eq!(round.mass(6), Some(172.5));
+        assert_eq!(round.mass(1000022), Some(97.));
+        assert_eq!(round.mass(5), Some(4.7));
+        assert_eq!(round.width(25), Some(4.07e-3));
+        assert_eq!(round.width(23), Some(2.4414));
+        assert_eq!(round.block("nmix").unwrap().value(&[2, 1]), Some(0.1));
+        assert_eq!(round.decay(25).unwrap().channels().count(), 1);
+    }
+
+    #[test]
+    fn block_arguments() {
+        let card = Slha::parse(
+            "\
+Block mass
+    5 4.700000e+00 # MB
+BLOCK QNUMBERS 9000006 # gv
+     1 0
+     4 1
+Block yukawa 2 q = 91.188
+",
+        );
+        assert_eq!(card.items.len(), 3);
+        assert_eq!(card.mass(5), Some(4.7));
+        assert_eq!(card.mass(1), None);
+        assert_eq!(card.mass(4), None);
+        let qnumbers = card.block("qnumbers").unwrap();
+        assert_eq!(qnumbers.arguments, vec!["9000006".to_string()]);
+        assert_eq!(qnumbers.scale, None);
+        assert_eq!(qnumbers.comment, Some("gv".to_string()));
+        assert_eq!(qnumbers.value(&[4]), Some(1.));
+        let yukawa = card.block("yukawa").unwrap();
+        assert_eq!(yukawa.arguments, vec!["2".to_string()]);
+        assert_eq!(yukawa.scale, Some(91.188));
+
+        assert_eq!(Slha::parse(&card.to_string()), card);
+    }
+
+    #[test]
+    fn add_mass_block() {
+        let mut card = Slha::parse("");
+        assert!(card.items.is_empty());
+        card.set_mass(6, 173.);
+        assert_eq!(card.to_string(), "Block MASS\n  6 1.73e2\n");
+    }
+}
@@ -55,7 +55,7 @@
 //!
 //! let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
 //!
-//! assert_eq!(lhe.version, "1.0".to_string());
+//! assert_eq!(lhe.version.as_str(), "1.0");
 //!
 //! let Comment { ref comment } = lhe.comment;
 //! assert_eq!(comment, &Some("Process: e+ e- > mu+ mu-".to_string()));
@@ -78,6 +78,8 @@
 
 use {ReadLhe, WriteLhe};
 use generic::LheFileGeneric;
+use nom_util::take_until_closing_tag;
+use version::{downgrade_text, Downgrade, DowngradeMode, LhefVersion};
 
 use nom;
 use std::io;
@@ -124,7 +126,7 @@ use quickcheck::Gen;
 ///
 /// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
 ///
-/// assert_eq!(lhe.version, "1.0".to_string());
+/// assert_eq!(lhe.version.as_str(), "1.0");
 ///
 /// let Comment { ref comment } = lhe.comment;
 /// assert_eq!(comment, &Some("Process: e+ e- > mu+ mu-".to_string()));
@@ -225,6 +227,8 @@ impl WriteLhe for Comment {
     }
 }
 
+impl Downgrade for Comment {}
+
 #[cfg(test)]
 impl Arbitrary for Comment {
     fn arbitrary<G: Gen>(gen: &mut G) -> Comment {
@@ -303,7 +307,7 @@ impl ReadLhe for Header {
             opt!(map_res!(
                 delimited!(
                     tag!("<header>"),
-                    take_until!("</header>"),
+                    call!(take_until_closing_tag, "</header>"),
                     tag!("</header>")
                 ),
                 |x| str::from_utf8(x).map(|x| x.trim().to_string())
@@ -326,6 +330,14 @@ impl WriteLhe for Header {
     }
 }
 
+impl Downgrade for Header {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        if let Some(ref mut header) = self.header {
+            *header = downgrade_text(header, version, mode);
+        }
+    }
+}
+
 #[cfg(test)]
 impl Arbitrary for Header {
     fn arbitrary<G: Gen>(gen: &mut G) -> Header {
@@ -398,6 +410,12 @@ impl WriteLhe for InitExtra {
     }
 }
 
+impl Downgrade for InitExtra {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        self.0 = downgrade_text(&self.0, version, mode);
+    }
+}
+
 #[cfg(test)]
 impl Arbitrary for InitExtra {
     fn arbitrary<G: Gen>(gen: &mut G) -> InitExtra {
@@ -476,6 +494,12 @@ impl WriteLhe for EventExtra {
     }
 }
 
+impl Downgrade for EventExtra {
+    fn downgrade(&mut self, version: &LhefVersion, mode: DowngradeMode) {
+        self.0 = downgrade_text(&self.0, version, mode);
+    }
+}
+
 #[cfg(test)]
 impl Arbitrary for EventExtra {
     fn arbitrary<G: Gen>(gen: &mut G) -> EventExtra {
@@ -496,8 +520,10 @@ mod tests {
     use std::str;
 
     use {Particle, ProcInfo, ReadLhe, WriteLhe};
-    use generic::{EventGeneric, InitGeneric, LheFileGeneric};
+    use attributes::Attributes;
+    use generic::{CommentPosition, EventGeneric, InitGeneric, LheFileGeneric};
     use lorentz_vector::LorentzVector;
+    use version::LhefVersion;
 
     use super::*;
 
@@ -606,6 +632,7 @@ extra line 2
                 },
             ],
             extra: InitExtra("# extra line 1\nextra line 2".to_string()),
+            attributes: Attributes::new(),
         };
         let result = InitGeneric::<InitExtra>::read_lhe(bytes)
             .to_full_result()
@@ -666,6 +693,7 @@ extra line 2
                 },
             ],
             extra: EventExtra("# extra line 1\nextra line 2".to_string()),
+            attributes: Attributes::new(),
         };
         let result = EventGeneric::<EventExtra>::read_lhe(bytes)
             .to_full_result()
@@ -686,7 +714,8 @@ extra line 2
 </event>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment { comment: None },
             header: Header { header: None },
             init: InitGeneric {
@@ -701,6 +730,7 @@ extra line 2
                 weighting_strategy: 60,
                 process_info: vec![],
                 extra: InitExtra("".to_string()),
+                attributes: Attributes::new(),
             },
             events: vec![
                 EventGeneric {
@@ -729,8 +759,11 @@ extra line 2
                         },
                     ],
                     extra: EventExtra("".to_string()),
+                    attributes: Attributes::new(),
                 },
             ],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -748,7 +781,8 @@ Generated using numbers
 </init>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {
                 comment: Some("Generated using numbers".to_string()),
             },
@@ -765,8 +799,11 @@ Generated using numbers
                 weighting_strategy: 60,
                 process_info: vec![],
                 extra: InitExtra("".to_string()),
+                attributes: Attributes::new(),
             },
             events: vec![],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -786,7 +823,8 @@ header line 1
 </header>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment { comment: None },
             header: Header {
                 header: Some(
@@ -805,8 +843,11 @@ header line 1
                 weighting_strategy: 60,
                 process_info: vec![],
                 extra: InitExtra("".to_string()),
+                attributes: Attributes::new(),
             },
             events: vec![],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -829,7 +870,8 @@ header line 1
 </init>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {
                 comment: Some("Generated using numbers".to_string()),
             },
@@ -850,8 +892,11 @@ header line 1
                 weighting_strategy: 60,
                 process_info: vec![],
                 extra: InitExtra("".to_string()),
+                attributes: Attributes::new(),
             },
             events: vec![],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -860,11 +905,14 @@ header line 1
     #[test]
     fn qc_regression() {
         let lhe = LheFileGeneric {
-            version: "".to_string(),
+            version: LhefVersion::from(""),
+            attributes: Attributes::new(),
             comment: Comment { comment: Some(".\u{9b}{£->\u{13}\u{fff2}\n>w蓲*খ?,®xy\u{79994}\n3\u{11cdd}R_\u{84}E{\"\u{7f}\u{92}\u{0}\u{3000}|\u{82}㖬⁛ٺ$![\u{88}8?\u{10f0be}\u{e56c}¬)\u{70f}\u{9a}\u{7}\u{2005}*\u{2006}\u{9cf}퐢\"\u{e922}\n䰝\u{2}‖\n^@4#7ª\u{1d}‶9\u{fff8}}!뇝\u{92081}*\u{81}颱RA3S )%\u{81}\u{48377}\u{10ffff}".to_string())},
             header: Header { header: Some("⁂\u{9c}\u{8d}\u{1d}\u{97}\u{1c}(7«8\u{17}g\u{8e}\u{a0}娐S\u{0}?\u{89}eª⁒h𪄡u\u{f}\u{ffff}4\u{1b}\u{10}\u{d278e}\u{80}§<麡B\u{13}ªG£5揬\\톤e\u{d3677}@&\u{b}?\u{7}⁒H,\u{92}뜒t}6]\u{16}`\u{9a}ⴑ¦¡V\u{84}_]\u{91}«}2(%7X~\u{86}癪\u{16}\u{feeb9}\u{91}{u\u{206a}$]+-X).Aq[\u{f}".to_string()) },
-            init: InitGeneric { beam_1_id: -17, beam_2_id: -95, beam_1_energy: -61.50434190590901, beam_2_energy: -84.36896434784065, beam_1_pdf_group_id: 15, beam_2_pdf_group_id: -91, beam_1_pdf_id: -58, beam_2_pdf_id: 84, weighting_strategy: -89, process_info: vec![ProcInfo { xsect: 3.0340493268133315, xsect_err: 73.39946519830431, maximum_weight: -70.69361722451761, process_id: -96 }], extra: InitExtra("p\n餻M⁊#𐫚&\u{84} 쒵\u{8d}a\u{8a}\\\u{2061}\u{3}8>횮\n$\u{99}\u{1c}\u{1b}:[\u{9e}פ#\u{206f}?2\u{91}\t£(+&b[\u{10715a}\u{70019}\u{17}\u{65953}&".to_string()) },
+            init: InitGeneric { beam_1_id: -17, beam_2_id: -95, beam_1_energy: -61.50434190590901, beam_2_energy: -84.36896434784065, beam_1_pdf_group_id: 15, beam_2_pdf_group_id: -91, beam_1_pdf_id: -58, beam_2_pdf_id: 84, weighting_strategy: -89, process_info: vec![ProcInfo { xsect: 3.0340493268133315, xsect_err: 73.39946519830431, maximum_weight: -70.69361722451761, process_id: -96 }], extra: InitExtra("p\n餻M⁊#𐫚&\u{84} 쒵\u{8d}a\u{8a}\\\u{2061}\u{3}8>횮\n$\u{99}\u{1c}\u{1b}:[\u{9e}פ#\u{206f}?2\u{91}\t£(+&b[\u{10715a}\u{70019}\u{17}\u{65953}&".to_string()), attributes: Attributes::new() },
             events: vec![],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let mut bytes = Vec::new();
         lhe.write_lhe(&mut bytes).unwrap();
@@ -913,7 +961,8 @@ header line 1
 </event>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {
                 comment: Some("Generated using numbers".to_string()),
             },
@@ -947,6 +996,7 @@ header line 1
                     },
                 ],
                 extra: InitExtra("# init extra ?".to_string()),
+                attributes: Attributes::new(),
             },
             events: vec![
                 EventGeneric {
@@ -992,6 +1042,7 @@ header line 1
                         },
                     ],
                     extra: EventExtra("# extra 1\n2nd extra".to_string()),
+                    attributes: Attributes::new(),
                 },
                 EventGeneric {
                     process_id: 33,
@@ -1019,8 +1070,11 @@ header line 1
                         },
                     ],
                     extra: EventExtra("52 53 54 55 56 57 58. 59. 60. 61. 62. 63. 64.".to_string()),
+                    attributes: Attributes::new(),
                 },
             ],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
@@ -1058,7 +1112,8 @@ Generated using numbers
 </event>
 </LesHouchesEvents>";
         let expected = LheFileGeneric {
-            version: "1.0".to_string(),
+            version: LhefVersion::V1,
+            attributes: Attributes::new(),
             comment: Comment {
                 comment: Some("Generated using numbers".to_string()),
             },
@@ -1092,6 +1147,7 @@ Generated using numbers
                     },
                 ],
                 extra: InitExtra("# init extra ?".to_string()),
+                attributes: Attributes::new(),
             },
             events: vec![
                 EventGeneric {
@@ -1137,6 +1193,7 @@ Generated using numbers
                         },
                     ],
                     extra: EventExtra("# extra 1\n2nd extra".to_string()),
+                    attributes: Attributes::new(),
                 },
                 EventGeneric {
                     process_id: 33,
@@ -1164,13 +1221,78 @@ Generated using numbers
                         },
                     ],
                     extra: EventExtra("52 53 54 55 56 57 58. 59. 60. 61. 62. 63. 64.".to_string()),
+                    attributes: Attributes::new(),
                 },
             ],
+            groups: Vec::new(),
+            comments: Vec::new(),
         };
         let result = LheFile::read_lhe(bytes).to_full_result().unwrap();
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn read_comments_and_cdata() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<!-- first -->
+
+<!-- second -->
+<header>
+<![CDATA[ </header> ]]>
+<!-- </header> -->
+</header>
+<!-- third -->
+<init>
+1 2 3. 4. 5 6 7 8 9 0
+</init>
+<!-- before event 0 -->
+<event>
+0 1 1. 2. 3. 4.
+</event>
+
+
+<eventgroup>
+<!-- inside group -->
+<event>
+0 1 1. 2. 3. 4.
+</event>
+<!-- inside group -->
+</eventgroup>
+<!--
+before the end
+-->
+</LesHouchesEvents>
+";
+        let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+        assert_eq!(lhe.comment.comment, Some("first".to_string()));
+        assert_eq!(
+            lhe.header.header,
+            Some("<![CDATA[ </header> ]]>\n<!-- </header> -->".to_string())
+        );
+        assert_eq!(lhe.events.len(), 2);
+        let comments: Vec<_> = lhe.comments
+            .iter()
+            .map(|c| (c.position, c.comment.comment.as_ref().unwrap().as_str()))
+            .collect();
+        assert_eq!(
+            comments,
+            vec![
+                (CommentPosition::Start, "second"),
+                (CommentPosition::Start, "third"),
+                (CommentPosition::BeforeEvent(0), "before event 0"),
+                (CommentPosition::InGroup(0, 1), "inside group"),
+                (CommentPosition::InGroup(0, 2), "inside group"),
+                (CommentPosition::BeforeEvent(2), "before the end"),
+            ]
+        );
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        let round = LheFile::read_lhe(&written).to_full_result().unwrap();
+        assert_eq!(lhe, round);
+    }
+
     const SAMPLE_FILES: &'static [&'static str] = &[
         "tests/real_world_files/helac_1loop_tree.lhe",
         "tests/real_world_files/helac_1loop_virt.lhe",
@@ -0,0 +1,516 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Check events for physical and structural consistency
+//!
+//! Parsing a file only checks that it has the right format.
+//! The `validate` methods of `EventGeneric` and `LheFileGeneric` check
+//! the contents of the events and return a [`Finding`] for every problem
+//! they find:
+//!
+//! - the sum of the momenta of the incoming particles (status -1) has to
+//!   be equal to the sum of the momenta of the outgoing particles
+//!   (status 1)
+//! - the mothers of all particles have to exist and no particle may be
+//!   its own ancestor
+//! - every colour tag has to connect exactly one colour and one
+//!   anticolour, where the colours of incoming particles count as
+//!   anticolours and vice versa
+//! - the status codes have to be one of the codes defined in the [`lhe`]
+//!   paper
+//! - the energy of every particle has to be consistent with its mass
+//!   and three momentum
+//! - the process id of every event has to be one of the process ids in
+//!   the init section (only checked by `LheFileGeneric::validate`)
+//!
+//! Particles are counted from zero, in the same way as they are indexed
+//! in `EventGeneric::particles`, while the mother ids are the (one based)
+//! ids used in the file.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::ReadLhe;
+//! use lhef::plain::LheFile;
+//! use lhef::validate::Tolerances;
+//!
+//! let lhe = LheFile::read_lhe_from_file(&"events.lhe").unwrap();
+//! for (event, finding) in lhe.validate(&Tolerances::default()) {
+//!     println!("event {}: {}", event, finding);
+//! }
+//! ```
+//!
+//! [`Finding`]: enum.Finding.html
+//! [`lhe`]: https://arxiv.org/abs/hep-ph/0609017
+
+use generic::{EventGeneric, LheFileGeneric};
+use lorentz_vector::LorentzVector;
+use Particle;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The status codes defined in the lhe standard
+const KNOWN_STATUS_CODES: &[i64] = &[-9, -2, -1, 1, 2, 3];
+
+/// The tolerances used for the checks that involve floating point numbers
+///
+/// Both tolerances are relative.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tolerances {
+    /// The allowed difference between the sums of the incoming and
+    /// outgoing momenta, relative to the energy of the incoming
+    /// particles
+    pub momentum: f64,
+    /// The allowed difference between the squared mass of a particle
+    /// and the square of its four momentum, relative to the square of
+    /// its energy
+    pub mass: f64,
+}
+
+impl Default for Tolerances {
+    fn default() -> Tolerances {
+        Tolerances {
+            momentum: 1e-6,
+            mass: 1e-4,
+        }
+    }
+}
+
+/// A problem found while validating an event
+#[derive(Clone, Debug, PartialEq)]
+pub enum Finding {
+    /// The momenta of the incoming and outgoing particles don't add up
+    MomentumNotConserved {
+        /// The sum of the momenta of the incoming particles
+        incoming: LorentzVector,
+        /// The sum of the momenta of the outgoing particles
+        outgoing: LorentzVector,
+    },
+    /// A mother id does not refer to another particle in the event
+    InvalidMother {
+        /// The index of the particle
+        particle: usize,
+        /// The invalid mother id
+        mother_id: i64,
+    },
+    /// A particle is its own ancestor
+    CyclicMothers {
+        /// The index of the particle
+        particle: usize,
+    },
+    /// A colour tag does not connect exactly one colour and one
+    /// anticolour
+    UnpairedColour {
+        /// The colour tag
+        tag: i64,
+    },
+    /// A status code that is not defined in the lhe standard
+    UnknownStatus {
+        /// The index of the particle
+        particle: usize,
+        /// The status code
+        status: i64,
+    },
+    /// The energy of a particle is inconsistent with its mass and three
+    /// momentum
+    MassMismatch {
+        /// The index of the particle
+        particle: usize,
+        /// The mass stored for the particle
+        mass: f64,
+        /// The invariant mass calculated from the four momentum, which
+        /// is negative for spacelike momenta
+        invariant_mass: f64,
+    },
+    /// The process id of the event is not in the init section
+    UnknownProcessId {
+        /// The process id of the event
+        process_id: i64,
+    },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Finding::MomentumNotConserved {
+                ref incoming,
+                ref outgoing,
+            } => write!(
+                f,
+                "momentum is not conserved: incoming ({}, {}, {}, {}), outgoing ({}, {}, {}, {})",
+                incoming.e,
+                incoming.px,
+                incoming.py,
+                incoming.pz,
+                outgoing.e,
+                outgoing.px,
+                outgoing.py,
+                outgoing.pz
+            ),
+            Finding::InvalidMother {
+                particle,
+                mother_id,
+            } => write!(
+                f,
+                "particle {} has an invalid mother id {}",
+                particle, mother_id
+            ),
+            Finding::CyclicMothers { particle } => {
+                write!(f, "particle {} is its own ancestor", particle)
+            }
+            Finding::UnpairedColour { tag } => write!(f, "colour tag {} is not paired", tag),
+            Finding::UnknownStatus { particle, status } => write!(
+                f,
+                "particle {} has an unknown status code {}",
+                particle, status
+            ),
+            Finding::MassMismatch {
+                particle,
+                mass,
+                invariant_mass,
+            } => write!(
+                f,
+                "particle {} has mass {}, but its four momentum has mass {}",
+                particle, mass, invariant_mass
+            ),
+            Finding::UnknownProcessId { process_id } => {
+                write!(f, "process id {} is not in the init section", process_id)
+            }
+        }
+    }
+}
+
+impl<EventExtra> EventGeneric<EventExtra> {
+    /// Check the event for consistency
+    ///
+    /// This runs all checks described in the [`validate`] module, except
+    /// for the one of the process id, which needs the init section.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lhef::ReadLhe;
+    /// use lhef::plain::LheFile;
+    /// use lhef::validate::Tolerances;
+    ///
+    /// let bytes = b"\
+    /// <LesHouchesEvents version=\"1.0\">
+    /// <init>
+    /// 2212 2212 6500 6500 0 0 13100 13100 3 1
+    /// 2.1 3.2E-03 1.0E+00 1
+    /// </init>
+    /// <event>
+    /// 4 1 1.0 91.2 0.0078 0.118
+    /// 21 -1 0 0 501 502 0.0 0.0 50.0 50.0 0.0 0. 9.
+    /// 21 -1 0 0 502 503 0.0 0.0 -50.0 50.0 0.0 0. 9.
+    /// 21 1 1 2 501 504 30.0 0.0 40.0 50.0 0.0 0. 9.
+    /// 21 1 1 2 504 503 -30.0 0.0 -40.0 50.0 0.0 0. 9.
+    /// </event>
+    /// </LesHouchesEvents>";
+    ///
+    /// let lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+    /// assert_eq!(lhe.events[0].validate(&Tolerances::default()), vec![]);
+    /// ```
+    ///
+    /// [`validate`]: ../validate/index.html
+    pub fn validate(&self, tolerances: &Tolerances) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        check_momentum_conservation(&self.particles, tolerances, &mut findings);
+        check_mothers(&self.particles, &mut findings);
+        check_colours(&self.particles, &mut findings);
+        for (i, particle) in self.particles.iter().enumerate() {
+            if !KNOWN_STATUS_CODES.contains(&particle.status) {
+                findings.push(Finding::UnknownStatus {
+                    particle: i,
+                    status: particle.status,
+                });
+            }
+            check_mass(i, particle, tolerances, &mut findings);
+        }
+        findings
+    }
+}
+
+impl<Comment, Header, InitExtra, EventExtra>
+    LheFileGeneric<Comment, Header, InitExtra, EventExtra>
+{
+    /// Check all events in the file for consistency
+    ///
+    /// This runs all checks described in the [`validate`] module.
+    /// The findings are returned together with the index of the event
+    /// they belong to.
+    ///
+    /// [`validate`]: ../validate/index.html
+    pub fn validate(&self, tolerances: &Tolerances) -> Vec<(usize, Finding)> {
+        let mut findings = Vec::new();
+        for (i, event) in self.events.iter().enumerate() {
+            if !self
+                .init
+                .process_info
+                .iter()
+                .any(|p| p.process_id == event.process_id)
+            {
+                findings.push((
+                    i,
+                    Finding::UnknownProcessId {
+                        process_id: event.process_id,
+                    },
+                ));
+            }
+            findings.extend(event.validate(tolerances).into_iter().map(|f| (i, f)));
+        }
+        findings
+    }
+}
+
+fn check_momentum_conservation(
+    particles: &[Particle],
+    tolerances: &Tolerances,
+    findings: &mut Vec<Finding>,
+) {
+    let incoming = sum_momenta(particles.iter().filter(|p| p.status == -1));
+    let outgoing = sum_momenta(particles.iter().filter(|p| p.status == 1));
+    if incoming.e == 0. && outgoing.e == 0. {
+        return;
+    }
+    let scale = incoming.e.abs().max(outgoing.e.abs());
+    let differences = [
+        incoming.e - outgoing.e,
+        incoming.px - outgoing.px,
+        incoming.py - outgoing.py,
+        incoming.pz - outgoing.pz,
+    ];
+    if differences
+        .iter()
+        .any(|d| d.is_nan() || d.abs() > tolerances.momentum * scale)
+    {
+        findings.push(Finding::MomentumNotConserved { incoming, outgoing });
+    }
+}
+
+fn sum_momenta<'a, I: Iterator<Item = &'a Particle>>(particles: I) -> LorentzVector {
+    let mut sum = LorentzVector {
+        e: 0.,
+        px: 0.,
+        py: 0.,
+        pz: 0.,
+    };
+    for p in particles {
+        sum.e += p.momentum.e;
+        sum.px += p.momentum.px;
+        sum.py += p.momentum.py;
+        sum.pz += p.momentum.pz;
+    }
+    sum
+}
+
+fn check_mothers(particles: &[Particle], findings: &mut Vec<Finding>) {
+    // The valid mothers of each particle, as indices into `particles`
+    let mut mothers = Vec::with_capacity(particles.len());
+    for (i, particle) in particles.iter().enumerate() {
+        let mut valid = Vec::new();
+        for &mother_id in &[particle.mother_1_id, particle.mother_2_id] {
+            if mother_id == 0 {
+                continue;
+            }
+            if mother_id < 0 || mother_id as usize > particles.len() || mother_id as usize == i + 1
+            {
+                findings.push(Finding::InvalidMother {
+                    particle: i,
+                    mother_id,
+                });
+            } else {
+                valid.push(mother_id as usize - 1);
+            }
+        }
+        mothers.push(valid);
+    }
+    for i in 0..particles.len() {
+        if is_ancestor(&mothers, i, i) {
+            findings.push(Finding::CyclicMothers { particle: i });
+        }
+    }
+}
+
+/// Check whether `ancestor` is reachable from `particle` by following
+/// the mothers
+fn is_ancestor(mothers: &[Vec<usize>], particle: usize, ancestor: usize) -> bool {
+    let mut visited = vec![false; mothers.len()];
+    let mut stack = mothers[particle].clone();
+    while let Some(p) = stack.pop() {
+        if p == ancestor {
+            return true;
+        }
+        if !visited[p] {
+            visited[p] = true;
+            stack.extend(&mothers[p]);
+        }
+    }
+    false
+}
+
+fn check_colours(particles: &[Particle], findings: &mut Vec<Finding>) {
+    // For every tag, the number of colours and anticolours, seen from
+    // the final state
+    let mut tags: BTreeMap<i64, (usize, usize)> = BTreeMap::new();
+    for particle in particles {
+        let (colour, anticolour) = match particle.status {
+            -1 => (particle.color_2, particle.color_1),
+            1 => (particle.color_1, particle.color_2),
+            _ => continue,
+        };
+        if colour != 0 {
+            tags.entry(colour).or_insert((0, 0)).0 += 1;
+        }
+        if anticolour != 0 {
+            tags.entry(anticolour).or_insert((0, 0)).1 += 1;
+        }
+    }
+    for (&tag, &count) in &tags {
+        if count != (1, 1) {
+            findings.push(Finding::UnpairedColour { tag });
+        }
+    }
+}
+
+fn check_mass(
+    index: usize,
+    particle: &Particle,
+    tolerances: &Tolerances,
+    findings: &mut Vec<Finding>,
+) {
+    let p = &particle.momentum;
+    let mass2 = p.e * p.e - p.px * p.px - p.py * p.py - p.pz * p.pz;
+    let difference = mass2 - particle.mass * particle.mass;
+    if difference.is_nan() || difference.abs() > tolerances.mass * p.e * p.e {
+        findings.push(Finding::MassMismatch {
+            particle: index,
+            mass: particle.mass,
+            invariant_mass: mass2.signum() * mass2.abs().sqrt(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Finding, Tolerances};
+
+    use plain::LheFile;
+    use ReadLhe;
+
+    const SAMPLE_FILES: &[&str] = &[
+        "tests/real_world_files/helac_1loop_tree.lhe",
+        "tests/real_world_files/helac_1loop_virt.lhe",
+        "tests/real_world_files/helac_dipoles_i.lhe",
+        "tests/real_world_files/helac_dipoles_kp.lhe",
+        "tests/real_world_files/helac_dipoles_rs.lhe",
+        "tests/real_world_files/mg5_aMC.lhe",
+        "tests/real_world_files/mg5_aMC_NLO.lhe",
+        "tests/real_world_files/mg5_aMC_NLO_rwgt.lhe",
+    ];
+
+    const EVENT: &str = "\
+<LesHouchesEvents version=\"1.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<event>
+4 1 1.0 91.2 0.0078 0.118
+21 -1 0 0 501 502 0.0 0.0 50.0 50.0 0.0 0. 9.
+21 -1 0 0 502 503 0.0 0.0 -50.0 50.0 0.0 0. 9.
+21 1 1 2 501 504 30.0 0.0 40.0 50.0 0.0 0. 9.
+21 1 1 2 504 503 -30.0 0.0 -40.0 50.0 0.0 0. 9.
+</event>
+</LesHouchesEvents>";
+
+    fn findings(file: &str) -> Vec<(usize, Finding)> {
+        LheFile::read_lhe(file.as_bytes())
+            .to_full_result()
+            .unwrap()
+            .validate(&Tolerances::default())
+    }
+
+    #[test]
+    fn sample_files_are_valid() {
+        for file in SAMPLE_FILES {
+            let lhe = LheFile::read_lhe_from_file(file).unwrap();
+            assert_eq!(lhe.validate(&Tolerances::default()), vec![], "{}", file);
+        }
+    }
+
+    #[test]
+    fn valid_event() {
+        assert_eq!(findings(EVENT), vec![]);
+    }
+
+    #[test]
+    fn momentum_not_conserved() {
+        let file = EVENT.replace("-30.0 0.0 -40.0 50.0", "-30.0 1.0 -40.0 50.0");
+        let found = findings(&file);
+        assert_eq!(found.len(), 2);
+        match found[0] {
+            (0, Finding::MomentumNotConserved { ref outgoing, .. }) => assert_eq!(outgoing.py, 1.),
+            _ => panic!("Expected momentum not to be conserved"),
+        }
+        match found[1] {
+            (0, Finding::MassMismatch { particle: 3, .. }) => {}
+            _ => panic!("Expected a mass mismatch"),
+        }
+    }
+
+    #[test]
+    fn invalid_mothers() {
+        let file = EVENT
+            .replace("21 1 1 2 501 504", "21 1 4 5 501 504")
+            .replace("21 1 1 2 504 503", "21 1 3 0 504 503");
+        assert_eq!(
+            findings(&file),
+            vec![
+                (
+                    0,
+                    Finding::InvalidMother {
+                        particle: 2,
+                        mother_id: 5,
+                    },
+                ),
+                (0, Finding::CyclicMothers { particle: 2 }),
+                (0, Finding::CyclicMothers { particle: 3 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn unpaired_colour() {
+        let file = EVENT.replace("21 1 1 2 504 503", "21 1 1 2 505 503");
+        assert_eq!(
+            findings(&file),
+            vec![
+                (0, Finding::UnpairedColour { tag: 504 }),
+                (0, Finding::UnpairedColour { tag: 505 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_status_and_process() {
+        let file = EVENT
+            .replace("4 1 1.0 91.2", "4 7 1.0 91.2")
+            .replace("21 1 1 2 504 503", "21 4 1 2 504 503");
+        let found = findings(&file);
+        assert_eq!(found[0], (0, Finding::UnknownProcessId { process_id: 7 }));
+        assert!(found.contains(&(
+            0,
+            Finding::UnknownStatus {
+                particle: 3,
+                status: 4,
+            }
+        )));
+    }
+}
@@ -0,0 +1,654 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Versions of the lhe standard
+//!
+//! The version of an lhe file is given in the opening tag of the file
+//! and stored as a [`LhefVersion`].
+//! Version 2.0 of the standard introduced the `<generator>`,
+//! `<xsecinfo>`, `<cutsinfo>`, `<procinfo>`, `<mergeinfo>`,
+//! `<weightinfo>`, `<eventgroup>`, `<weight>`, `<clustering>`,
+//! `<pdfinfo>` and `<scales>` tags, and version 3.0 added the
+//! `<initrwgt>`, `<weightgroup>`, `<rwgt>`, `<wgt>` and `<weights>`
+//! tags.
+//! All other tags are allowed in every version, so that the custom tags
+//! of specific programs are not affected.
+//!
+//! The usual parsers accept the tags of all versions.
+//! The [`read_lhe`] and [`read_lhe_from_file`] functions of this module
+//! additionally check that the file only contains tags that are allowed
+//! in its version.
+//! To write a file for an older version, the tags that are not allowed
+//! in that version can be removed or turned into comments with
+//! `LheFileGeneric::downgrade`.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::version::{self, DowngradeMode, LhefVersion};
+//! use lhef::lhef3::LheFile;
+//!
+//! let mut lhe: LheFile = version::read_lhe_from_file(&"events.lhe").unwrap();
+//! lhe.downgrade(LhefVersion::V1, DowngradeMode::Comment);
+//! ```
+//!
+//! [`LhefVersion`]: enum.LhefVersion.html
+//! [`read_lhe`]: fn.read_lhe.html
+//! [`read_lhe_from_file`]: fn.read_lhe_from_file.html
+
+use {ReadError, ReadLhe, WriteLhe};
+use attributes::read_element;
+use compression;
+use error::{ErrorLocator, Section};
+use generic::{CommentPosition, LheFileGeneric};
+
+use std::cmp;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+/// The tags introduced in version 2.0 of the lhe standard
+const VERSION_2_TAGS: &[&str] = &[
+    "generator",
+    "xsecinfo",
+    "cutsinfo",
+    "procinfo",
+    "mergeinfo",
+    "weightinfo",
+    "eventgroup",
+    "weight",
+    "clustering",
+    "pdfinfo",
+    "scales",
+];
+
+/// The tags introduced in version 3.0 of the lhe standard
+const VERSION_3_TAGS: &[&str] = &["initrwgt", "weightgroup", "rwgt", "wgt", "weights"];
+
+/// The version of the lhe standard that a file follows
+///
+/// The versions 1.0, 2.0 and 3.0 are known, any other version is kept
+/// as it is given in the file.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::version::LhefVersion;
+///
+/// assert_eq!(LhefVersion::from("3.0"), LhefVersion::V3);
+/// assert_eq!(LhefVersion::V1.as_str(), "1.0");
+/// assert_eq!(LhefVersion::from("3"), LhefVersion::Other("3".to_string()));
+///
+/// assert!(LhefVersion::V3.allows_tag("rwgt"));
+/// assert!(!LhefVersion::V2.allows_tag("rwgt"));
+/// assert!(LhefVersion::V1.allows_tag("mgrwt"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(test, derive(Serialize, Deserialize))]
+#[cfg_attr(test, serde(from = "String", into = "String"))]
+pub enum LhefVersion {
+    /// Version 1.0
+    V1,
+    /// Version 2.0
+    V2,
+    /// Version 3.0
+    V3,
+    /// Any other version, as given in the file
+    Other(String),
+}
+
+impl LhefVersion {
+    /// The version as it is written in the opening tag of a file
+    pub fn as_str(&self) -> &str {
+        match *self {
+            LhefVersion::V1 => "1.0",
+            LhefVersion::V2 => "2.0",
+            LhefVersion::V3 => "3.0",
+            LhefVersion::Other(ref version) => version,
+        }
+    }
+
+    /// Check whether the tag `name` is allowed in files of this version
+    ///
+    /// All tags are allowed in unknown versions.
+    pub fn allows_tag(&self, name: &str) -> bool {
+        let required = if VERSION_3_TAGS.contains(&name) {
+            3
+        } else if VERSION_2_TAGS.contains(&name) {
+            2
+        } else {
+            1
+        };
+        match self.number() {
+            Some(number) => required <= number,
+            None => true,
+        }
+    }
+
+    fn number(&self) -> Option<u8> {
+        match *self {
+            LhefVersion::V1 => Some(1),
+            LhefVersion::V2 => Some(2),
+            LhefVersion::V3 => Some(3),
+            LhefVersion::Other(_) => None,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for LhefVersion {
+    fn from(version: &'a str) -> LhefVersion {
+        match version {
+            "1.0" => LhefVersion::V1,
+            "2.0" => LhefVersion::V2,
+            "3.0" => LhefVersion::V3,
+            _ => LhefVersion::Other(version.to_string()),
+        }
+    }
+}
+
+impl From<String> for LhefVersion {
+    fn from(version: String) -> LhefVersion {
+        match LhefVersion::from(version.as_str()) {
+            LhefVersion::Other(_) => LhefVersion::Other(version),
+            known => known,
+        }
+    }
+}
+
+impl From<LhefVersion> for String {
+    fn from(version: LhefVersion) -> String {
+        match version {
+            LhefVersion::Other(version) => version,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for LhefVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// What to do with tags that are not allowed in the target version when
+/// downgrading
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DowngradeMode {
+    /// Remove the tags
+    Strip,
+    /// Keep the tags in comments (`<!-- ... -->`)
+    Comment,
+}
+
+/// A trait for the additional information of lhe files that may contain
+/// tags of newer versions of the lhe standard
+///
+/// The default implementation does nothing, which is right for types
+/// that never contain such tags.
+pub trait Downgrade {
+    /// Remove the tags that are not allowed in `version`, or turn them
+    /// into comments
+    fn downgrade(&mut self, _version: &LhefVersion, _mode: DowngradeMode) {}
+}
+
+impl<Comment, Header, InitExtra, EventExtra> LheFileGeneric<Comment, Header, InitExtra, EventExtra>
+where
+    Comment: Downgrade,
+    Header: Downgrade,
+    InitExtra: Downgrade,
+    EventExtra: Downgrade,
+{
+    /// Convert the file to the version `version`
+    ///
+    /// All tags that are not allowed in `version` are removed or turned
+    /// into comments, depending on `mode`.
+    /// Event groups are dissolved if they are not allowed, keeping their
+    /// events.
+    /// Nothing is removed if the file doesn't contain any tags of newer
+    /// versions, in which case only the version is changed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use lhef::{ReadLhe, WriteLhe};
+    /// use lhef::lhef3::LheFile;
+    /// use lhef::version::{DowngradeMode, LhefVersion};
+    ///
+    /// let bytes = b"\
+    /// <LesHouchesEvents version=\"3.0\">
+    /// <init>
+    /// 2212 2212 6500 6500 0 0 13100 13100 3 1
+    /// 2.1 3.2E-03 1.0E+00 1
+    /// </init>
+    /// <event>
+    /// 2 1 1.0 91.2 0.0078 0.118
+    /// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+    /// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+    /// <rwgt>
+    /// <wgt id=\"1\"> 2.0 </wgt>
+    /// </rwgt>
+    /// <scales muf=\"45.6\"/>
+    /// </event>
+    /// </LesHouchesEvents>";
+    ///
+    /// let mut lhe = LheFile::read_lhe(bytes).to_full_result().unwrap();
+    /// lhe.downgrade(LhefVersion::V2, DowngradeMode::Strip);
+    /// assert_eq!(lhe.version, LhefVersion::V2);
+    /// assert_eq!(lhe.events[0].extra.rwgt, None);
+    /// assert_eq!(lhe.events[0].extra.scales.as_ref().unwrap().muf, Some(45.6));
+    ///
+    /// lhe.downgrade(LhefVersion::V1, DowngradeMode::Comment);
+    /// assert_eq!(lhe.events[0].extra.scales, None);
+    /// assert!(lhe.events[0].extra.extra.starts_with("<!--"));
+    ///
+    /// let mut written = Vec::new();
+    /// lhe.write_lhe(&mut written).unwrap();
+    /// ```
+    pub fn downgrade(&mut self, version: LhefVersion, mode: DowngradeMode) {
+        self.comment.downgrade(&version, mode);
+        self.header.downgrade(&version, mode);
+        self.init.extra.downgrade(&version, mode);
+        for event in &mut self.events {
+            event.extra.downgrade(&version, mode);
+        }
+        if !version.allows_tag("eventgroup") {
+            // Comments inside of a group are kept before the same event
+            for comment in &mut self.comments {
+                if let CommentPosition::InGroup(index, n) = comment.position {
+                    let end = self.groups.get(index).map_or(n, |group| group.events.end);
+                    comment.position = CommentPosition::BeforeEvent(cmp::min(n, end));
+                }
+            }
+            self.groups.clear();
+        }
+        self.version = version;
+    }
+}
+
+/// Read a complete lhe file, checking that it only contains tags that
+/// are allowed in its version
+///
+/// Apart from the check, this is the same as
+/// `LheFileGeneric::read_lhe_from_bytes`.
+/// A tag that is not allowed is reported as a parse error at its
+/// position.
+/// Tags in comments are not checked.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::ReadError;
+/// use lhef::error::Section;
+/// use lhef::plain::LheFile;
+/// use lhef::version;
+///
+/// let bytes = b"\
+/// <LesHouchesEvents version=\"1.0\">
+/// <init>
+/// 2212 2212 6500 6500 0 0 13100 13100 3 1
+/// 2.1 3.2E-03 1.0E+00 1
+/// </init>
+/// <event>
+/// 2 1 1.0 91.2 0.0078 0.118
+/// 21 -1 0 0 501 502 0.0 0.0 1.0 1.0 0.0 0. 9.
+/// 21 -1 0 0 502 501 0.0 0.0 -1.0 1.0 0.0 0. 9.
+/// <scales muf=\"45.6\"/>
+/// </event>
+/// </LesHouchesEvents>";
+///
+/// let lhe: Result<LheFile, _> = version::read_lhe(bytes);
+/// match lhe {
+///     Err(ReadError::Parse(err)) => {
+///         assert_eq!(err.line, 10);
+///         assert_eq!(err.section, Section::EventExtra(0));
+///         assert_eq!(err.found, "<scales muf=\"45.6\"/>");
+///     }
+///     _ => panic!("Expected a parse error"),
+/// }
+/// ```
+pub fn read_lhe<Comment, Header, InitExtra, EventExtra>(
+    input: &[u8],
+) -> Result<LheFileGeneric<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    Comment: ReadLhe + PartialEq,
+    Header: ReadLhe + PartialEq,
+    InitExtra: ReadLhe + PartialEq,
+    EventExtra: ReadLhe,
+{
+    let lhe = LheFileGeneric::read_lhe_from_bytes(input)?;
+    check_tags(input, &lhe.version)?;
+    Ok(lhe)
+}
+
+/// Read a complete lhe file from the file at `path`, checking that it
+/// only contains tags that are allowed in its version
+///
+/// Compressed files are decompressed on the fly, see the
+/// [`compression`] module.
+///
+/// [`compression`]: ../compression/index.html
+pub fn read_lhe_from_file<P, Comment, Header, InitExtra, EventExtra>(
+    path: &P,
+) -> Result<LheFileGeneric<Comment, Header, InitExtra, EventExtra>, ReadError>
+where
+    P: AsRef<Path>,
+    Comment: ReadLhe + PartialEq,
+    Header: ReadLhe + PartialEq,
+    InitExtra: ReadLhe + PartialEq,
+    EventExtra: ReadLhe,
+{
+    let mut file = compression::open(path)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    read_lhe(&contents)
+}
+
+/// Check that all tags in the file `input` are allowed in `version`
+fn check_tags(input: &[u8], version: &LhefVersion) -> Result<(), ReadError> {
+    let mut section = Section::File;
+    let mut events = 0;
+    for (position, name) in find_tags(input) {
+        match name {
+            b"header" => section = Section::Header,
+            b"init" => section = Section::InitExtra,
+            b"event" => {
+                section = Section::EventExtra(events);
+                events += 1;
+            }
+            b"/header" | b"/init" | b"/event" => section = Section::File,
+            _ => {}
+        }
+        let name = String::from_utf8_lossy(name);
+        if !version.allows_tag(&name) {
+            let mut locator = ErrorLocator::new(input);
+            locator.advance(position);
+            locator.set_section(section);
+            let expected = format!("a tag allowed in version {} files", version);
+            return Err(ReadError::Parse(locator.error(None, &expected)));
+        }
+    }
+    Ok(())
+}
+
+/// Find the positions and names of all tags in `input`, including
+/// closing tags, whose names start with a `/`
+///
+/// Comments, CDATA sections and processing instructions are skipped.
+fn find_tags(input: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut tags = Vec::new();
+    let mut position = 0;
+    while let Some(start) = input[position..].iter().position(|c| *c == b'<') {
+        let start = position + start;
+        let rest = &input[start..];
+        let skip = [
+            (&b"<!--"[..], &b"-->"[..]),
+            (b"<![CDATA[", b"]]>"),
+            (b"<?", b"?>"),
+        ];
+        if let Some(&(_, end)) = skip.iter().find(|&&(open, _)| rest.starts_with(open)) {
+            match find(rest, end) {
+                Some(n) => position = start + n + end.len(),
+                None => break,
+            }
+            continue;
+        }
+        let length = rest[1..]
+            .iter()
+            .take_while(|c| c.is_ascii_alphanumeric() || b"/_-:.".contains(c))
+            .count();
+        if length > 0 {
+            tags.push((start, &rest[1..=length]));
+        }
+        position = start + 1 + length;
+    }
+    tags
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Remove the elements that are not allowed in `version` from `text`,
+/// or turn them into comments
+///
+/// Elements that are removed take the rest of their line with them if
+/// the line contains nothing else.
+pub(crate) fn downgrade_text(text: &str, version: &LhefVersion, mode: DowngradeMode) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut position = 0;
+    for (start, name) in find_tags(text.as_bytes()) {
+        if start < position {
+            continue;
+        }
+        let name = match String::from_utf8(name.to_vec()) {
+            Ok(ref name) if !version.allows_tag(name) => name.clone(),
+            _ => continue,
+        };
+        let length = match read_element(&text[start..], &name) {
+            Some((_, length)) => length,
+            None => continue,
+        };
+        let end = start + length;
+        match mode {
+            DowngradeMode::Strip => {
+                let line_start = text[..start].rfind('\n').map_or(0, |n| n + 1);
+                let line_end = text[end..].find('\n').map_or(text.len(), |n| end + n + 1);
+                let alone = text[line_start.max(position)..start].trim().is_empty()
+                    && text[end..line_end].trim().is_empty();
+                if alone && line_start >= position {
+                    result.push_str(&text[position..line_start]);
+                    position = line_end;
+                } else {
+                    result.push_str(&text[position..start]);
+                    position = end;
+                }
+            }
+            DowngradeMode::Comment => {
+                result.push_str(&text[position..start]);
+                result.push_str(&comment(&text[start..end]));
+                position = end;
+            }
+        }
+    }
+    result.push_str(&text[position..]);
+    if mode == DowngradeMode::Strip {
+        result.trim().to_string()
+    } else {
+        result
+    }
+}
+
+/// Append the tags in `removed` to `text` as a comment
+pub(crate) fn comment_out(text: &mut String, removed: &[u8]) {
+    if removed.is_empty() {
+        return;
+    }
+    let removed = String::from_utf8_lossy(removed);
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(&comment(removed.trim()));
+}
+
+/// Write `value` to `removed`
+pub(crate) fn write_removed<T: WriteLhe>(value: &T, removed: &mut Vec<u8>) {
+    value
+        .write_lhe(removed)
+        .expect("BUG: Writing to a Vec failed");
+}
+
+fn comment(text: &str) -> String {
+    format!("<!--\n{}\n-->", text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{downgrade_text, read_lhe, DowngradeMode, LhefVersion};
+
+    use error::Section;
+    use generic::CommentPosition;
+    use lhef3;
+    use plain;
+    use string;
+    use ReadError;
+    use ReadLhe;
+    use WriteLhe;
+
+    use std::fs;
+
+    const SAMPLE_FILES: &[&str] = &[
+        "tests/real_world_files/helac_1loop_tree.lhe",
+        "tests/real_world_files/helac_dipoles_rs.lhe",
+        "tests/real_world_files/mg5_aMC.lhe",
+        "tests/real_world_files/mg5_aMC_NLO.lhe",
+        "tests/real_world_files/mg5_aMC_NLO_rwgt.lhe",
+    ];
+
+    #[test]
+    fn version_strings() {
+        for version in &["1.0", "2.0", "3.0", "3", "1.0 beta", ""] {
+            let parsed = LhefVersion::from(*version);
+            assert_eq!(parsed.as_str(), *version);
+            assert_eq!(String::from(parsed), version.to_string());
+        }
+        assert_eq!(LhefVersion::from("2.0".to_string()), LhefVersion::V2);
+    }
+
+    #[test]
+    fn sample_files_follow_their_version() {
+        for file in SAMPLE_FILES {
+            let bytes = fs::read(file).unwrap();
+            let lhe: string::LheFile = read_lhe(&bytes).unwrap();
+            assert_eq!(
+                lhe,
+                string::LheFile::read_lhe(&bytes).to_full_result().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn tag_of_newer_version() {
+        let bytes = fs::read("tests/real_world_files/mg5_aMC_NLO_rwgt.lhe").unwrap();
+        let bytes =
+            String::from_utf8(bytes)
+                .unwrap()
+                .replacen("version=\"3.0\"", "version=\"2.0\"", 1);
+        let err = match read_lhe::<plain::Comment, plain::Header, plain::InitExtra, plain::EventExtra>(
+            bytes.as_bytes(),
+        ) {
+            Err(ReadError::Parse(err)) => err,
+            _ => panic!("Expected a parse error"),
+        };
+        assert_eq!(err.section, Section::Header);
+        assert!(err.found.starts_with("<initrwgt>"));
+        assert_eq!(err.expected, "a tag allowed in version 2.0 files");
+    }
+
+    #[test]
+    fn downgrade_text_strip_and_comment() {
+        let text = "\
+# info
+<rwgt>
+<wgt id=\"1\"> 2.0 </wgt>
+</rwgt>
+<mgrwt> <scales muf=\"1\"/> </mgrwt>
+<!-- <weights> 1 </weights> -->";
+        assert_eq!(
+            downgrade_text(text, &LhefVersion::V1, DowngradeMode::Strip),
+            "# info\n<mgrwt>  </mgrwt>\n<!-- <weights> 1 </weights> -->"
+        );
+        assert_eq!(
+            downgrade_text(text, &LhefVersion::V2, DowngradeMode::Comment),
+            "\
+# info
+<!--
+<rwgt>
+<wgt id=\"1\"> 2.0 </wgt>
+</rwgt>
+-->
+<mgrwt> <scales muf=\"1\"/> </mgrwt>
+<!-- <weights> 1 </weights> -->"
+        );
+        assert_eq!(
+            downgrade_text(text, &LhefVersion::V3, DowngradeMode::Strip),
+            text
+        );
+    }
+
+    #[test]
+    fn downgrade_to_version_1() {
+        let files = [
+            "tests/real_world_files/mg5_aMC.lhe",
+            "tests/real_world_files/mg5_aMC_NLO_rwgt.lhe",
+        ];
+        for file in &files {
+            for &mode in &[DowngradeMode::Strip, DowngradeMode::Comment] {
+                let mut lhe = lhef3::LheFile::read_lhe_from_file(file).unwrap();
+                let n_events = lhe.events.len();
+                lhe.downgrade(LhefVersion::V1, mode);
+                let mut written = Vec::new();
+                lhe.write_lhe(&mut written).unwrap();
+                let round: string::LheFile = read_lhe(&written).unwrap();
+                assert_eq!(round.version, LhefVersion::V1);
+                assert_eq!(round.events.len(), n_events);
+
+                let round = lhef3::LheFile::read_lhe(&written).to_full_result().unwrap();
+                assert_eq!(round.header.initrwgt, None);
+                assert!(round.events.iter().all(|e| e.extra.rwgt.is_none()));
+                let header = round.header.header.unwrap();
+                assert_eq!(
+                    header.contains("<weightgroup"),
+                    mode == DowngradeMode::Comment && file.ends_with("rwgt.lhe")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn downgrade_comments_in_groups() {
+        let bytes = b"\
+<LesHouchesEvents version=\"3.0\">
+<init>
+2212 2212 6500 6500 0 0 13100 13100 3 1
+2.1 3.2E-03 1.0E+00 1
+</init>
+<eventgroup>
+<!-- first -->
+<event>
+0 1 1.0 91.2 0.0078 0.118
+</event>
+<event>
+0 1 -1.0 91.2 0.0078 0.118
+</event>
+<!-- last -->
+</eventgroup>
+<event>
+0 1 2.0 91.2 0.0078 0.118
+</event>
+</LesHouchesEvents>
+";
+        let mut lhe = string::LheFile::read_lhe(bytes).to_full_result().unwrap();
+        lhe.downgrade(LhefVersion::V1, DowngradeMode::Strip);
+        let positions: Vec<_> = lhe.comments.iter().map(|c| c.position).collect();
+        assert_eq!(
+            positions,
+            vec![CommentPosition::BeforeEvent(0), CommentPosition::BeforeEvent(2)]
+        );
+
+        let mut written = Vec::new();
+        lhe.write_lhe(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let first = written.find("first").unwrap();
+        let last = written.find("last").unwrap();
+        let events: Vec<_> = written.match_indices("<event>").map(|(n, _)| n).collect();
+        assert!(first < events[0]);
+        assert!(events[1] < last && last < events[2]);
+    }
+}
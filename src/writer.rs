@@ -0,0 +1,344 @@
+// Copyright 2018 Torsten Weber
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Write events to lhe files one at a time
+//!
+//! Writing a file with `WriteLhe::write_lhe` requires all events to be
+//! stored in an `LheFileGeneric` first.
+//! The [`LheWriter`] in this module writes the start of the file, i.e.
+//! the comment, the header and the init section, when it is created and
+//! then accepts the events one by one.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use lhef::version::LhefVersion;
+//! use lhef::writer::LheWriter;
+//!
+//! let mut writer = LheWriter::create(
+//!     &"events.lhe",
+//!     &LhefVersion::V1,
+//!     &attributes,
+//!     &comment,
+//!     &header,
+//!     &init,
+//! ).unwrap();
+//! for event in generate_events() {
+//!     writer.write_event(&event).unwrap();
+//! }
+//! writer.finish().unwrap();
+//! ```
+//!
+//! [`LheWriter`]: struct.LheWriter.html
+
+use attributes::Attributes;
+use compression;
+use generic::{write_closing_file_tag, write_opening_file_tag, EventGeneric, EventGroup,
+              EventItem, InitGeneric};
+use version::LhefVersion;
+use WriteLhe;
+
+use std::io;
+use std::marker;
+use std::path::Path;
+
+/// A writer for the events in an lhe file
+///
+/// The opening tag, the comment, the header and the init section are
+/// written when the writer is created, so it is not possible to write
+/// any event before the init section.
+/// The closing `</LesHouchesEvents>` tag is written by `finish`, or
+/// when the writer is dropped.
+/// Since errors can not be reported when the writer is dropped,
+/// calling `finish` is preferable.
+///
+/// # Examples
+///
+/// ```rust
+/// use lhef::{Particle, ReadLhe};
+/// use lhef::attributes::Attributes;
+/// use lhef::generic::{EventGeneric, InitGeneric};
+/// use lhef::string::{LheFile, Comment, Header, InitExtra, EventExtra};
+/// use lhef::version::LhefVersion;
+/// use lhef::writer::LheWriter;
+///
+/// let comment = Comment { comment: Some("Written event by event".to_string()) };
+/// let header = Header { header: None };
+/// let init = InitGeneric {
+///     beam_1_id: 2212,
+///     beam_2_id: 2212,
+///     beam_1_energy: 6500.,
+///     beam_2_energy: 6500.,
+///     beam_1_pdf_group_id: 0,
+///     beam_2_pdf_group_id: 0,
+///     beam_1_pdf_id: 13100,
+///     beam_2_pdf_id: 13100,
+///     weighting_strategy: 3,
+///     process_info: vec![],
+///     extra: InitExtra(String::new()),
+///     attributes: Attributes::new(),
+/// };
+/// let event = EventGeneric {
+///     process_id: 1,
+///     weight: 1.,
+///     scale: 91.2,
+///     alpha_ew: 0.0078,
+///     alpha_qcd: 0.118,
+///     particles: vec![],
+///     extra: EventExtra("# event information".to_string()),
+///     attributes: Attributes::new(),
+/// };
+///
+/// let mut writer =
+///     LheWriter::new(Vec::new(), &LhefVersion::V1, &Attributes::new(), &comment, &header, &init).unwrap();
+/// writer.write_event(&event).unwrap();
+/// writer.write_event(&event).unwrap();
+/// let bytes = writer.finish().unwrap();
+///
+/// let lhe = LheFile::read_lhe(&bytes).to_full_result().unwrap();
+/// assert_eq!(lhe.comment, comment);
+/// assert_eq!(lhe.events.len(), 2);
+/// assert_eq!(lhe.events[1], event);
+/// ```
+#[derive(Debug)]
+pub struct LheWriter<W: io::Write, EventExtra> {
+    writer: Option<W>,
+    event_extra: marker::PhantomData<EventExtra>,
+}
+
+impl<W, EventExtra> LheWriter<W, EventExtra>
+where
+    W: io::Write,
+    EventExtra: WriteLhe,
+{
+    /// Create a new writer
+    ///
+    /// This writes the opening tag of the file, the comment, the header
+    /// and the init section to `writer`.
+    pub fn new<Comment, Header, InitExtra>(
+        mut writer: W,
+        version: &LhefVersion,
+        attributes: &Attributes,
+        comment: &Comment,
+        header: &Header,
+        init: &InitGeneric<InitExtra>,
+    ) -> io::Result<LheWriter<W, EventExtra>>
+    where
+        Comment: WriteLhe,
+        Header: WriteLhe,
+        InitExtra: WriteLhe,
+    {
+        write_opening_file_tag(&mut writer, version.as_str(), attributes)?;
+        comment.write_lhe(&mut writer)?;
+        header.write_lhe(&mut writer)?;
+        init.write_lhe(&mut writer)?;
+        Ok(LheWriter {
+            writer: Some(writer),
+            event_extra: marker::PhantomData,
+        })
+    }
+
+    /// Write a single event
+    pub fn write_event(&mut self, event: &EventGeneric<EventExtra>) -> io::Result<()> {
+        event.write_lhe(self.writer())
+    }
+
+    /// Write an `<eventgroup>` with all of its events
+    pub fn write_group(&mut self, group: &EventGroup<EventExtra>) -> io::Result<()> {
+        group.write_lhe(self.writer())
+    }
+
+    /// Write a single event or an event group
+    pub fn write_item(&mut self, item: &EventItem<EventExtra>) -> io::Result<()> {
+        item.write_lhe(self.writer())
+    }
+
+    /// Write a comment between the events
+    ///
+    /// The comment is read as a `FileComment` by
+    /// `LheFileGeneric::read_lhe`.
+    pub fn write_comment<Comment: WriteLhe>(&mut self, comment: &Comment) -> io::Result<()> {
+        comment.write_lhe(self.writer())
+    }
+
+    /// Write the closing tag of the file
+    ///
+    /// This returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut writer = self
+            .writer
+            .take()
+            .expect("BUG: LheWriter used after finish");
+        write_closing_file_tag(&mut writer)?;
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    fn writer(&mut self) -> &mut W {
+        self.writer
+            .as_mut()
+            .expect("BUG: LheWriter used after finish")
+    }
+}
+
+impl<EventExtra> LheWriter<compression::Writer, EventExtra>
+where
+    EventExtra: WriteLhe,
+{
+    /// Create a new writer for the file at `path`
+    ///
+    /// The file is created if it doesn't exist and truncated otherwise.
+    /// If the extension of `path` belongs to a compression format, the
+    /// file is compressed, see the [`compression`] module.
+    /// In that case `finish` should be called on the writer returned by
+    /// `LheWriter::finish` to complete the compressed stream.
+    ///
+    /// [`compression`]: ../compression/index.html
+    pub fn create<P, Comment, Header, InitExtra>(
+        path: &P,
+        version: &LhefVersion,
+        attributes: &Attributes,
+        comment: &Comment,
+        header: &Header,
+        init: &InitGeneric<InitExtra>,
+    ) -> io::Result<LheWriter<compression::Writer, EventExtra>>
+    where
+        P: AsRef<Path>,
+        Comment: WriteLhe,
+        Header: WriteLhe,
+        InitExtra: WriteLhe,
+    {
+        LheWriter::new(
+            compression::create(path)?,
+            version,
+            attributes,
+            comment,
+            header,
+            init,
+        )
+    }
+}
+
+impl<W: io::Write, EventExtra> Drop for LheWriter<W, EventExtra> {
+    fn drop(&mut self) {
+        if let Some(ref mut writer) = self.writer {
+            let _ = write_closing_file_tag(writer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use attributes::Attributes;
+    use generic::EventGroupRange;
+    use helac;
+    use string;
+    use ReadLhe;
+    use WriteLhe;
+
+    use super::LheWriter;
+
+    #[test]
+    fn write_like_lhefile() {
+        let lhe =
+            string::LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let mut expected = Vec::new();
+        lhe.write_lhe(&mut expected).unwrap();
+
+        let mut writer = LheWriter::new(
+            Vec::new(),
+            &lhe.version,
+            &lhe.attributes,
+            &lhe.comment,
+            &lhe.header,
+            &lhe.init,
+        )
+        .unwrap();
+        for event in &lhe.events {
+            writer.write_event(event).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn roundtrip_rs() {
+        let lhe =
+            helac::LheFileRS::read_lhe_from_file(&"tests/real_world_files/helac_dipoles_rs.lhe")
+                .unwrap();
+        let mut writer = LheWriter::new(
+            Vec::new(),
+            &lhe.version,
+            &lhe.attributes,
+            &lhe.comment,
+            &lhe.header,
+            &lhe.init,
+        )
+        .unwrap();
+        for event in &lhe.events {
+            writer.write_event(event).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let round = helac::LheFileRS::read_lhe(&bytes).to_full_result().unwrap();
+        assert_eq!(lhe, round);
+    }
+
+    #[test]
+    fn closing_tag_on_drop() {
+        let lhe =
+            string::LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let mut bytes = Vec::new();
+        {
+            let mut writer = LheWriter::new(
+                &mut bytes,
+                &lhe.version,
+                &lhe.attributes,
+                &lhe.comment,
+                &lhe.header,
+                &lhe.init,
+            )
+            .unwrap();
+            writer.write_event(&lhe.events[0]).unwrap();
+        }
+        let round = string::LheFile::read_lhe(&bytes).to_full_result().unwrap();
+        assert_eq!(round.events.len(), 1);
+        assert_eq!(round.events[0], lhe.events[0]);
+    }
+
+    #[test]
+    fn write_items() {
+        let mut lhe =
+            string::LheFile::read_lhe_from_file(&"tests/real_world_files/mg5_aMC.lhe").unwrap();
+        let mut attributes = Attributes::new();
+        attributes.insert("nreal", "1");
+        attributes.insert("ncounter", "1");
+        lhe.groups.push(EventGroupRange {
+            attributes,
+            events: 1..3,
+        });
+        let mut expected = Vec::new();
+        lhe.write_lhe(&mut expected).unwrap();
+
+        let mut writer = LheWriter::new(
+            Vec::new(),
+            &lhe.version,
+            &lhe.attributes,
+            &lhe.comment,
+            &lhe.header,
+            &lhe.init,
+        )
+        .unwrap();
+        for item in lhe.clone().into_items().unwrap() {
+            writer.write_item(&item).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        assert_eq!(bytes, expected);
+        let round = string::LheFile::read_lhe(&bytes).to_full_result().unwrap();
+        assert_eq!(round, lhe);
+    }
+}